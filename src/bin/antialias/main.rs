@@ -1,26 +1,39 @@
+use std::sync::Arc;
+
 use vec3::*;
 
-use crate::common::{camera::Camera, hittable::HittableList, sphere::Sphere};
+use crate::common::{
+    camera::Camera, hittable::HittableList, integrator::Normals, material::Lambertian,
+    scene::Scene, sphere::Sphere,
+};
 #[path ="../../common/mod.rs"]
-mod common;
+pub mod common;
 
 fn main() {
     // World
+    let material_ground = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+    let material_center = Arc::new(Lambertian::new(Color::new(0.7, 0.3, 0.3)));
+
     let mut world = HittableList::new();
     world.objects.push(Box::new(Sphere::new(
         Point3::new(0.0, 0.0, -1.0),
         0.5,
+        material_center,
     )));
     world.objects.push(Box::new(Sphere::new(
         Point3::new(0.0, -100.5, -1.0),
         100.0,
+        material_ground,
     )));
 
     // Camera
     let aspect_ratio: f64 = 16.0/ 9.0;
     let image_width: i64 = 400;
     let samples_per_pixel: i64 = 100;
-    let mut cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
 
-    cam.render(&world);
+    // Shaded by surface normal, so the materials only matter to other
+    // integrators
+    let mut scene = Scene::new(cam, world);
+    scene.render(&Normals);
 }
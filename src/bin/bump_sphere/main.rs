@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    bump::{BumpMapped, NormalMapped},
+    camera::Camera,
+    hittable::HittableList,
+    material::Lambertian,
+    path::PathTracer,
+    perlin::Perlin,
+    scene::Scene,
+    sphere::Sphere,
+    texture::{NoiseTexture, Texture},
+    util::seeded_rng,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+/// A tangent-space normal map of round dimples laid out on a (u, v) grid,
+/// built procedurally so the scene needs no image files.
+struct Dimples {
+    cells_u: f64,
+    cells_v: f64,
+}
+
+impl Texture for Dimples {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        // Position within the cell, from -1 to 1 on each axis
+        let s = 2.0 * (u * self.cells_u).fract() - 1.0;
+        let t = 2.0 * (v * self.cells_v).fract() - 1.0;
+        let r2 = s * s + t * t;
+        let n = if r2 < 0.64 {
+            // The inside of a spherical cap, so the normal leans to the center
+            Vec3::new(-s, -t, (1.0 - r2).sqrt()).unit_vector()
+        } else {
+            Vec3::new(0.0, 0.0, 1.0)
+        };
+        (n + Vec3::from_float(1.0)) * 0.5
+    }
+}
+
+fn main() {
+    let mut rng = seeded_rng(7);
+
+    // World
+    let material_ground = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let material_bumpy = Arc::new(BumpMapped::new(
+        Arc::new(Lambertian::new(Color::new(0.8, 0.4, 0.3))),
+        Arc::new(NoiseTexture::new(Perlin::new(&mut rng), 8.0)),
+        0.05,
+    ));
+    let material_dimpled = Arc::new(NormalMapped::new(
+        Arc::new(Lambertian::new(Color::new(0.3, 0.5, 0.8))),
+        Arc::new(Dimples {
+            cells_u: 24.0,
+            cells_v: 12.0,
+        }),
+        1.0,
+    ));
+
+    let mut world = HittableList::new();
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        material_ground,
+    )));
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(-0.6, 0.0, -2.0),
+        0.5,
+        material_bumpy,
+    )));
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.6, 0.0, -2.0),
+        0.5,
+        material_dimpled,
+    )));
+
+    // Camera
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 100;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+
+    let mut scene = Scene::new(cam, world);
+    scene.srgb = true;
+    scene.render(&PathTracer::new(50));
+}
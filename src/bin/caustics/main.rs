@@ -0,0 +1,82 @@
+use std::env;
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    background::Solid,
+    bdpt::Bidirectional,
+    camera::Camera,
+    hittable::HittableList,
+    integrator::Integrator,
+    material::{DiffuseLight, Lambertian},
+    path::PathTracer,
+    photon::ProgressivePhotonMapping,
+    quad::Quad,
+    scene::Scene,
+    specular::{Dielectric, Metal},
+    sphere::Sphere,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+fn main() {
+    // Pass --path to render with the unidirectional path tracer instead, or
+    // --photons for progressive photon mapping
+    let integrator: Box<dyn Integrator> = if env::args().any(|arg| arg == "--path") {
+        Box::new(PathTracer::new(8))
+    } else if env::args().any(|arg| arg == "--photons") {
+        Box::new(ProgressivePhotonMapping::new(8, 100_000, 0.05))
+    } else {
+        Box::new(Bidirectional::new(8))
+    };
+
+    // World: a box lit by a small lamp in the ceiling, with a glass ball
+    // focusing it onto the floor and a mirror ball throwing it on the wall
+    let white = Arc::new(Lambertian::new(Color::from_float(0.73)));
+    let red = Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let green = Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+    let lamp = Arc::new(DiffuseLight::new(Color::from_float(60.0)));
+
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    let walls = [
+        // Floor, ceiling and back
+        (Point3::new(-1.5, -1.0, 0.5), Vec3::new(3.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -4.0), white.clone()),
+        (Point3::new(-1.5, 1.0, -3.5), Vec3::new(3.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 4.0), white.clone()),
+        (Point3::new(-1.5, -1.0, -3.5), Vec3::new(3.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), white),
+        // Left and right
+        (Point3::new(-1.5, -1.0, 0.5), Vec3::new(0.0, 0.0, -4.0), Vec3::new(0.0, 2.0, 0.0), red),
+        (Point3::new(1.5, -1.0, -3.5), Vec3::new(0.0, 0.0, 4.0), Vec3::new(0.0, 2.0, 0.0), green),
+    ];
+    for (q, u, v, mat) in walls {
+        world.objects.push(Box::new(Quad::new(q, u, v, mat)));
+    }
+    // Facing down, just under the ceiling
+    let (q, u, v) = (Point3::new(-0.15, 0.999, -2.15), Vec3::new(0.3, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.3));
+    world.objects.push(Box::new(Quad::new(q, u, v, lamp.clone())));
+    lights.objects.push(Box::new(Quad::new(q, u, v, lamp)));
+
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(-0.5, -0.55, -2.2),
+        0.45,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.7, -0.6, -2.7),
+        0.4,
+        Arc::new(Metal::new(Color::from_float(0.9), 0.0)),
+    )));
+
+    // Camera, looking in through the open front
+    let aspect_ratio: f64 = 1.0;
+    let image_width: i64 = 300;
+    let samples_per_pixel: i64 = 64;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+    let mut scene = Scene::new(cam, world);
+    scene.srgb = true;
+    scene.lights = lights;
+    scene.background = Arc::new(Solid(Color::zero()));
+
+    scene.render(integrator.as_ref());
+}
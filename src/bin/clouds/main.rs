@@ -0,0 +1,67 @@
+use std::env;
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    camera::Camera, grid_volume::{GridMedium, VoxelGrid}, hittable::HittableList,
+    material::Lambertian, path::PathTracer, phase::HenyeyGreenstein, scene::Scene,
+    sphere::Sphere,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+/// A lumpy cloud built from a few overlapping soft blobs.
+fn procedural_cloud() -> VoxelGrid {
+    let blobs = [
+        (Point3::new(0.35, 0.4, 0.5), 0.25),
+        (Point3::new(0.6, 0.45, 0.45), 0.3),
+        (Point3::new(0.5, 0.6, 0.6), 0.2),
+        (Point3::new(0.75, 0.35, 0.55), 0.15),
+    ];
+    VoxelGrid::from_fn(48, 48, 48, |p| {
+        blobs
+            .iter()
+            .map(|(center, radius)| {
+                let d = (p - *center).length() / radius;
+                (1.0 - d * d).max(0.0)
+            })
+            .sum::<f64>()
+            .min(1.0)
+    })
+}
+
+fn main() {
+    // An optional raw voxel file replaces the procedural cloud
+    let grid = match env::args().nth(1) {
+        Some(path) => VoxelGrid::load_raw(&path).expect("Could not read voxel file"),
+        None => procedural_cloud(),
+    };
+
+    // World
+    let material_ground = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+
+    let mut world = HittableList::new();
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        material_ground,
+    )));
+    world.objects.push(Box::new(GridMedium::new(
+        Point3::new(-1.2, -0.6, -3.5),
+        Point3::new(1.2, 1.2, -1.5),
+        grid,
+        8.0,
+        Color::from_float(0.95),
+        Arc::new(HenyeyGreenstein::new(0.6)),
+    )));
+
+    // Camera
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 100;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+
+    let mut scene = Scene::new(cam, world);
+    scene.render(&PathTracer::new(50));
+}
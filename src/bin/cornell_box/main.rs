@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    background::Solid,
+    camera::Camera,
+    cuboid::Cuboid,
+    hittable::HittableList,
+    material::{DiffuseLight, Lambertian},
+    path::PathTracer,
+    quad::Quad,
+    scene::Scene,
+    transformed::Transformed,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+fn main() {
+    // World
+    let red = Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let white = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let green = Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+    let light = Arc::new(DiffuseLight::new(Color::new(15.0, 15.0, 15.0)));
+
+    // A room two units across with its open side facing the camera
+    let mut world = HittableList::new();
+    let (near, far) = (-1.1, -3.1);
+    let depth = Vec3::new(0.0, 0.0, far - near);
+    world.objects.push(Box::new(Quad::new(
+        Point3::new(1.0, -1.0, near),
+        Vec3::new(0.0, 2.0, 0.0),
+        depth,
+        green,
+    )));
+    world.objects.push(Box::new(Quad::new(
+        Point3::new(-1.0, -1.0, near),
+        Vec3::new(0.0, 2.0, 0.0),
+        depth,
+        red,
+    )));
+    world.objects.push(Box::new(Quad::new(
+        Point3::new(-1.0, -1.0, near),
+        Vec3::new(2.0, 0.0, 0.0),
+        depth,
+        white.clone(),
+    )));
+    world.objects.push(Box::new(Quad::new(
+        Point3::new(-1.0, 1.0, near),
+        Vec3::new(2.0, 0.0, 0.0),
+        depth,
+        white.clone(),
+    )));
+    world.objects.push(Box::new(Quad::new(
+        Point3::new(-1.0, -1.0, far),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(0.0, 2.0, 0.0),
+        white.clone(),
+    )));
+
+    // The light hangs just under the ceiling and faces down. It is also
+    // listed on its own so the renderer can aim rays at it.
+    let light_quad = || {
+        Box::new(Quad::new(
+            Point3::new(-0.25, 0.999, -2.3),
+            Vec3::new(0.5, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 0.4),
+            light.clone(),
+        ))
+    };
+    world.objects.push(light_quad());
+    let mut lights = HittableList::new();
+    lights.objects.push(light_quad());
+
+    let tall = Arc::new(Cuboid::new(Point3::zero(), Point3::new(0.6, 1.2, 0.6), white.clone()));
+    world.objects.push(Box::new(Transformed::new(
+        tall,
+        Transform::translate(Vec3::new(-0.6, -1.0, -2.6)) * Transform::rotate_y(15.0),
+    )));
+    let short = Arc::new(Cuboid::new(Point3::zero(), Point3::new(0.6, 0.6, 0.6), white));
+    world.objects.push(Box::new(Transformed::new(
+        short,
+        Transform::translate(Vec3::new(0.1, -1.0, -2.0)) * Transform::rotate_y(-18.0),
+    )));
+
+    // Camera, with nothing but the light to see by
+    let aspect_ratio: f64 = 1.0;
+    let image_width: i64 = 300;
+    let samples_per_pixel: i64 = 100;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+    let mut scene = Scene::new(cam, world);
+    scene.srgb = true;
+    scene.lights = lights;
+    scene.background = Arc::new(Solid(Color::zero()));
+
+    scene.render(&PathTracer::new(50));
+}
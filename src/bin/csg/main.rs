@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    camera::Camera, csg::{Difference, Intersection, Union}, cuboid::Cuboid,
+    cylinder::Cylinder, hittable::HittableList, material::Lambertian, path::PathTracer,
+    scene::Scene, sphere::Sphere, transformed::Transformed,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+fn main() {
+    // World
+    let material_ground = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+    let material_box = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5)));
+    let material_ball = Arc::new(Lambertian::new(Color::new(0.7, 0.3, 0.3)));
+
+    let mut world = HittableList::new();
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        material_ground,
+    )));
+
+    // A cube with a sphere scooped out of its top front corner
+    let cube = Arc::new(Cuboid::new(
+        Point3::new(-0.4, -0.4, -0.4),
+        Point3::new(0.4, 0.4, 0.4),
+        material_box.clone(),
+    ));
+    let scoop = Arc::new(Sphere::new(Point3::new(0.0, 0.4, 0.4), 0.45, material_ball.clone()));
+    let carved = Arc::new(Difference::new(cube.clone(), scoop));
+    world.objects.push(Box::new(Transformed::new(
+        carved,
+        Transform::translate(Vec3::new(-1.3, -0.1, -3.0)) * Transform::rotate_y(30.0),
+    )));
+
+    // The classic rounded cube: the intersection of a cube and a sphere
+    let rounding = Arc::new(Sphere::new(Point3::zero(), 0.52, material_ball.clone()));
+    let rounded = Arc::new(Intersection::new(cube.clone(), rounding));
+    world.objects.push(Box::new(Transformed::new(
+        rounded,
+        Transform::translate(Vec3::new(0.0, -0.1, -3.0)) * Transform::rotate_y(-20.0),
+    )));
+
+    // Three crossed cylinders joined together, then hollowed by a sphere
+    let axis = Arc::new(Cylinder::new(Point3::new(0.0, -0.5, 0.0), 0.15, 1.0, material_box));
+    let along_x = Arc::new(Transformed::new(axis.clone(), Transform::rotate_z(90.0)));
+    let along_z = Arc::new(Transformed::new(axis.clone(), Transform::rotate_x(90.0)));
+    let cross = Arc::new(Union::new(Arc::new(Union::new(axis, along_x)), along_z));
+    let hollow = Arc::new(Difference::new(
+        cross,
+        Arc::new(Sphere::new(Point3::zero(), 0.2, material_ball)),
+    ));
+    world.objects.push(Box::new(Transformed::new(
+        hollow,
+        Transform::translate(Vec3::new(1.3, 0.0, -3.0))
+            * Transform::rotate_y(35.0)
+            * Transform::rotate_x(25.0),
+    )));
+
+    // Camera
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 100;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+
+    let mut scene = Scene::new(cam, world);
+    scene.render(&PathTracer::new(50));
+}
@@ -0,0 +1,67 @@
+use std::env;
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    background::Solid,
+    camera::Camera,
+    hittable::HittableList,
+    material::{DiffuseLight, Lambertian},
+    path::PathTracer,
+    quad::Quad,
+    scene::Scene,
+    spectrum::Ior,
+    specular::Dielectric,
+    sphere::Sphere,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+fn main() {
+    // Pass --rgb to render the same scene without dispersion
+    let spectral = !env::args().any(|arg| arg == "--rgb");
+
+    // World: crown glass, dense flint and diamond in front of bright white
+    // bars, whose edges seen through them fringe with colour
+    let material_ground = Arc::new(Lambertian::new(Color::from_float(0.2)));
+    let bar = Arc::new(DiffuseLight::new(Color::from_float(4.0)));
+
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    world.objects.push(Box::new(Quad::new(
+        Point3::new(-10.0, -0.5, 10.0),
+        Vec3::new(20.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -20.0),
+        material_ground,
+    )));
+    for i in 0..7 {
+        let x = -1.95 + 0.6 * i as f64;
+        let q = Point3::new(x, -0.5, -3.5);
+        let (u, v) = (Vec3::new(0.15, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0));
+        world.objects.push(Box::new(Quad::new(q, u, v, bar.clone())));
+        lights.objects.push(Box::new(Quad::new(q, u, v, bar.clone())));
+    }
+    for (x, ior) in [(-1.1, Ior::BK7), (0.0, Ior::SF11), (1.1, Ior::DIAMOND)] {
+        world.objects.push(Box::new(Sphere::new(
+            Point3::new(x, 0.0, -2.0),
+            0.5,
+            Arc::new(Dielectric::dispersive(ior)),
+        )));
+    }
+
+    // Camera, in the dark
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 200;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+    let mut scene = Scene::new(cam, world);
+    scene.srgb = true;
+    scene.lights = lights;
+    scene.background = Arc::new(Solid(Color::from_float(0.02)));
+
+    scene.render(&PathTracer {
+        max_depth: 20,
+        spectral,
+    });
+}
@@ -0,0 +1,94 @@
+use std::env;
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    camera::Camera,
+    envmap::EnvironmentMap,
+    hittable::HittableList,
+    image::Image,
+    material::Lambertian,
+    path::PathTracer,
+    scene::Scene,
+    specular::{Dielectric, Metal},
+    sphere::Sphere,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+/// A latitude-longitude sky fading from blue overhead to haze at the horizon
+/// over a dark ground, with a small sun `elevation` degrees up behind the
+/// camera's left shoulder, so the scene renders without an HDR file at hand.
+fn procedural_sky(width: usize, height: usize, elevation: f64) -> Image {
+    let (sin_elevation, cos_elevation) = elevation.to_radians().sin_cos();
+    let sun = Vec3::new(-0.5 * cos_elevation, sin_elevation, 0.866 * cos_elevation);
+    let pixels = (0..width * height)
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            let theta = std::f64::consts::PI * (y as f64 + 0.5) / height as f64;
+            let phi = 2.0 * std::f64::consts::PI * ((x as f64 + 0.5) / width as f64 - 0.5);
+            let d = Vec3::new(theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos());
+            if d.dot(&sun) > 0.9997 {
+                return Color::new(1000.0, 900.0, 750.0);
+            }
+            if d.y < 0.0 {
+                return Color::new(0.08, 0.07, 0.06);
+            }
+            let haze = Color::new(0.9, 0.9, 1.0);
+            let zenith = Color::new(0.2, 0.35, 0.8);
+            haze * (1.0 - d.y) + zenith * d.y
+        })
+        .collect();
+    Image::new(width, height, pixels)
+}
+
+fn main() {
+    // Pass a .hdr file, then optionally its rotation in degrees and intensity
+    let mut args = env::args().skip(1);
+    let image = match args.next() {
+        Some(path) => Image::load(&path).unwrap_or_else(|e| panic!("Could not load {path}: {e}")),
+        None => procedural_sky(1024, 512, 25.0),
+    };
+    let rotation = args.next().map_or(0.0, |a| a.parse().expect("rotation in degrees"));
+    let intensity = args.next().map_or(1.0, |a| a.parse().expect("intensity"));
+
+    // World
+    let material_ground = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let material_matte = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.2)));
+    let material_glass = Arc::new(Dielectric::new(1.5));
+    let material_metal = Arc::new(Metal::new(Color::new(0.9, 0.9, 0.9), 0.05));
+
+    let mut world = HittableList::new();
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        material_ground,
+    )));
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(-1.1, 0.0, -2.0),
+        0.5,
+        material_matte,
+    )));
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.0, 0.0, -2.0),
+        0.5,
+        material_glass,
+    )));
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(1.1, 0.0, -2.0),
+        0.5,
+        material_metal,
+    )));
+
+    // Camera, lit by the environment alone
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 50;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+    let mut scene = Scene::new(cam, world);
+    scene.srgb = true;
+    scene.background = Arc::new(EnvironmentMap::new(Arc::new(image), rotation, intensity));
+
+    scene.render(&PathTracer::new(20));
+}
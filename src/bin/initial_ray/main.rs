@@ -1,6 +1,8 @@
 use vec3::*;
-#[path = "../../common/ray.rs"]
-mod ray;
+
+use crate::common::ray;
+#[path = "../../common/mod.rs"]
+pub mod common;
 
 fn hit_sphere(center: &Point3, radius: f64, r: &ray::Ray) -> f64 {
     let oc = r.origin() - *center;
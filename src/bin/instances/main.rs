@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    camera::Camera, cylinder::Cylinder, hittable::HittableList, material::Lambertian,
+    path::PathTracer, scene::Scene, sphere::Sphere, torus::Torus, transformed::Transformed,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+fn main() {
+    // World
+    let material_ground = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+    let material_solid = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5)));
+
+    let mut world = HittableList::new();
+
+    // One torus shared by a row of instances, each tipped a little further over
+    let torus = Arc::new(Torus::new(Point3::zero(), 0.3, 0.1, material_solid.clone()));
+    for i in 0..4 {
+        let placement = Transform::translate(Vec3::new(-1.5 + i as f64, 0.3, -3.0))
+            * Transform::rotate_x(30.0 * i as f64);
+        world.objects.push(Box::new(Transformed::new(torus.clone(), placement)));
+    }
+
+    // A squashed sphere and a leaning cylinder
+    let sphere = Arc::new(Sphere::new(Point3::zero(), 0.5, material_solid.clone()));
+    world.objects.push(Box::new(Transformed::new(
+        sphere,
+        Transform::translate(Vec3::new(-0.8, -0.3, -3.0))
+            * Transform::scale(Vec3::new(1.0, 0.4, 1.0)),
+    )));
+    let cylinder = Arc::new(Cylinder::new(Point3::zero(), 0.2, 0.8, material_solid));
+    world.objects.push(Box::new(Transformed::new(
+        cylinder,
+        Transform::translate(Vec3::new(0.8, -0.5, -3.0)) * Transform::rotate_z(-30.0),
+    )));
+
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        material_ground,
+    )));
+
+    // Camera
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 100;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+
+    let mut scene = Scene::new(cam, world);
+    scene.render(&PathTracer::new(10));
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    background::Solid,
+    camera::Camera,
+    hittable::HittableList,
+    light::{DirectionalLight, PointLight, SpotLight},
+    material::Lambertian,
+    path::PathTracer,
+    scene::Scene,
+    specular::{Dielectric, Metal},
+    sphere::Sphere,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+fn main() {
+    // World
+    let material_ground = Arc::new(Lambertian::new(Color::new(0.6, 0.6, 0.6)));
+    let material_matte = Arc::new(Lambertian::new(Color::new(0.7, 0.3, 0.2)));
+    let material_glass = Arc::new(Dielectric::new(1.5));
+    let material_metal = Arc::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.1));
+
+    let mut world = HittableList::new();
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        material_ground,
+    )));
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(-1.1, 0.0, -2.0),
+        0.5,
+        material_matte,
+    )));
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.0, 0.0, -2.0),
+        0.5,
+        material_glass,
+    )));
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(1.1, 0.0, -2.0),
+        0.5,
+        material_metal,
+    )));
+
+    // Camera, against a dim sky so the lights do the work
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 50;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+    let mut scene = Scene::new(cam, world);
+    scene.srgb = true;
+    scene.background = Arc::new(Solid(Color::new(0.02, 0.02, 0.04)));
+
+    // A low warm sun from the left, a blue fill light behind the camera and a
+    // white spot picking out the middle of the floor
+    scene.analytic_lights.push(Arc::new(DirectionalLight::new(
+        Vec3::new(1.0, -0.6, -0.3),
+        Color::new(1.2, 0.9, 0.6),
+    )));
+    scene.analytic_lights.push(Arc::new(PointLight::new(
+        Point3::new(0.5, 1.5, 0.5),
+        Color::new(0.6, 0.8, 1.6),
+    )));
+    scene.analytic_lights.push(Arc::new(SpotLight::new(
+        Point3::new(0.0, 2.0, -2.8),
+        Vec3::new(0.0, -1.0, 0.3),
+        Color::from_float(6.0),
+        25.0,
+        15.0,
+    )));
+
+    scene.render(&PathTracer::new(20));
+}
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    camera::Camera,
+    hittable::HittableList,
+    material::Lambertian,
+    microfacet::{RoughConductor, RoughDielectric, TrowbridgeReitz},
+    path::PathTracer,
+    scene::Scene,
+    sky::PreethamSky,
+    sphere::Sphere,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+fn main() {
+    // World: gold spheres along the back and glass ones in front, getting
+    // rougher from left to right
+    let material_ground = Arc::new(Lambertian::new(Color::new(0.25, 0.25, 0.25)));
+    let gold_eta = Color::new(0.143, 0.374, 1.442);
+    let gold_k = Color::new(3.983, 2.385, 1.603);
+
+    let mut world = HittableList::new();
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        material_ground,
+    )));
+    for (i, roughness) in [0.0, 0.2, 0.4, 0.7].into_iter().enumerate() {
+        let x = -1.5 + i as f64;
+        let distribution = TrowbridgeReitz::from_roughness(roughness);
+        world.objects.push(Box::new(Sphere::new(
+            Point3::new(x, 0.0, -3.2),
+            0.4,
+            Arc::new(RoughConductor::new(gold_eta, gold_k, distribution)),
+        )));
+        world.objects.push(Box::new(Sphere::new(
+            Point3::new(x * 0.8, -0.2, -2.0),
+            0.28,
+            Arc::new(RoughDielectric::new(1.5, distribution)),
+        )));
+    }
+
+    // Camera, under a clear afternoon sky
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 100;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+    let mut scene = Scene::new(cam, world);
+    scene.srgb = true;
+    scene.background = Arc::new(PreethamSky::new(
+        Vec3::new(-0.6, 0.7, 0.4),
+        2.5,
+        Color::from_float(0.3),
+    ));
+
+    scene.render(&PathTracer::new(20));
+}
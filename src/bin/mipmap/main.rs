@@ -0,0 +1,80 @@
+use std::env;
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    camera::Camera,
+    cuboid::Cuboid,
+    hittable::HittableList,
+    image::Image,
+    material::Lambertian,
+    mipmap::FilterMode,
+    path::PathTracer,
+    scene::Scene,
+    specular::{Dielectric, Metal},
+    sphere::Sphere,
+    texture::{ImageTexture, WrapMode},
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+/// A black and white checkerboard image with square checks `check` texels wide.
+fn checkerboard(size: usize, check: usize) -> Image {
+    let pixels = (0..size * size)
+        .map(|i| {
+            let (x, y) = (i % size, i / size);
+            if (x / check + y / check).is_multiple_of(2) {
+                Color::from_float(0.9)
+            } else {
+                Color::from_float(0.05)
+            }
+        })
+        .collect();
+    Image::new(size, size, pixels)
+}
+
+fn main() {
+    // Compare filters by passing bilinear, trilinear or ewa
+    let filter = match env::args().nth(1).as_deref() {
+        None | Some("ewa") => FilterMode::Ewa,
+        Some("trilinear") => FilterMode::Trilinear,
+        Some("bilinear") => FilterMode::Bilinear,
+        Some(other) => panic!("Unknown filter {other}, expected bilinear, trilinear or ewa"),
+    };
+
+    // World
+    let mut checks = ImageTexture::new(Arc::new(checkerboard(2048, 8)), WrapMode::Repeat);
+    checks.filter = filter;
+    let material_ground = Arc::new(Lambertian::from_texture(Arc::new(checks)));
+    let material_mirror = Arc::new(Metal::new(Color::new(0.9, 0.9, 0.9), 0.0));
+    let material_glass = Arc::new(Dielectric::new(1.5));
+
+    let mut world = HittableList::new();
+    // A wide slab whose top face recedes to the horizon
+    world.objects.push(Box::new(Cuboid::new(
+        Point3::new(-100.0, -1.5, -200.0),
+        Point3::new(100.0, -0.5, 0.0),
+        material_ground,
+    )));
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(-0.6, 0.0, -2.0),
+        0.5,
+        material_mirror,
+    )));
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.6, 0.0, -2.0),
+        0.5,
+        material_glass,
+    )));
+
+    // Camera, with few samples so aliasing is not simply averaged away
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 4;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+
+    let mut scene = Scene::new(cam, world);
+    scene.srgb = true;
+    scene.render(&PathTracer::new(50));
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    camera::Camera, hittable::HittableList, material::Lambertian, path::PathTracer,
+    scene::Scene, sphere::Sphere, torus::Torus, transformed::Transformed,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+fn main() {
+    // World
+    let material_ground = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+    let material_solid = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5)));
+
+    let mut world = HittableList::new();
+
+    // A sphere dropping while the shutter is open
+    world.objects.push(Box::new(Sphere::new_moving(
+        Point3::new(-1.0, 0.3, -3.0),
+        Point3::new(-1.0, -0.1, -3.0),
+        0.4,
+        material_solid.clone(),
+    )));
+
+    // A torus that slides right while spinning a quarter turn
+    let torus = Arc::new(Torus::new(Point3::zero(), 0.4, 0.12, material_solid));
+    let start = Transform::translate(Vec3::new(0.5, 0.0, -3.0)) * Transform::rotate_x(60.0);
+    let end = Transform::translate(Vec3::new(1.3, 0.0, -3.0))
+        * Transform::rotate_z(90.0)
+        * Transform::rotate_x(60.0);
+    world.objects.push(Box::new(Transformed::animated(
+        torus,
+        AnimatedTransform::new(vec![(0.0, start), (1.0, end)]),
+    )));
+
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        material_ground,
+    )));
+
+    // Camera
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 100;
+    let mut cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+    cam.shutter_open = 0.0;
+    cam.shutter_close = 1.0;
+
+    let mut scene = Scene::new(cam, world);
+    scene.render(&PathTracer::new(10));
+}
@@ -0,0 +1,76 @@
+use std::env;
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    camera::Camera,
+    cylinder::Cylinder,
+    hittable::HittableList,
+    material::Lambertian,
+    path::PathTracer,
+    perlin::Perlin,
+    scene::Scene,
+    sphere::Sphere,
+    texture::{MarbleTexture, NoiseTexture, WoodTexture},
+    util::seeded_rng,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+fn main() {
+    // The same seed always builds the same textures
+    let seed = env::args()
+        .nth(1)
+        .map(|s| s.parse().expect("Seed must be an integer"))
+        .unwrap_or(42);
+    let mut rng = seeded_rng(seed);
+
+    // World
+    let material_ground = Arc::new(Lambertian::from_texture(Arc::new(NoiseTexture::new(
+        Perlin::new(&mut rng),
+        4.0,
+    ))));
+    let material_marble = Arc::new(Lambertian::from_texture(Arc::new(MarbleTexture::new(
+        Perlin::new(&mut rng),
+        8.0,
+        10.0,
+        Color::new(0.15, 0.15, 0.2),
+        Color::new(0.9, 0.9, 0.85),
+    ))));
+    let material_wood = Arc::new(Lambertian::from_texture(Arc::new(WoodTexture::new(
+        Perlin::new(&mut rng),
+        12.0,
+        1.5,
+        Color::new(0.75, 0.5, 0.25),
+        Color::new(0.35, 0.18, 0.07),
+    ))));
+
+    let mut world = HittableList::new();
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        material_ground,
+    )));
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(-0.6, 0.0, -2.0),
+        0.5,
+        material_marble,
+    )));
+    world.objects.push(Box::new(Cylinder::new(
+        Point3::new(0.6, -0.5, -2.0),
+        0.35,
+        0.9,
+        material_wood,
+    )));
+
+    // Camera
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 100;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+
+    let mut scene = Scene::new(cam, world);
+    scene.srgb = true;
+    scene.render(&PathTracer::new(50));
+}
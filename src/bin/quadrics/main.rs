@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    camera::Camera, cone::Cone, cylinder::Cylinder, hittable::HittableList,
+    material::Lambertian, paraboloid::Paraboloid, path::PathTracer, scene::Scene,
+    sphere::Sphere, torus::Torus,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+fn main() {
+    // World
+    let material_ground = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+    let material_solid = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5)));
+
+    let mut world = HittableList::new();
+    world.objects.push(Box::new(Cylinder::new(
+        Point3::new(-1.8, -0.5, -3.0),
+        0.35,
+        1.0,
+        material_solid.clone(),
+    )));
+    world.objects.push(Box::new(Cone::new(
+        Point3::new(-0.6, -0.5, -3.0),
+        0.4,
+        1.0,
+        material_solid.clone(),
+    )));
+    world.objects.push(Box::new(Paraboloid::new(
+        Point3::new(0.6, -0.5, -3.0),
+        0.4,
+        1.0,
+        material_solid.clone(),
+    )));
+    world.objects.push(Box::new(Torus::new(
+        Point3::new(1.8, -0.2, -3.0),
+        0.4,
+        0.15,
+        material_solid,
+    )));
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        material_ground,
+    )));
+
+    // Camera
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 100;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+
+    let mut scene = Scene::new(cam, world);
+    scene.render(&PathTracer::new(10));
+}
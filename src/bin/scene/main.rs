@@ -0,0 +1,15 @@
+use std::env;
+
+use crate::common::scene::SceneDesc;
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+fn main() {
+    // Pass a JSON scene file, or see the principled material's range
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "scenes/principled.json".to_string());
+    let (mut scene, integrator) = SceneDesc::load(&path).unwrap_or_else(|e| panic!("Could not load {path}: {e}"));
+
+    scene.render(integrator.as_ref());
+}
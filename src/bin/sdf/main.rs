@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    camera::Camera,
+    hittable::HittableList,
+    material::Lambertian,
+    path::PathTracer,
+    scene::Scene,
+    sdf::{Mandelbulb, Repeat, Sdf, SdfBox, SdfHittable, SdfSphere, SdfTorus, SmoothUnion, Twist},
+    sphere::Sphere,
+    transformed::Transformed,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+fn main() {
+    // World
+    let material_ground = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+    let material_blob = Arc::new(Lambertian::new(Color::new(0.7, 0.3, 0.3)));
+    let material_solid = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5)));
+
+    let mut world = HittableList::new();
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        material_ground,
+    )));
+
+    // Two spheres and a ring melted together
+    let blob = SmoothUnion {
+        a: Arc::new(SmoothUnion {
+            a: Arc::new(SdfSphere { center: Point3::new(-1.5, -0.1, -3.0), radius: 0.3 }),
+            b: Arc::new(SdfSphere { center: Point3::new(-1.1, 0.1, -3.0), radius: 0.25 }),
+            k: 0.2,
+        }),
+        b: Arc::new(SdfTorus {
+            center: Point3::new(-1.3, -0.3, -3.0),
+            major_radius: 0.4,
+            minor_radius: 0.08,
+        }),
+        k: 0.15,
+    };
+    world.objects.push(Box::new(SdfHittable::new(Arc::new(blob), material_blob.clone())));
+
+    // A twisted column
+    let column = Twist {
+        inner: Arc::new(SdfBox { center: Point3::zero(), half_extents: Vec3::new(0.15, 0.5, 0.15) }),
+        rate: 2.5,
+    };
+    let mut column = SdfHittable::new(Arc::new(column), material_solid.clone());
+    column.step_scale = 0.5;
+    world.objects.push(Box::new(Transformed::new(
+        Arc::new(column),
+        Transform::translate(Vec3::new(-0.2, -0.05, -3.0)),
+    )));
+
+    // A fractal with no closed form intersection
+    let mut bulb = SdfHittable::new(
+        Arc::new(Mandelbulb { power: 8.0, iterations: 12 }),
+        material_blob,
+    );
+    bulb.max_steps = 512;
+    world.objects.push(Box::new(Transformed::new(
+        Arc::new(bulb),
+        Transform::translate(Vec3::new(1.1, 0.05, -3.0)) * Transform::scale(Vec3::from_float(0.5)),
+    )));
+
+    // An endless row of small spheres in the distance
+    let row = |p: &Point3| {
+        Repeat {
+            inner: Arc::new(SdfSphere { center: Point3::zero(), radius: 0.15 }),
+            period: Vec3::new(0.6, 0.0, 0.0),
+        }
+        .distance(p)
+    };
+    world.objects.push(Box::new(Transformed::new(
+        Arc::new(SdfHittable::new(Arc::new(row), material_solid)),
+        Transform::translate(Vec3::new(0.0, -0.35, -6.0)),
+    )));
+
+    // Camera
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 100;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+
+    let mut scene = Scene::new(cam, world);
+    scene.render(&PathTracer::new(50));
+}
@@ -0,0 +1,68 @@
+use std::env;
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    camera::Camera,
+    cuboid::Cuboid,
+    hittable::HittableList,
+    material::Lambertian,
+    path::PathTracer,
+    quad::Quad,
+    scene::Scene,
+    sky::PreethamSky,
+    specular::Metal,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+fn main() {
+    // Pass the sun's elevation in degrees and the turbidity to change the
+    // time of day and the weather
+    let mut args = env::args().skip(1);
+    let elevation: f64 = args.next().map_or(35.0, |a| a.parse().expect("elevation in degrees"));
+    let turbidity: f64 = args.next().map_or(3.0, |a| a.parse().expect("turbidity"));
+
+    // World: a plaza with a few blocks standing in for buildings
+    let material_ground = Arc::new(Lambertian::new(Color::new(0.4, 0.4, 0.4)));
+    let material_wall = Arc::new(Lambertian::new(Color::new(0.8, 0.75, 0.7)));
+    let material_glass = Arc::new(Metal::new(Color::new(0.6, 0.7, 0.8), 0.02));
+
+    let mut world = HittableList::new();
+    world.objects.push(Box::new(Quad::new(
+        Point3::new(-50.0, -1.0, 10.0),
+        Vec3::new(100.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -100.0),
+        material_ground,
+    )));
+    world.objects.push(Box::new(Cuboid::new(
+        Point3::new(-3.0, -1.0, -7.0),
+        Point3::new(-1.2, 2.5, -5.0),
+        material_wall.clone(),
+    )));
+    world.objects.push(Box::new(Cuboid::new(
+        Point3::new(-0.6, -1.0, -9.0),
+        Point3::new(1.0, 4.0, -7.5),
+        material_glass,
+    )));
+    world.objects.push(Box::new(Cuboid::new(
+        Point3::new(1.5, -1.0, -5.0),
+        Point3::new(2.7, 0.5, -3.8),
+        material_wall,
+    )));
+
+    // Camera, with the sun off to the right and a little behind
+    let (sin_elevation, cos_elevation) = elevation.to_radians().sin_cos();
+    let sun = Vec3::new(0.8 * cos_elevation, sin_elevation, 0.6 * cos_elevation);
+
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 50;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+    let mut scene = Scene::new(cam, world);
+    scene.srgb = true;
+    scene.background = Arc::new(PreethamSky::new(sun, turbidity, Color::from_float(0.3)));
+
+    scene.render(&PathTracer::new(20));
+}
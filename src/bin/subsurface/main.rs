@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    background::Gradient,
+    camera::Camera,
+    hittable::HittableList,
+    material::{DiffuseLight, Lambertian},
+    path::PathTracer,
+    phase::HenyeyGreenstein,
+    quad::Quad,
+    scene::Scene,
+    sphere::Sphere,
+    subsurface::Subsurface,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+fn main() {
+    // World: an opaque sphere for comparison, then wax, skin, jade and
+    // marble, lit from behind so light shows through their edges
+    let material_ground = Arc::new(Lambertian::new(Color::from_float(0.4)));
+    let material_opaque = Arc::new(Lambertian::new(Color::new(0.9, 0.8, 0.6)));
+    let light = Arc::new(DiffuseLight::new(Color::from_float(6.0)));
+
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    world.objects.push(Box::new(Quad::new(
+        Point3::new(-10.0, -0.4, 10.0),
+        Vec3::new(20.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -20.0),
+        material_ground,
+    )));
+    let (q, u, v) = (
+        Point3::new(-2.0, 1.5, -4.5),
+        Vec3::new(4.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.2),
+    );
+    world.objects.push(Box::new(Quad::new(q, u, v, light.clone())));
+    lights.objects.push(Box::new(Quad::new(q, u, v, light)));
+
+    let ball = |x: f64| Arc::new(Sphere::new(Point3::new(x, 0.0, -2.5), 0.4, material_opaque.clone()));
+    world.objects.push(Box::new(Sphere::new(Point3::new(-2.0, 0.0, -2.5), 0.4, material_opaque.clone())));
+    let wax = Subsurface::new(ball(-1.0), Color::new(0.99, 0.97, 0.9), Color::new(0.2, 0.15, 0.08), 1.45);
+    let skin = Subsurface::new(ball(0.0), Color::new(0.98, 0.85, 0.75), Color::new(0.15, 0.05, 0.03), 1.4);
+    let jade = Subsurface::new(ball(1.0), Color::new(0.7, 0.97, 0.8), Color::from_float(0.25), 1.6);
+    let marble = Subsurface::with_phase(
+        ball(2.0),
+        Color::from_float(0.995),
+        Color::from_float(0.03),
+        1.5,
+        Arc::new(HenyeyGreenstein::new(0.4)),
+    );
+    for object in [wax, skin, jade, marble] {
+        world.objects.push(Box::new(object));
+    }
+
+    // Camera, under a dim sky
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 200;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+    let mut scene = Scene::new(cam, world);
+    scene.srgb = true;
+    scene.lights = lights;
+    scene.background = Arc::new(Gradient {
+        bottom: Color::from_float(0.1),
+        top: Color::new(0.05, 0.07, 0.1),
+    });
+
+    scene.render(&PathTracer::new(20));
+}
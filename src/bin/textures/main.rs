@@ -0,0 +1,85 @@
+use std::env;
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    camera::Camera,
+    cylinder::Cylinder,
+    hittable::HittableList,
+    image::Image,
+    material::Lambertian,
+    path::PathTracer,
+    scene::Scene,
+    sphere::Sphere,
+    texture::{CheckerTexture, ImageTexture, WrapMode},
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+/// A latitude/longitude grid so the mapping is visible without an image file.
+fn grid_image() -> Image {
+    let (width, height) = (256, 128);
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let on_line = x % 32 < 2 || y % 32 < 2;
+            let base = Color::new(x as f64 / width as f64, 0.3, y as f64 / height as f64);
+            pixels.push(if on_line { Color::one() } else { base });
+        }
+    }
+    Image::new(width, height, pixels)
+}
+
+fn main() {
+    // An optional PNG or PPM replaces the generated grid
+    let image = match env::args().nth(1) {
+        Some(path) => Image::load(&path).expect("Could not read image"),
+        None => grid_image(),
+    };
+    let image = Arc::new(image);
+
+    // World
+    let checker = Arc::new(CheckerTexture::from_colors(
+        0.32,
+        Color::new(0.2, 0.3, 0.1),
+        Color::new(0.9, 0.9, 0.9),
+    ));
+    let material_ground = Arc::new(Lambertian::from_texture(checker));
+    let material_globe = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        image.clone(),
+        WrapMode::Repeat,
+    ))));
+    let material_label = Arc::new(Lambertian::from_texture(Arc::new(ImageTexture::new(
+        image,
+        WrapMode::Mirror,
+    ))));
+
+    let mut world = HittableList::new();
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        material_ground,
+    )));
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(-0.6, 0.0, -2.0),
+        0.5,
+        material_globe,
+    )));
+    world.objects.push(Box::new(Cylinder::new(
+        Point3::new(0.6, -0.5, -2.0),
+        0.35,
+        0.9,
+        material_label,
+    )));
+
+    // Camera
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 100;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+
+    let mut scene = Scene::new(cam, world);
+    scene.srgb = true;
+    scene.render(&PathTracer::new(50));
+}
@@ -0,0 +1,72 @@
+use std::env;
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    camera::Camera,
+    hittable::HittableList,
+    material::Lambertian,
+    path::PathTracer,
+    quad::Quad,
+    scene::Scene,
+    sky::PreethamSky,
+    specular::Dielectric,
+    sphere::Sphere,
+    thin_film::ThinFilm,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+fn main() {
+    // Pass --spectral to follow wavelengths rather than RGB
+    let spectral = env::args().any(|arg| arg == "--spectral");
+
+    // World: an oil slick on wet tarmac, soap bubbles of increasing
+    // thickness in front of a dark wall and anodised titanium in front
+    let wall = Arc::new(Lambertian::new(Color::from_float(0.02)));
+    let tarmac = Arc::new(Lambertian::new(Color::from_float(0.05)));
+    let oil = Arc::new(ThinFilm::new(tarmac, 450.0, 1.47, 1.33));
+    let air = Arc::new(Dielectric::new(1.0));
+
+    let mut world = HittableList::new();
+    world.objects.push(Box::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, oil)));
+    world.objects.push(Box::new(Quad::new(
+        Point3::new(-4.0, -0.6, -4.0),
+        Vec3::new(8.0, 0.0, 0.0),
+        Vec3::new(0.0, 3.0, 0.0),
+        wall,
+    )));
+    for (i, thickness) in [250.0, 350.0, 500.0, 700.0].into_iter().enumerate() {
+        world.objects.push(Box::new(Sphere::new(
+            Point3::new(-1.5 + i as f64, 0.1, -3.0),
+            0.45,
+            Arc::new(ThinFilm::new(air.clone(), thickness, 1.33, 1.0)),
+        )));
+    }
+    for (i, thickness) in [60.0, 110.0, 160.0].into_iter().enumerate() {
+        world.objects.push(Box::new(Sphere::new(
+            Point3::new(-0.8 + 0.8 * i as f64, -0.25, -1.6),
+            0.25,
+            Arc::new(ThinFilm::over_conductor(thickness, 2.4, 1.9, 2.9)),
+        )));
+    }
+
+    // Camera, under a clear sky
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 100;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+    let mut scene = Scene::new(cam, world);
+    scene.srgb = true;
+    scene.background = Arc::new(PreethamSky::new(
+        Vec3::new(0.4, 0.6, 0.5),
+        3.0,
+        Color::from_float(0.3),
+    ));
+
+    scene.render(&PathTracer {
+        max_depth: 20,
+        spectral,
+    });
+}
@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use vec3::*;
+
+use crate::common::{
+    camera::Camera, constant_medium::ConstantMedium, cylinder::Cylinder,
+    hittable::HittableList, material::Lambertian, path::PathTracer, scene::Scene,
+    sphere::Sphere,
+};
+#[path = "../../common/mod.rs"]
+pub mod common;
+
+fn main() {
+    // World
+    let material_ground = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+    let material_solid = Arc::new(Lambertian::new(Color::new(0.7, 0.3, 0.3)));
+
+    let mut world = HittableList::new();
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        material_ground,
+    )));
+
+    // A solid sphere wrapped in a thin white fog
+    world.objects.push(Box::new(Sphere::new(
+        Point3::new(-0.7, -0.1, -2.5),
+        0.3,
+        material_solid.clone(),
+    )));
+    let fog_boundary = Arc::new(Sphere::new(
+        Point3::new(-0.7, -0.1, -2.5),
+        0.6,
+        material_solid.clone(),
+    ));
+    world.objects.push(Box::new(ConstantMedium::new(
+        fog_boundary,
+        1.5,
+        Color::from_float(1.0),
+    )));
+
+    // A column of dense dark smoke
+    let smoke_boundary = Arc::new(Cylinder::new(
+        Point3::new(0.8, -0.5, -2.5),
+        0.35,
+        1.2,
+        material_solid,
+    ));
+    world.objects.push(Box::new(ConstantMedium::new(
+        smoke_boundary,
+        4.0,
+        Color::from_float(0.1),
+    )));
+
+    // Camera
+    let aspect_ratio: f64 = 16.0 / 9.0;
+    let image_width: i64 = 400;
+    let samples_per_pixel: i64 = 100;
+    let cam = Camera::new(aspect_ratio, image_width, samples_per_pixel);
+
+    let mut scene = Scene::new(cam, world);
+    scene.render(&PathTracer::new(50));
+}
@@ -0,0 +1,48 @@
+use crate::common::{pdf::Pdf, ray::Ray};
+use vec3::*;
+
+/// What a ray sees when it leaves the scene without hitting anything.
+pub trait Background: Send + Sync {
+    fn value(&self, r: &Ray) -> Color;
+
+    /// A distribution over directions roughly following the light given off,
+    /// if the background is worth aiming shadow rays at.
+    fn pdf(&self) -> Option<&dyn Pdf> {
+        None
+    }
+}
+
+/// Blends from `bottom` looking straight down to `top` looking straight up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gradient {
+    pub bottom: Color,
+    pub top: Color,
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Gradient {
+            bottom: Color::from_float(1.0),
+            top: Color::new(0.5, 0.7, 1.0),
+        }
+    }
+}
+
+impl Background for Gradient {
+    fn value(&self, r: &Ray) -> Color {
+        let unit_direction = r.direction().unit_vector();
+        let t = 0.5 * (unit_direction.y + 1.0);
+        self.bottom * (1.0 - t) + self.top * t
+    }
+}
+
+/// The same color in every direction. Black makes an enclosed scene lit only
+/// by its own emitters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Solid(pub Color);
+
+impl Background for Solid {
+    fn value(&self, _r: &Ray) -> Color {
+        self.0
+    }
+}
@@ -0,0 +1,543 @@
+use core::f64;
+
+use crate::common::{
+    camera::Camera,
+    hittable::{HitRecord, Hittable, HittableList},
+    integrator::{sample_analytic_light, sample_background, Integrator},
+    material::ScatterRecord,
+    pdf::{CosinePdf, Pdf},
+    ray::Ray,
+    sampler::Sampler,
+    scene::Scene,
+    util::{power_heuristic, Interval},
+};
+use vec3::*;
+
+/// Bidirectional path tracing. Every sample traces one subpath from the
+/// camera and one from a point on the scene's lights, then joins each prefix
+/// of one to each prefix of the other. All the ways of building the same path
+/// are weighted against each other by multiple importance sampling, so each
+/// kind of light transport comes mostly from the strategy best at finding it,
+/// and caustics and light through small openings turn up far sooner than with
+/// the path tracer.
+///
+/// Light from the background and from analytic lights is gathered along the
+/// camera subpath as the path tracer does; only area lights are traced from.
+pub struct Bidirectional {
+    pub max_depth: i64,
+}
+
+impl Bidirectional {
+    pub fn new(max_depth: i64) -> Self {
+        Bidirectional { max_depth }
+    }
+
+    // Everything one sample finds: what belongs to the pixel the camera ray
+    // went through, and what light traced to the camera lands elsewhere
+    fn sample(&self, r: &Ray, scene: &Scene, splats: &mut Vec<(i64, i64, Color)>) -> Color {
+        let max_depth = self.max_depth.max(0) as usize;
+        let mut color = Color::zero();
+        let camera_path = camera_subpath(scene, *r, max_depth, &mut color);
+        let light_path = light_subpath(scene, r.time(), max_depth);
+
+        for t in 1..=camera_path.len() {
+            for s in 0..=light_path.len() {
+                // Lights seen straight from the camera are left to the camera
+                // ray
+                if s + t < 2 || s + t - 2 > max_depth || (s == 1 && t == 1) {
+                    continue;
+                }
+                let Some((l, pixel)) = connect(scene, &light_path, &camera_path, s, t) else {
+                    continue;
+                };
+                match pixel {
+                    Some((x, y)) => splats.push((x, y, l)),
+                    None => color += l,
+                }
+            }
+        }
+        color
+    }
+}
+
+impl Integrator for Bidirectional {
+    /// Only what belongs to the camera ray's own pixel; light traced straight
+    /// to the camera needs `render`.
+    fn li(&self, r: &Ray, scene: &Scene, _sampler: &mut dyn Sampler) -> Color {
+        self.sample(r, scene, &mut Vec::new())
+    }
+
+    /// Joins straight to the camera can land in any pixel, so the image is
+    /// only complete once every pixel has been sampled.
+    fn render(&self, scene: &Scene, sampler: &mut dyn Sampler) -> Vec<Color> {
+        let cam = &scene.camera;
+        let width = cam.image_width;
+        let mut pixels = vec![Color::zero(); (width * cam.image_height()) as usize];
+        let mut splats = Vec::new();
+        for j in 0..cam.image_height() {
+            eprint!("\r Scanlines remaining: {j}");
+            for i in 0..width {
+                for _sample in 0..cam.samples_per_pixel {
+                    let r = cam.get_ray(i, j, sampler);
+                    pixels[(j * width + i) as usize] += self.sample(&r, scene, &mut splats);
+                    for (x, y, l) in splats.drain(..) {
+                        pixels[(y * width + x) as usize] += l;
+                    }
+                }
+            }
+        }
+        pixels
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Camera,
+    Light,
+    Surface,
+}
+
+// A point on a subpath and what it took to get there
+#[derive(Clone)]
+struct Vertex {
+    kind: Kind,
+    rec: HitRecord,
+    // The ray that found the vertex, for asking its material about other
+    // directions. For the first vertex of a subpath, the ray leaving it.
+    r_in: Ray,
+    // Throughput from the start of the subpath up to here
+    beta: Color,
+    // Reached by a specular bounce, so nothing can be joined to it
+    delta: bool,
+    // Density per unit area of sampling this vertex from the one before it on
+    // its own subpath, and of sampling it the other way along the path
+    pdf_fwd: f64,
+    pdf_rev: f64,
+}
+
+impl Vertex {
+    fn p(&self) -> Point3 {
+        self.rec.p
+    }
+
+    // Points inside a medium or at the camera have no surface to be tilted by
+    fn on_surface(&self) -> bool {
+        self.kind != Kind::Camera && !self.rec.geometric_normal().near_zero()
+    }
+
+    // Turns a density per steradian of going from here towards `next` into
+    // one per unit area at `next`
+    fn convert_density(&self, pdf: f64, next: &Vertex) -> f64 {
+        let w = next.p() - self.p();
+        let distance_squared = w.length_squared();
+        if distance_squared == 0.0 {
+            return 0.0;
+        }
+        let mut pdf = pdf / distance_squared;
+        if next.on_surface() {
+            pdf *= next.rec.geometric_normal().dot(&w).abs() / distance_squared.sqrt();
+        }
+        pdf
+    }
+
+    // What the vertex sends towards `to` per unit of what reached it: the
+    // BSDF times the cosine on a surface, emitted radiance times the cosine
+    // at a light
+    fn f(&self, to: &Point3) -> Color {
+        let Some(mat) = &self.rec.mat else {
+            return Color::zero();
+        };
+        let w = *to - self.p();
+        match self.kind {
+            Kind::Camera => Color::zero(),
+            Kind::Light => {
+                let cosine = self.rec.normal().dot(&w.unit_vector());
+                if cosine <= 0.0 {
+                    return Color::zero();
+                }
+                mat.emitted(&Ray::new_with_time(*to, -w, self.r_in.time()), &self.rec) * cosine
+            }
+            Kind::Surface => mat.eval(&self.r_in, &self.rec, &self.rec.spawn_ray(&self.r_in, w)),
+        }
+    }
+
+    // Density per steradian of the material sending what arrives from `from`
+    // off along `direction`
+    fn bsdf_pdf(&self, from: &Point3, direction: &Vec3<f64>) -> f64 {
+        let Some(mat) = &self.rec.mat else {
+            return 0.0;
+        };
+        let r = Ray::new_with_time(*from, self.p() - *from, self.r_in.time());
+        let rec = self.rec.seen_along(&r);
+        mat.pdf(&r, &rec, &rec.spawn_ray(&r, *direction))
+    }
+
+    // Density per steradian of a light emitting towards `direction`
+    fn emission_pdf(&self, direction: &Vec3<f64>) -> f64 {
+        CosinePdf::new(&self.rec.normal()).value(direction)
+    }
+
+    // Density per unit area of this vertex sampling `next`, having been
+    // reached from `prev`
+    fn pdf(&self, camera: &Camera, prev: Option<&Vertex>, next: &Vertex) -> f64 {
+        let pdf = match (self.kind, prev) {
+            (Kind::Camera, _) => camera.importance(&next.p()).map_or(0.0, |(_, _, pdf)| pdf),
+            (Kind::Light, _) => self.emission_pdf(&(next.p() - self.p())),
+            (Kind::Surface, Some(prev)) => self.bsdf_pdf(&prev.p(), &(next.p() - self.p())),
+            (Kind::Surface, None) => 0.0,
+        };
+        self.convert_density(pdf, next)
+    }
+
+    // Density per unit area of a camera path's emitter being where a light
+    // subpath starts, as seen from `from`
+    fn pdf_light_origin(&self, lights: &HittableList, from: &Vertex) -> f64 {
+        lights.surface_pdf(&from.p(), &(self.p() - from.p()), from.r_in.time())
+    }
+
+    // Density per unit area of a camera path's emitter emitting towards `next`
+    fn pdf_light(&self, next: &Vertex) -> f64 {
+        self.convert_density(self.emission_pdf(&(next.p() - self.p())), next)
+    }
+}
+
+// A subpath from the camera along `r`. Light from the background and analytic
+// lights met along the way is added to `direct`.
+fn camera_subpath(scene: &Scene, r: Ray, max_depth: usize, direct: &mut Color) -> Vec<Vertex> {
+    let mut rec = HitRecord::new();
+    rec.p = r.origin();
+    let pdf_dir = scene.camera.importance(&r.at(1.0)).map_or(0.0, |(_, _, pdf)| pdf);
+    let mut path = vec![Vertex {
+        kind: Kind::Camera,
+        rec,
+        r_in: r,
+        beta: Color::one(),
+        delta: false,
+        pdf_fwd: 1.0,
+        pdf_rev: 0.0,
+    }];
+    random_walk(scene, r, Color::one(), pdf_dir, max_depth + 2, &mut path, Some(direct));
+    path
+}
+
+// A subpath starting from a point on `lights`, emitting with a cosine
+// distribution about its normal
+fn light_subpath(scene: &Scene, time: f64, max_depth: usize) -> Vec<Vertex> {
+    let Some((rec, pdf_pos)) = scene.lights.sample_surface(time) else {
+        return Vec::new();
+    };
+    let Some(mat) = rec.mat.clone() else {
+        return Vec::new();
+    };
+    let cosine_pdf = CosinePdf::new(&rec.normal());
+    let direction = cosine_pdf.generate();
+    let pdf_dir = cosine_pdf.value(&direction);
+    let r = rec.spawn_ray(&Ray::new_with_time(rec.p, direction, time), direction);
+    let emitted = mat.emitted(&Ray::new_with_time(rec.p + direction, -direction, time), &rec);
+    let cosine = rec.normal().dot(&direction.unit_vector());
+
+    let mut path = vec![Vertex {
+        kind: Kind::Light,
+        rec,
+        r_in: r,
+        beta: Color::one() / pdf_pos,
+        delta: false,
+        pdf_fwd: pdf_pos,
+        pdf_rev: 0.0,
+    }];
+    if pdf_dir > 0.0 && emitted != Color::zero() {
+        let beta = emitted * (cosine / (pdf_pos * pdf_dir));
+        random_walk(scene, r, beta, pdf_dir, max_depth + 1, &mut path, None);
+    }
+    path
+}
+
+// Extends `path`, whose last vertex sent `r` off with density `pdf_dir` per
+// steradian, until it escapes, is absorbed or has `max_vertices`
+fn random_walk(
+    scene: &Scene,
+    mut r: Ray,
+    mut beta: Color,
+    mut pdf_dir: f64,
+    max_vertices: usize,
+    path: &mut Vec<Vertex>,
+    mut direct: Option<&mut Color>,
+) {
+    // Density with which the last material chose `r`, None after a specular
+    // bounce or for the camera ray
+    let mut bsdf_pdf = None;
+    while path.len() < max_vertices {
+        let mut rec = HitRecord::new();
+        if !scene.world.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            if let Some(direct) = direct.as_deref_mut() {
+                let mut color = scene.background.value(&r);
+                if let Some(bsdf_pdf) = bsdf_pdf
+                    && let Some(background_pdf) = scene.background.pdf()
+                {
+                    color *= power_heuristic(bsdf_pdf, background_pdf.value(&r.direction()));
+                }
+                *direct += beta * color;
+            }
+            return;
+        }
+        rec.compute_differentials(&r);
+        let Some(mat) = rec.mat.clone() else {
+            return;
+        };
+
+        let mut vertex = Vertex {
+            kind: Kind::Surface,
+            rec: rec.clone(),
+            r_in: r,
+            beta,
+            delta: false,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+        };
+        vertex.pdf_fwd = path[path.len() - 1].convert_density(pdf_dir, &vertex);
+        path.push(vertex);
+        if path.len() == max_vertices {
+            return;
+        }
+
+        let mut srec = ScatterRecord::default();
+        if !mat.scatter(&r, &rec, &mut srec) {
+            return;
+        }
+        let n = path.len();
+        let Some(bsdf) = srec.pdf else {
+            // No other strategy can find a specular bounce
+            path[n - 1].delta = true;
+            path[n - 2].pdf_rev = 0.0;
+            beta *= srec.attenuation;
+            pdf_dir = 0.0;
+            bsdf_pdf = None;
+            r = srec.specular_ray;
+            continue;
+        };
+
+        if let Some(direct) = direct.as_deref_mut() {
+            if let Some(background_pdf) = scene.background.pdf() {
+                *direct += beta * sample_background::<Color>(&r, &rec, mat.as_ref(), bsdf.as_ref(), background_pdf, scene);
+            }
+            for light in &scene.analytic_lights {
+                *direct += beta * sample_analytic_light::<Color>(&r, &rec, mat.as_ref(), light.as_ref(), scene);
+            }
+        }
+
+        let scattered = rec.spawn_ray(&r, bsdf.generate());
+        let pdf = bsdf.value(&scattered.direction());
+        if pdf <= 0.0 {
+            return;
+        }
+        let f = mat.eval(&r, &rec, &scattered);
+        if f == Color::zero() {
+            return;
+        }
+        beta = beta * f / pdf;
+
+        // How likely the material is to send what comes back along the new
+        // ray to the vertex before
+        let back = path[n - 1].bsdf_pdf(&(rec.p + scattered.direction()), &-r.direction());
+        path[n - 2].pdf_rev = path[n - 1].convert_density(back, &path[n - 2]);
+
+        // Measured like `back`, over every lobe the material might have picked
+        pdf_dir = mat.pdf(&r, &rec, &scattered);
+        bsdf_pdf = Some(pdf);
+        r = scattered;
+    }
+}
+
+// Whether nothing in `world` lies between two vertices
+fn visible(world: &dyn Hittable, a: &Vertex, b: &Vertex) -> bool {
+    let r = a.rec.spawn_ray(&a.r_in, b.p() - a.p());
+    let epsilon = 0.001 / r.direction().length();
+    let mut rec = HitRecord::new();
+    !world.hit(&r, Interval::new(epsilon, 1.0 - epsilon), &mut rec)
+}
+
+// The weighted contribution of joining the first `s` vertices of the light
+// subpath to the first `t` of the camera subpath, and the pixel it belongs
+// to when it is joined straight to the camera
+fn connect(
+    scene: &Scene,
+    light_path: &[Vertex],
+    camera_path: &[Vertex],
+    s: usize,
+    t: usize,
+) -> Option<(Color, Option<(i64, i64)>)> {
+    let (l, pixel) = if s == 0 {
+        // The camera subpath found a light by itself
+        let pt = &camera_path[t - 1];
+        let mat = pt.rec.mat.as_ref()?;
+        (pt.beta * mat.emitted(&pt.r_in, &pt.rec), None)
+    } else if t == 1 {
+        // Light traced all the way to the camera
+        let (qs, camera) = (&light_path[s - 1], &camera_path[0]);
+        if qs.delta {
+            return None;
+        }
+        let (x, y, importance) = scene.camera.importance(&qs.p())?;
+        let distance_squared = (camera.p() - qs.p()).length_squared();
+        let l = qs.beta * qs.f(&camera.p()) * (importance / distance_squared);
+        if l == Color::zero() || !visible(&scene.world, qs, camera) {
+            return None;
+        }
+        (l, Some((x, y)))
+    } else {
+        let (qs, pt) = (&light_path[s - 1], &camera_path[t - 1]);
+        if qs.delta || pt.delta {
+            return None;
+        }
+        let distance_squared = (pt.p() - qs.p()).length_squared();
+        let l = qs.beta * qs.f(&pt.p()) * pt.f(&qs.p()) * pt.beta / distance_squared;
+        if l == Color::zero() || !visible(&scene.world, qs, pt) {
+            return None;
+        }
+        (l, None)
+    };
+    if l == Color::zero() {
+        return None;
+    }
+    Some((l * mis_weight(scene, light_path, camera_path, s, t), pixel))
+}
+
+// The balance heuristic over every strategy that could have built the same
+// path, found by walking along it and swapping forward densities for reverse
+// ones one vertex at a time
+fn mis_weight(scene: &Scene, light_path: &[Vertex], camera_path: &[Vertex], s: usize, t: usize) -> f64 {
+    if s + t == 2 {
+        return 1.0;
+    }
+
+    // Forward and reverse densities and delta flags as this strategy sees
+    // them: the joined vertices are not delta, and the reverse densities
+    // around the join follow from the vertices on the other side
+    let densities = |v: &Vertex| (v.pdf_fwd, v.pdf_rev, v.delta);
+    let cam = &scene.camera;
+    let mut camera: Vec<(f64, f64, bool)> = camera_path[..t].iter().map(densities).collect();
+    let mut light: Vec<(f64, f64, bool)> = light_path[..s].iter().map(densities).collect();
+    let pt = &camera_path[t - 1];
+    let pt_minus = t.checked_sub(2).map(|i| &camera_path[i]);
+    let qs = s.checked_sub(1).map(|i| &light_path[i]);
+    let qs_minus = s.checked_sub(2).map(|i| &light_path[i]);
+
+    camera[t - 1].2 = false;
+    camera[t - 1].1 = match (qs, pt_minus) {
+        (Some(qs), _) => qs.pdf(cam, qs_minus, pt),
+        (None, Some(pt_minus)) => pt.pdf_light_origin(&scene.lights, pt_minus),
+        (None, None) => 0.0,
+    };
+    // Emitters that are not among the lights can only be found this way
+    if s == 0 && camera[t - 1].1 == 0.0 {
+        return 1.0;
+    }
+    if let Some(pt_minus) = pt_minus {
+        camera[t - 2].1 = match qs {
+            Some(qs) => pt.pdf(cam, Some(qs), pt_minus),
+            None => pt.pdf_light(pt_minus),
+        };
+    }
+    if let Some(qs) = qs {
+        light[s - 1].2 = false;
+        light[s - 1].1 = pt.pdf(cam, pt_minus, qs);
+        if let Some(qs_minus) = qs_minus {
+            light[s - 2].1 = qs.pdf(cam, Some(pt), qs_minus);
+        }
+    }
+
+    // Delta densities are zero and cancel out of the ratios
+    let remap = |pdf: f64| if pdf == 0.0 { 1.0 } else { pdf };
+    let mut sum = 0.0;
+    let mut ratio = 1.0;
+    for i in (1..t).rev() {
+        ratio *= remap(camera[i].1) / remap(camera[i].0);
+        if !camera[i].2 && !camera[i - 1].2 {
+            sum += ratio;
+        }
+    }
+    ratio = 1.0;
+    for i in (0..s).rev() {
+        ratio *= remap(light[i].1) / remap(light[i].0);
+        let after_delta = i > 0 && light[i - 1].2;
+        if !light[i].2 && !after_delta {
+            sum += ratio;
+        }
+    }
+    1.0 / (1.0 + sum)
+}
+
+/// The mean red value of an 8 x 8 render of a grey wall facing the camera,
+/// lit by a small sphere at `light` with a ball of radius 0.3 at `ball_center`,
+/// for tests comparing integrators on caustics.
+#[cfg(test)]
+pub fn wall_mean(
+    light: Point3,
+    ball_center: Point3,
+    ball: std::sync::Arc<dyn crate::common::material::Material>,
+    integrator: &dyn Integrator,
+    samples_per_pixel: i64,
+) -> f64 {
+    use std::sync::Arc;
+
+    use crate::common::{
+        background::Solid,
+        material::{DiffuseLight, Lambertian},
+        quad::Quad,
+        sphere::Sphere,
+    };
+
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    let emitter = Arc::new(DiffuseLight::new(Color::from_float(20.0)));
+    world.objects.push(Box::new(Quad::new(
+        Point3::new(-3.0, -3.0, -2.0),
+        Vec3::new(6.0, 0.0, 0.0),
+        Vec3::new(0.0, 6.0, 0.0),
+        Arc::new(Lambertian::new(Color::from_float(0.7))),
+    )));
+    world.objects.push(Box::new(Sphere::new(light, 0.1, emitter.clone())));
+    lights.objects.push(Box::new(Sphere::new(light, 0.1, emitter)));
+    world.objects.push(Box::new(Sphere::new(ball_center, 0.3, ball)));
+
+    let mut scene = Scene::new(Camera::new(1.0, 8, samples_per_pixel), world);
+    scene.lights = lights;
+    scene.background = Arc::new(Solid(Color::zero()));
+    let pixels = scene.render_pixels(integrator);
+    pixels.iter().map(|p| p.x).sum::<f64>() / (pixels.len() as i64 * samples_per_pixel) as f64
+}
+
+#[test]
+fn bidirectional_agrees_with_path_tracing() {
+    use std::sync::Arc;
+
+    use crate::common::{
+        material::{Lambertian, Material},
+        path::PathTracer,
+        specular::Dielectric,
+    };
+
+    // A wall facing the camera, lit by a small sphere just out of frame over
+    // a ball. The average over the image should not depend on the integrator.
+    let mean = |ball: Arc<dyn Material>, integrator: &dyn Integrator| {
+        wall_mean(Point3::new(0.0, 1.3, -1.0), Point3::new(0.0, 0.6, -1.4), ball, integrator, 256)
+    };
+
+    let diffuse: Arc<dyn Material> = Arc::new(Lambertian::new(Color::from_float(0.3)));
+    let unidirectional = mean(diffuse.clone(), &PathTracer::new(6));
+    let bidirectional = mean(diffuse, &Bidirectional::new(6));
+    assert!(
+        (bidirectional - unidirectional).abs() < 0.05 * unidirectional,
+        "{bidirectional} vs {unidirectional}"
+    );
+
+    // Glass focuses the light into a caustic on the wall, which the path
+    // tracer only finds by chance, so its estimate is much rougher
+    let glass: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+    let unidirectional = mean(glass.clone(), &PathTracer::new(6));
+    let bidirectional = mean(glass, &Bidirectional::new(6));
+    assert!(
+        (bidirectional - unidirectional).abs() < 0.25 * unidirectional,
+        "{bidirectional} vs {unidirectional}"
+    );
+}
@@ -0,0 +1,205 @@
+use std::sync::Arc;
+
+use crate::common::{
+    hittable::HitRecord,
+    material::{Material, ScatterRecord},
+    ray::Ray,
+    texture::Texture,
+};
+use vec3::*;
+
+/// Tilts the shading normal by a tangent-space normal map before handing the
+/// hit to `base`. The map stores each component remapped from [-1, 1] to
+/// [0, 1], with x along dp/du, y along dp/dv and z out of the surface.
+pub struct NormalMapped {
+    base: Arc<dyn Material>,
+    map: Arc<dyn Texture>,
+    // Scales the tangential part of the mapped normal, 0 leaves it flat
+    strength: f64,
+}
+
+impl NormalMapped {
+    pub fn new(base: Arc<dyn Material>, map: Arc<dyn Texture>, strength: f64) -> Self {
+        NormalMapped {
+            base,
+            map,
+            strength,
+        }
+    }
+}
+
+impl NormalMapped {
+    fn bumped(&self, rec: &HitRecord) -> HitRecord {
+        let encoded = self.map.value(rec.u, rec.v, &rec.p);
+        let local = encoded * 2.0 - Vec3::from_float(1.0);
+        let (tangent, bitangent) = rec.tangent_frame();
+        let n = tangent * (local.x * self.strength)
+            + bitangent * (local.y * self.strength)
+            + rec.normal() * local.z;
+
+        let mut bumped = rec.clone();
+        if !n.near_zero() {
+            bumped.set_shading_normal(&n);
+        }
+        bumped
+    }
+}
+
+impl Material for NormalMapped {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        self.base.scatter(r_in, &self.bumped(rec), srec)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        self.base.eval(r_in, &self.bumped(rec), scattered)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.base.pdf(r_in, &self.bumped(rec), scattered)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        self.base.emitted(r_in, rec)
+    }
+}
+
+/// Perturbs the shading normal as if the surface were displaced along it by
+/// the average of the `height` texture's channels times `scale`.
+pub struct BumpMapped {
+    base: Arc<dyn Material>,
+    height: Arc<dyn Texture>,
+    scale: f64,
+}
+
+impl BumpMapped {
+    pub fn new(base: Arc<dyn Material>, height: Arc<dyn Texture>, scale: f64) -> Self {
+        BumpMapped {
+            base,
+            height,
+            scale,
+        }
+    }
+
+    fn height_at(&self, u: f64, v: f64, p: &Point3) -> f64 {
+        let c = self.height.value(u, v, p);
+        self.scale * (c.x + c.y + c.z) / 3.0
+    }
+
+    fn bumped(&self, rec: &HitRecord) -> HitRecord {
+        // Work in the tangent frame, keeping the lengths of dp/du and dp/dv so
+        // the bumps follow the texture's scale where the surface provides them
+        let (tangent, bitangent) = rec.tangent_frame();
+        let length_or_one = |d: Vec3<f64>| {
+            let l = d.length();
+            if l > 0.0 { l } else { 1.0 }
+        };
+        let dpdu = tangent * length_or_one(rec.dpdu);
+        let dpdv = bitangent * length_or_one(rec.dpdv);
+
+        // Forward differences, moving p as well for solid textures
+        let delta = 1e-3;
+        let h = self.height_at(rec.u, rec.v, &rec.p);
+        let h_u = self.height_at(rec.u + delta, rec.v, &(rec.p + dpdu * delta));
+        let h_v = self.height_at(rec.u, rec.v + delta, &(rec.p + dpdv * delta));
+
+        let n = rec.normal();
+        let bumped_dpdu = dpdu + n * ((h_u - h) / delta);
+        let bumped_dpdv = dpdv + n * ((h_v - h) / delta);
+        let mut bumped_normal = Vec3::cross_product(&bumped_dpdu, &bumped_dpdv);
+        if bumped_normal.dot(&n) < 0.0 {
+            bumped_normal = -bumped_normal;
+        }
+
+        let mut bumped = rec.clone();
+        if !bumped_normal.near_zero() {
+            bumped.set_shading_normal(&bumped_normal);
+        }
+        bumped
+    }
+}
+
+impl Material for BumpMapped {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        self.base.scatter(r_in, &self.bumped(rec), srec)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        self.base.eval(r_in, &self.bumped(rec), scattered)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.base.pdf(r_in, &self.bumped(rec), scattered)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        self.base.emitted(r_in, rec)
+    }
+}
+
+#[test]
+fn flat_maps_keep_the_normal() {
+    use crate::common::{specular::reflect, texture::SolidColor};
+
+    // A record for a hit on the z = 0 plane seen from +z
+    let r = Ray::new(Point3::new(0.3, 0.4, 1.0), Vec3::new(0.0, 0.0, -1.0));
+    let mut rec = HitRecord::new();
+    rec.p = Point3::new(0.3, 0.4, 0.0);
+    rec.set_face_normal(&r, &Vec3::new(0.0, 0.0, 1.0));
+    rec.dpdu = Vec3::new(1.0, 0.0, 0.0);
+    rec.dpdv = Vec3::new(0.0, 1.0, 0.0);
+
+    // A mirror-like base material so the outgoing ray reveals the normal
+    struct Reflect;
+    impl Material for Reflect {
+        fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+            srec.specular_ray = rec.spawn_ray(r_in, reflect(&r_in.direction(), &rec.normal()));
+            true
+        }
+    }
+
+    let flat = Arc::new(SolidColor::new(Color::new(0.5, 0.5, 1.0)));
+    let tilted = Arc::new(SolidColor::new(Color::new(1.0, 0.5, 1.0)));
+    let mut srec = ScatterRecord::default();
+
+    NormalMapped::new(Arc::new(Reflect), flat.clone(), 1.0).scatter(&r, &rec, &mut srec);
+    assert!((srec.specular_ray.direction() - Vec3::new(0.0, 0.0, 1.0)).near_zero());
+    BumpMapped::new(Arc::new(Reflect), flat, 1.0).scatter(&r, &rec, &mut srec);
+    assert!((srec.specular_ray.direction() - Vec3::new(0.0, 0.0, 1.0)).near_zero());
+
+    // A normal tilted 45 degrees towards +x sends the reflection along +x
+    NormalMapped::new(Arc::new(Reflect), tilted, 1.0).scatter(&r, &rec, &mut srec);
+    assert!((srec.specular_ray.direction() - Vec3::new(1.0, 0.0, 0.0)).near_zero());
+}
+
+#[test]
+fn bump_ramp_tilts_the_shading_normal() {
+    // Height rises linearly along u, so the bumped surface is a plane of
+    // slope `scale` whose normal is (-scale, 0, 1) normalized
+    struct Ramp;
+    impl Texture for Ramp {
+        fn value(&self, u: f64, _v: f64, _p: &Point3) -> Color {
+            Color::from_float(u)
+        }
+    }
+
+    let r = Ray::new(Point3::new(0.3, 0.4, 1.0), Vec3::new(0.0, 0.0, -1.0));
+    let mut rec = HitRecord::new();
+    rec.p = Point3::new(0.3, 0.4, 0.0);
+    (rec.u, rec.v) = (0.3, 0.4);
+    rec.set_face_normal(&r, &Vec3::new(0.0, 0.0, 1.0));
+    rec.dpdu = Vec3::new(1.0, 0.0, 0.0);
+    rec.dpdv = Vec3::new(0.0, 1.0, 0.0);
+
+    struct Base;
+    impl Material for Base {
+        fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _srec: &mut ScatterRecord) -> bool {
+            false
+        }
+    }
+
+    let scale = 0.5;
+    let bumped = BumpMapped::new(Arc::new(Base), Arc::new(Ramp), scale).bumped(&rec);
+    let expected = Vec3::new(-scale, 0.0, 1.0).unit_vector();
+    assert!((bumped.normal() - expected).near_zero(), "{:?}", bumped.normal());
+    assert_eq!(bumped.geometric_normal(), Vec3::new(0.0, 0.0, 1.0));
+}
@@ -1,6 +1,11 @@
-use crate::common::{hittable, ray::Ray, util::Interval};
+use crate::common::{
+    ray::{Ray, RayDifferential},
+    sampler::Sampler,
+};
 use vec3::*;
 
+/// A pinhole at the origin looking down -z, turning pixels into rays. What
+/// the rays see is up to an `Integrator`.
 pub struct Camera {
     // origin: Point3,
     // lower_left_corner: Point3,
@@ -10,6 +15,9 @@ pub struct Camera {
     pub aspect_ratio: f64,
     pub image_width: i64,
     pub samples_per_pixel: i64,
+    // Rays are spread uniformly over the time the shutter is open
+    pub shutter_open: f64,
+    pub shutter_close: f64,
 
     // Private fields
     image_height: i64,
@@ -74,6 +82,8 @@ impl Camera {
             aspect_ratio,
             image_width,
             samples_per_pixel,
+            shutter_open: 0.0,
+            shutter_close: 1.0,
             image_height,
             center,
             pixel100_location,
@@ -83,42 +93,72 @@ impl Camera {
 
     }
 
-    pub fn render(&mut self, world: &dyn hittable::Hittable)
-    {
-        self.initialize();
-
-        println!("P3\n{0} {1}\n255", self.image_width, self.image_height);
-
-        for j in (0..self.image_height) {
-            eprint!("\r Scanlines remaining: {j}");
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::zero();
-                for _sample in 0..self.samples_per_pixel {
-                    let r = self.get_ray(i, j);
-                    pixel_color += Camera::ray_color(&r, world);
-                }
-                pixel_color.write_color_aa(self.samples_per_pixel);
-            }
-        }
-
-        eprintln!("\rDone rendering!");
-    }
-
-    pub fn get_ray(&self, i: i64, j: i64) -> Ray {
+    pub fn get_ray(&self, i: i64, j: i64, sampler: &mut dyn Sampler) -> Ray {
         // Construct a camera ray originating from the origin and directed at
-        // randomly sampled point around the pixel location (i, j)
+        // randomly sampled point around the pixel location (i, j), at a random
+        // time while the shutter is open
 
-        let offset = Vec3::<f64>::sample_square();
+        let (offset_x, offset_y) = sampler.get_2d();
         let pixel_sample = self.pixel100_location +
-            (self.pixel_delta_u * (i as f64 + offset.x)) +
-            (self.pixel_delta_v * (j as f64 + offset.y));
+            (self.pixel_delta_u * (i as f64 + offset_x - 0.5)) +
+            (self.pixel_delta_v * (j as f64 + offset_y - 0.5));
 
         let ray_origin = self.center;
         let ray_direction = pixel_sample - ray_origin;
-        Ray::new(ray_origin, ray_direction)
+        let ray_time = self.sample_time(sampler);
+        // Neighbouring pixels' rays, for texture filtering
+        let differentials = RayDifferential {
+            rx_origin: ray_origin,
+            rx_direction: ray_direction + self.pixel_delta_u,
+            ry_origin: ray_origin,
+            ry_direction: ray_direction + self.pixel_delta_v,
+        };
+        Ray::new_with_time(ray_origin, ray_direction, ray_time).with_differentials(Some(differentials))
+    }
+
+    /// A moment, spread uniformly over the time the shutter is open.
+    pub fn sample_time(&self, sampler: &mut dyn Sampler) -> f64 {
+        if self.shutter_close > self.shutter_open {
+            self.shutter_open + (self.shutter_close - self.shutter_open) * sampler.get_1d()
+        } else {
+            self.shutter_open
+        }
     }
 
-    fn initialize(&mut self)
+    pub fn image_height(&self) -> i64 {
+        self.image_height
+    }
+
+    /// Where `p` shows up in the image: the pixel it falls in and the camera's
+    /// importance for it times the cosine to the view axis, 1 / (A cos^3) for
+    /// an image of area A at unit distance. That is also the density per
+    /// steradian, over the whole image, of `get_ray` picking the direction.
+    /// None if `p` is behind the camera or out of frame.
+    pub fn importance(&self, p: &Point3) -> Option<(i64, i64, f64)> {
+        let image_u = self.pixel_delta_u * self.image_width as f64;
+        let image_v = self.pixel_delta_v * self.image_height as f64;
+        let upper_left = self.pixel100_location - (self.pixel_delta_u + self.pixel_delta_v) * 0.5;
+        // The image plane lies one unit along the axis
+        let axis = upper_left + (image_u + image_v) * 0.5 - self.center;
+        let d = *p - self.center;
+        let along = d.dot(&axis);
+        if along <= 0.0 {
+            return None;
+        }
+
+        let on_plane = self.center + d / along - upper_left;
+        let x = on_plane.dot(&self.pixel_delta_u) / self.pixel_delta_u.length_squared();
+        let y = on_plane.dot(&self.pixel_delta_v) / self.pixel_delta_v.length_squared();
+        if !(0.0..self.image_width as f64).contains(&x) || !(0.0..self.image_height as f64).contains(&y) {
+            return None;
+        }
+        let cos_theta = along / d.length();
+        let area = image_u.length() * image_v.length();
+        Some((x as i64, y as i64, 1.0 / (area * cos_theta * cos_theta * cos_theta)))
+    }
+
+    /// Works out where the pixels lie. Call after changing the image size.
+    pub fn initialize(&mut self)
     {
         self.image_height = (self.image_width as f64 / self.aspect_ratio) as i64;
         self.image_height = if self.image_height < 1 {
@@ -148,17 +188,26 @@ impl Camera {
             - viewport_v / 2.0;
         self.pixel100_location = viewport_upper_left + (self.pixel_delta_u + self.pixel_delta_v) * 0.5;
     }
+}
 
-    fn ray_color(r: &Ray, world: &dyn hittable::Hittable) -> Color {
-        let mut rec = hittable::HitRecord::new();
-        if world.hit(r, Interval::new(0.0, f64::INFINITY), &mut rec) {
-            return (rec.normal() + Color::from_float(1.0)) * 0.5;
-        }
-
-        let unit_direction = r.direction().unit_vector();
-        let t = 0.5 * (unit_direction.y + 1.0);
-        let color1 = Color::from_float(1.0) * (1.0 - t);
-        let color2 = Color::new(0.5, 0.7, 1.0) * t;
-        color1 + color2
+#[test]
+fn rays_land_in_their_pixels() {
+    use crate::common::sampler::IndependentSampler;
+
+    let mut cam = Camera::new(16.0 / 9.0, 64, 1);
+    cam.initialize();
+    let mut sampler = IndependentSampler;
+    for (i, j) in [(0, 0), (63, 35), (20, 7), (41, 30)] {
+        let r = cam.get_ray(i, j, &mut sampler);
+        let (x, y, importance) = cam.importance(&r.at(3.0)).unwrap();
+        assert_eq!((x, y), (i, j));
+
+        // Importance falls off with the cube of the cosine to the axis over
+        // an image plane 2 high and 2 * 64 / 36 wide
+        let cos_theta = -r.direction().unit_vector().z;
+        let expected = 1.0 / (4.0 * 64.0 / 36.0 * cos_theta.powi(3));
+        assert!((importance - expected).abs() < 1e-9 * expected);
     }
+    assert!(cam.importance(&Point3::new(0.0, 0.0, 1.0)).is_none());
+    assert!(cam.importance(&Point3::new(5.0, 0.0, -1.0)).is_none());
 }
@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use crate::common::csg::Solid;
+use crate::common::hittable::{Hittable, HitRecord};
+use crate::common::material::Material;
+use crate::common::ray::*;
+use crate::common::util::{azimuth_u, radial_direction, solve_quadratic, Interval};
+use vec3::*;
+
+/// A cone along the y axis with its apex `height` above the base, closed by a
+/// disk at the base. `center` is the middle of the base.
+pub struct Cone {
+    center: Point3,
+    radius: f64,
+    height: f64,
+    mat: Arc<dyn Material>,
+}
+
+impl Cone {
+    pub fn new(c: Point3, r: f64, h: f64, mat: Arc<dyn Material>) -> Self {
+        Cone {
+            center: c,
+            radius: r,
+            height: h,
+            mat,
+        }
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let oc = r.origin() - self.center;
+        let dir = r.direction();
+        let mut closest_so_far = ray_t.max;
+        // (t, outward normal, u, v, dp/dv) of the nearest accepted intersection
+        let mut nearest = None;
+
+        // Side: x^2 + z^2 = k^2 (height - y)^2 with 0 <= y <= height
+        let k2 = (self.radius / self.height) * (self.radius / self.height);
+        let to_apex = self.height - oc.y;
+        let a = dir.x * dir.x + dir.z * dir.z - k2 * dir.y * dir.y;
+        let b = 2.0 * (oc.x * dir.x + oc.z * dir.z + k2 * to_apex * dir.y);
+        let c = oc.x * oc.x + oc.z * oc.z - k2 * to_apex * to_apex;
+        if let Some((t0, t1)) = solve_quadratic(a, b, c) {
+            for root in [t0, t1] {
+                let p = oc + dir * root;
+                if Interval::new(ray_t.min, closest_so_far).surrounds(root)
+                    && (0.0..=self.height).contains(&p.y)
+                {
+                    let gradient = Vec3::new(p.x, k2 * (self.height - p.y), p.z);
+                    // The apex has no well defined normal, point it up the axis
+                    let outward_normal = if gradient.length_squared() > 1e-16 {
+                        gradient.unit_vector()
+                    } else {
+                        Vec3::new(0.0, 1.0, 0.0)
+                    };
+                    let u = azimuth_u(p.x, p.z);
+                    let v = p.y / self.height;
+                    // The radius shrinks linearly to zero at the apex
+                    let dpdv = Vec3::new(0.0, self.height, 0.0) - radial_direction(&p) * self.radius;
+                    closest_so_far = root;
+                    nearest = Some((root, outward_normal, u, v, dpdv));
+                }
+            }
+        }
+
+        // Base cap
+        if dir.y != 0.0 {
+            let root = -oc.y / dir.y;
+            let p = oc + dir * root;
+            let rho2 = p.x * p.x + p.z * p.z;
+            if Interval::new(ray_t.min, closest_so_far).surrounds(root)
+                && rho2 <= self.radius * self.radius
+            {
+                let u = azimuth_u(p.x, p.z);
+                let v = rho2.sqrt() / self.radius;
+                let dpdv = radial_direction(&p) * self.radius;
+                nearest = Some((root, Vec3::new(0.0, -1.0, 0.0), u, v, dpdv));
+            }
+        }
+
+        let Some((t, outward_normal, u, v, dpdv)) = nearest else {
+            return false;
+        };
+        rec.t = t;
+        rec.p = r.at(rec.t);
+        rec.u = u;
+        rec.v = v;
+        let local = rec.p - self.center;
+        rec.dpdu = Vec3::new(local.z, 0.0, -local.x) * (2.0 * std::f64::consts::PI);
+        rec.dpdv = dpdv;
+        // Treated as locally flat when propagating ray differentials
+        rec.dndu = Vec3::zero();
+        rec.dndv = Vec3::zero();
+        rec.set_face_normal(r, &outward_normal);
+        rec.mat = Some(self.mat.clone());
+
+        true
+    }
+}
+
+impl Solid for Cone {}
+
+#[test]
+fn cone_hits_side_and_base() {
+    let mat = Arc::new(crate::common::material::Lambertian::new(Color::from_float(0.5)));
+    let cone = Cone::new(Point3::zero(), 1.0, 2.0, mat);
+    let mut rec = HitRecord::new();
+
+    // Halfway up the radius has halved, and the normal leans up by the slope
+    let r = Ray::new(Point3::new(0.0, 1.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+    assert!(cone.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+    assert!((rec.t - 4.5).abs() < 1e-9);
+    assert!((rec.normal() - Vec3::new(0.0, 0.5, 1.0).unit_vector()).length() < 1e-9);
+    assert!((rec.u - 0.25).abs() < 1e-9 && (rec.v - 0.5).abs() < 1e-9);
+
+    // The base from below, before the side behind it
+    let r = Ray::new(Point3::new(0.5, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+    assert!(cone.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+    assert!((rec.t - 5.0).abs() < 1e-9);
+    assert!((rec.normal() - Vec3::new(0.0, -1.0, 0.0)).length() < 1e-9);
+    assert!((rec.u - 0.5).abs() < 1e-9 && (rec.v - 0.5).abs() < 1e-9);
+
+    // Near the apex the cone is too thin to reach
+    let r = Ray::new(Point3::new(0.9, 1.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+    assert!(!cone.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use crate::common::hittable::{Hittable, HitRecord};
+use crate::common::material::{Isotropic, Material};
+use crate::common::ray::*;
+use crate::common::util::{random_double, Interval};
+use vec3::*;
+
+/// A volume of uniform density filling a closed boundary shape, such as fog or
+/// smoke. Rays scatter inside it with probability given by the Beer-Lambert law.
+pub struct ConstantMedium {
+    boundary: Arc<dyn Hittable>,
+    neg_inv_density: f64,
+    phase_function: Arc<dyn Material>,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Arc<dyn Hittable>, density: f64, albedo: Color) -> Self {
+        ConstantMedium {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase_function: Arc::new(Isotropic::new(albedo)),
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        // Find where the ray enters and leaves the boundary, even if it starts inside
+        let mut rec1 = HitRecord::new();
+        let mut rec2 = HitRecord::new();
+
+        if !self.boundary.hit(r, Interval::UNIVERSE, &mut rec1) {
+            return false;
+        }
+
+        if !self.boundary.hit(r, Interval::new(rec1.t + 0.0001, f64::INFINITY), &mut rec2) {
+            return false;
+        }
+
+        rec1.t = rec1.t.max(ray_t.min);
+        rec2.t = rec2.t.min(ray_t.max);
+        if rec1.t >= rec2.t {
+            return false;
+        }
+        rec1.t = rec1.t.max(0.0);
+
+        // Sample an exponential free-flight distance and see if it ends inside
+        let ray_length = r.direction().length();
+        let distance_inside_boundary = (rec2.t - rec1.t) * ray_length;
+        let hit_distance = self.neg_inv_density * (1.0 - random_double()).ln();
+
+        if hit_distance > distance_inside_boundary {
+            return false;
+        }
+
+        rec.t = rec1.t + hit_distance / ray_length;
+        rec.p = r.at(rec.t);
+        rec.set_arbitrary_normal();
+        rec.mat = Some(self.phase_function.clone());
+
+        true
+    }
+}
+
+#[test]
+fn slab_transmission_follows_beer_lambert() {
+    // The region 0 < z < thickness, open in x and y
+    struct Slab {
+        thickness: f64,
+    }
+
+    impl Hittable for Slab {
+        fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+            let dz = r.direction().z;
+            let mut roots = [-r.origin().z / dz, (self.thickness - r.origin().z) / dz];
+            roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            match roots.into_iter().find(|&t| ray_t.surrounds(t)) {
+                Some(t) => {
+                    rec.t = t;
+                    rec.p = r.at(t);
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+
+    let density = 0.7;
+    let thickness = 2.0;
+    let medium = ConstantMedium::new(Arc::new(Slab { thickness }), density, Color::one());
+
+    let trials = 100_000;
+    let mut rec = HitRecord::new();
+    let scattered = (0..trials)
+        .filter(|_| {
+            // Direction is not unit length, which must not change the result
+            let r = Ray::new(Point3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 3.0));
+            medium.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec)
+        })
+        .count();
+
+    let expected = 1.0 - (-density * thickness).exp();
+    let measured = scattered as f64 / trials as f64;
+    assert!((measured - expected).abs() < 0.01, "{measured} != {expected}");
+}
@@ -0,0 +1,226 @@
+use std::sync::Arc;
+
+use crate::common::hittable::{Hittable, HitRecord};
+use crate::common::ray::*;
+use crate::common::util::Interval;
+
+/// A stretch of a ray spent inside a solid, with the surface crossed at each
+/// end.
+#[derive(Clone, Default)]
+pub struct Span {
+    pub enter: HitRecord,
+    pub exit: HitRecord,
+}
+
+/// A closed shape that can report every interval of a ray lying inside it,
+/// which is what constructive solid geometry needs to combine shapes.
+pub trait Solid: Hittable {
+    /// Appends the spans inside the solid along the whole ray, ignoring any
+    /// interval restriction, in increasing order of t.
+    ///
+    /// The default walks the surface crossings with repeated calls to `hit`,
+    /// pairing entries and exits by which face was hit.
+    fn spans(&self, r: &Ray, spans: &mut Vec<Span>) {
+        const MAX_CROSSINGS: usize = 64;
+
+        let mut t_min = f64::NEG_INFINITY;
+        let mut enter: Option<HitRecord> = None;
+        for _ in 0..MAX_CROSSINGS {
+            let mut rec = HitRecord::new();
+            if !self.hit(r, Interval::new(t_min, f64::INFINITY), &mut rec) {
+                break;
+            }
+            t_min = rec.t + 1e-9 * rec.t.abs().max(1.0);
+
+            if rec.front_face() {
+                enter = Some(rec);
+            } else if let Some(enter_rec) = enter.take() {
+                spans.push(Span {
+                    enter: enter_rec,
+                    exit: rec,
+                });
+            }
+        }
+    }
+}
+
+/// How a boolean node decides it is inside from its two children.
+#[derive(Clone, Copy)]
+enum Operation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl Operation {
+    fn inside(self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            Operation::Union => in_a || in_b,
+            Operation::Intersection => in_a && in_b,
+            Operation::Difference => in_a && !in_b,
+        }
+    }
+}
+
+/// Merges the span lists of two solids under a boolean operation by sweeping
+/// their boundaries in order and emitting a span whenever the combined
+/// inside state switches on and back off.
+fn combine(op: Operation, a: &[Span], b: &[Span], out: &mut Vec<Span>) {
+    // (t, is the boundary of a, entering, record)
+    let mut events: Vec<(f64, bool, bool, &HitRecord)> = Vec::with_capacity(2 * (a.len() + b.len()));
+    for (spans, is_a) in [(a, true), (b, false)] {
+        for span in spans {
+            events.push((span.enter.t, is_a, true, &span.enter));
+            events.push((span.exit.t, is_a, false, &span.exit));
+        }
+    }
+    events.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+
+    let (mut in_a, mut in_b) = (false, false);
+    let mut current: Option<HitRecord> = None;
+    for (_, is_a, entering, rec) in events {
+        let was_inside = op.inside(in_a, in_b);
+        if is_a {
+            in_a = entering;
+        } else {
+            in_b = entering;
+        }
+        let is_inside = op.inside(in_a, in_b);
+
+        if !was_inside && is_inside {
+            let mut enter = rec.clone();
+            enter.set_front_face(true);
+            current = Some(enter);
+        } else if was_inside && !is_inside {
+            let mut exit = rec.clone();
+            exit.set_front_face(false);
+            if let Some(enter) = current.take() {
+                out.push(Span { enter, exit });
+            }
+        }
+    }
+}
+
+/// The nearest span boundary inside `ray_t`.
+fn nearest_boundary(spans: &[Span], ray_t: &Interval, rec: &mut HitRecord) -> bool {
+    for span in spans {
+        for boundary in [&span.enter, &span.exit] {
+            if ray_t.surrounds(boundary.t) {
+                *rec = boundary.clone();
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// A node combining two solids with a boolean operation.
+struct Boolean {
+    op: Operation,
+    a: Arc<dyn Solid>,
+    b: Arc<dyn Solid>,
+}
+
+impl Boolean {
+    fn spans(&self, r: &Ray, spans: &mut Vec<Span>) {
+        let mut a_spans = Vec::new();
+        let mut b_spans = Vec::new();
+        self.a.spans(r, &mut a_spans);
+        // Nothing can come of the second operand if the first is empty
+        if a_spans.is_empty() && !matches!(self.op, Operation::Union) {
+            return;
+        }
+        self.b.spans(r, &mut b_spans);
+        combine(self.op, &a_spans, &b_spans, spans);
+    }
+
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let mut spans = Vec::new();
+        self.spans(r, &mut spans);
+        nearest_boundary(&spans, &ray_t, rec)
+    }
+}
+
+macro_rules! boolean_node {
+    ($(#[$doc:meta])* $name:ident, $op:expr) => {
+        $(#[$doc])*
+        pub struct $name(Boolean);
+
+        impl $name {
+            pub fn new(a: Arc<dyn Solid>, b: Arc<dyn Solid>) -> Self {
+                $name(Boolean { op: $op, a, b })
+            }
+        }
+
+        impl Hittable for $name {
+            fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+                self.0.hit(r, ray_t, rec)
+            }
+        }
+
+        impl Solid for $name {
+            fn spans(&self, r: &Ray, spans: &mut Vec<Span>) {
+                self.0.spans(r, spans)
+            }
+        }
+    };
+}
+
+boolean_node!(
+    /// Everything inside either solid.
+    Union,
+    Operation::Union
+);
+boolean_node!(
+    /// Only what is inside both solids.
+    Intersection,
+    Operation::Intersection
+);
+boolean_node!(
+    /// The first solid with the second carved out of it.
+    Difference,
+    Operation::Difference
+);
+
+#[test]
+fn difference_carves_a_hole() {
+    use crate::common::cuboid::Cuboid;
+    use crate::common::material::Lambertian;
+    use crate::common::sphere::Sphere;
+    use vec3::*;
+
+    let mat = Arc::new(Lambertian::new(Color::from_float(0.5)));
+    let cube = Arc::new(Cuboid::new(Point3::from_float(-1.0), Point3::from_float(1.0), mat.clone()));
+    let ball = Arc::new(Sphere::new(Point3::new(0.0, 0.0, 1.0), 0.5, mat));
+    let carved = Difference::new(cube.clone(), ball.clone());
+
+    // Straight into the hole: the cube face at z = 1 is gone, so the first
+    // surface is the bottom of the dent at z = 0.5, seen from outside
+    let r = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+    let mut rec = HitRecord::new();
+    assert!(carved.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+    assert!((rec.t - 4.5).abs() < 1e-9);
+    assert!(rec.front_face());
+    assert!((rec.normal().z - 1.0).abs() < 1e-9);
+
+    let mut spans = Vec::new();
+    carved.spans(&r, &mut spans);
+    assert_eq!(spans.len(), 1);
+    assert!((spans[0].exit.t - 6.0).abs() < 1e-9);
+
+    // Beside the hole the cube is untouched
+    let r = Ray::new(Point3::new(0.8, 0.8, 5.0), Vec3::new(0.0, 0.0, -1.0));
+    assert!(carved.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+    assert!((rec.t - 4.0).abs() < 1e-9);
+
+    // The union and intersection along the axis
+    let r = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+    let mut spans = Vec::new();
+    Union::new(cube.clone(), ball.clone()).spans(&r, &mut spans);
+    assert_eq!(spans.len(), 1);
+    assert!((spans[0].enter.t - 3.5).abs() < 1e-9);
+    let mut spans = Vec::new();
+    Intersection::new(cube, ball).spans(&r, &mut spans);
+    assert_eq!(spans.len(), 1);
+    assert!((spans[0].enter.t - 4.0).abs() < 1e-9 && (spans[0].exit.t - 4.5).abs() < 1e-9);
+}
@@ -0,0 +1,169 @@
+use std::sync::Arc;
+
+use crate::common::csg::{Solid, Span};
+use crate::common::hittable::{Hittable, HitRecord};
+use crate::common::material::Material;
+use crate::common::ray::*;
+use crate::common::util::{random_double, Interval};
+use vec3::*;
+
+/// An axis-aligned box between two opposite corners.
+pub struct Cuboid {
+    min: Point3,
+    max: Point3,
+    mat: Arc<dyn Material>,
+}
+
+impl Cuboid {
+    pub fn new(a: Point3, b: Point3, mat: Arc<dyn Material>) -> Self {
+        Cuboid {
+            min: Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+            mat,
+        }
+    }
+
+    /// Entry and exit parameters along the whole ray, with the axis of the
+    /// slab crossed at each.
+    fn slabs(&self, r: &Ray) -> Option<((f64, usize), (f64, usize))> {
+        let mut enter = (f64::NEG_INFINITY, 0);
+        let mut exit = (f64::INFINITY, 0);
+        for axis in 0..3 {
+            let inv_d = 1.0 / r.direction()[axis];
+            let mut t0 = (self.min[axis] - r.origin()[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - r.origin()[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            if t0 > enter.0 {
+                enter = (t0, axis);
+            }
+            if t1 < exit.0 {
+                exit = (t1, axis);
+            }
+            if exit.0 <= enter.0 {
+                return None;
+            }
+        }
+        Some((enter, exit))
+    }
+
+    fn area(&self) -> f64 {
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    fn record(&self, r: &Ray, t: f64, axis: usize, rec: &mut HitRecord) {
+        rec.t = t;
+        rec.p = r.at(t);
+
+        // The face is on the max side if it is closer to max than min
+        let mut outward_normal = Vec3::zero();
+        outward_normal[axis] = if rec.p[axis] - self.min[axis] > self.max[axis] - rec.p[axis] {
+            1.0
+        } else {
+            -1.0
+        };
+        rec.set_face_normal(r, &outward_normal);
+
+        // Planar mapping using the two axes spanning the face
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        rec.u = (rec.p[a] - self.min[a]) / (self.max[a] - self.min[a]);
+        rec.v = (rec.p[b] - self.min[b]) / (self.max[b] - self.min[b]);
+        rec.dpdu = Vec3::zero();
+        rec.dpdu[a] = self.max[a] - self.min[a];
+        rec.dpdv = Vec3::zero();
+        rec.dpdv[b] = self.max[b] - self.min[b];
+        rec.dndu = Vec3::zero();
+        rec.dndv = Vec3::zero();
+        rec.mat = Some(self.mat.clone());
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let Some(((t_enter, enter_axis), (t_exit, exit_axis))) = self.slabs(r) else {
+            return false;
+        };
+
+        if ray_t.surrounds(t_enter) {
+            self.record(r, t_enter, enter_axis, rec);
+        } else if ray_t.surrounds(t_exit) {
+            self.record(r, t_exit, exit_axis, rec);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(HitRecord, f64)> {
+        // Pick a face by its share of the area, then a point on it uniformly,
+        // and look back at that from just outside
+        let d = self.max - self.min;
+        let mut pick = random_double() * self.area();
+        let (axis, on_max) = (0..6)
+            .map(|i| (i % 3, i >= 3))
+            .find(|&(axis, _)| {
+                pick -= d[(axis + 1) % 3] * d[(axis + 2) % 3];
+                pick < 0.0
+            })
+            .unwrap_or((2, true));
+        let mut p = self.min + d * Vec3::new(random_double(), random_double(), random_double());
+        p[axis] = if on_max { self.max[axis] } else { self.min[axis] };
+        let mut outward_normal = Vec3::zero();
+        outward_normal[axis] = if on_max { 1.0 } else { -1.0 };
+        let mut rec = HitRecord::new();
+        let r = Ray::new_with_time(p + outward_normal, -outward_normal, time);
+        if !self.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec) {
+            return None;
+        }
+        Some((rec, 1.0 / self.area()))
+    }
+
+    fn surface_pdf(&self, origin: &Point3, direction: &Vec3<f64>, time: f64) -> f64 {
+        let mut rec = HitRecord::new();
+        let r = Ray::new_with_time(*origin, *direction, time);
+        if !self.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
+        1.0 / self.area()
+    }
+}
+
+impl Solid for Cuboid {
+    fn spans(&self, r: &Ray, spans: &mut Vec<Span>) {
+        if let Some(((t_enter, enter_axis), (t_exit, exit_axis))) = self.slabs(r) {
+            let mut span = Span::default();
+            self.record(r, t_enter, enter_axis, &mut span.enter);
+            self.record(r, t_exit, exit_axis, &mut span.exit);
+            spans.push(span);
+        }
+    }
+}
+
+#[test]
+fn cuboid_surface_samples() {
+    let mat = Arc::new(crate::common::material::Lambertian::new(Color::from_float(0.5)));
+    let cuboid = Cuboid::new(Point3::zero(), Point3::new(1.0, 2.0, 3.0), mat);
+    let area = 2.0 * (2.0 + 6.0 + 3.0);
+
+    // Faces come up in proportion to their area, here the two 2 x 3 faces
+    // facing along x
+    let n = 22_000;
+    let mut along_x = 0;
+    for _ in 0..n {
+        let (rec, pdf) = cuboid.sample_surface(0.0).unwrap();
+        assert!((pdf - 1.0 / area).abs() < 1e-12);
+        let on_face = (0..3).any(|axis| rec.p[axis] == 0.0 || rec.p[axis] == [1.0, 2.0, 3.0][axis]);
+        assert!(on_face, "{:?}", rec.p);
+        if rec.normal().x != 0.0 {
+            along_x += 1;
+        }
+    }
+    let fraction = along_x as f64 / n as f64;
+    assert!((fraction - 12.0 / area).abs() < 0.02, "{fraction}");
+
+    let origin = Point3::new(0.5, 1.0, 5.0);
+    assert_eq!(cuboid.surface_pdf(&origin, &Vec3::new(0.0, 0.0, -2.0), 0.0), 1.0 / area);
+    assert_eq!(cuboid.surface_pdf(&origin, &Vec3::new(0.0, 1.0, 0.0), 0.0), 0.0);
+}
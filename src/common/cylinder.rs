@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use crate::common::csg::Solid;
+use crate::common::hittable::{Hittable, HitRecord};
+use crate::common::material::Material;
+use crate::common::ray::*;
+use crate::common::util::{azimuth_u, radial_direction, solve_quadratic, Interval};
+use vec3::*;
+
+/// A cylinder along the y axis, closed by a disk at each end. `center` is the
+/// middle of the bottom cap.
+pub struct Cylinder {
+    center: Point3,
+    radius: f64,
+    height: f64,
+    mat: Arc<dyn Material>,
+}
+
+impl Cylinder {
+    pub fn new(c: Point3, r: f64, h: f64, mat: Arc<dyn Material>) -> Self {
+        Cylinder {
+            center: c,
+            radius: r,
+            height: h,
+            mat,
+        }
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let oc = r.origin() - self.center;
+        let dir = r.direction();
+        let mut closest_so_far = ray_t.max;
+        // (t, outward normal, u, v, dp/dv) of the nearest accepted intersection
+        let mut nearest = None;
+
+        // Side wall: x^2 + z^2 = r^2 with 0 <= y <= height
+        let a = dir.x * dir.x + dir.z * dir.z;
+        let b = 2.0 * (oc.x * dir.x + oc.z * dir.z);
+        let c = oc.x * oc.x + oc.z * oc.z - self.radius * self.radius;
+        if let Some((t0, t1)) = solve_quadratic(a, b, c) {
+            for root in [t0, t1] {
+                let p = oc + dir * root;
+                if Interval::new(ray_t.min, closest_so_far).surrounds(root)
+                    && (0.0..=self.height).contains(&p.y)
+                {
+                    let outward_normal = Vec3::new(p.x, 0.0, p.z) / self.radius;
+                    let u = azimuth_u(p.x, p.z);
+                    let v = p.y / self.height;
+                    closest_so_far = root;
+                    nearest = Some((root, outward_normal, u, v, Vec3::new(0.0, self.height, 0.0)));
+                }
+            }
+        }
+
+        // End caps, mapped radially so the seam lines up with the wall
+        for (cap_y, normal_y) in [(0.0, -1.0), (self.height, 1.0)] {
+            if dir.y == 0.0 {
+                break;
+            }
+            let root = (cap_y - oc.y) / dir.y;
+            let p = oc + dir * root;
+            let rho2 = p.x * p.x + p.z * p.z;
+            if Interval::new(ray_t.min, closest_so_far).surrounds(root)
+                && rho2 <= self.radius * self.radius
+            {
+                let u = azimuth_u(p.x, p.z);
+                let v = rho2.sqrt() / self.radius;
+                let dpdv = radial_direction(&p) * self.radius;
+                closest_so_far = root;
+                nearest = Some((root, Vec3::new(0.0, normal_y, 0.0), u, v, dpdv));
+            }
+        }
+
+        let Some((t, outward_normal, u, v, dpdv)) = nearest else {
+            return false;
+        };
+        rec.t = t;
+        rec.p = r.at(rec.t);
+        rec.u = u;
+        rec.v = v;
+        let local = rec.p - self.center;
+        rec.dpdu = Vec3::new(local.z, 0.0, -local.x) * (2.0 * std::f64::consts::PI);
+        rec.dpdv = dpdv;
+        // Only the side curves, and only around the axis
+        rec.dndu = if outward_normal.y == 0.0 {
+            rec.dpdu / self.radius
+        } else {
+            Vec3::zero()
+        };
+        rec.dndv = Vec3::zero();
+        rec.set_face_normal(r, &outward_normal);
+        rec.mat = Some(self.mat.clone());
+
+        true
+    }
+}
+
+impl Solid for Cylinder {}
+
+#[test]
+fn cylinder_hits_side_and_caps() {
+    let mat = Arc::new(crate::common::material::Lambertian::new(Color::from_float(0.5)));
+    let cylinder = Cylinder::new(Point3::zero(), 1.0, 2.0, mat);
+    let mut rec = HitRecord::new();
+
+    // The side a quarter of the way up, a quarter turn round from the seam
+    let r = Ray::new(Point3::new(0.0, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+    assert!(cylinder.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+    assert!((rec.t - 4.0).abs() < 1e-9);
+    assert!((rec.normal() - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+    assert!((rec.u - 0.25).abs() < 1e-9 && (rec.v - 0.25).abs() < 1e-9);
+
+    // The top cap, halfway out from the axis
+    let r = Ray::new(Point3::new(0.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+    assert!(cylinder.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+    assert!((rec.t - 3.0).abs() < 1e-9);
+    assert!((rec.normal() - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-9);
+    assert!((rec.u - 0.5).abs() < 1e-9 && (rec.v - 0.5).abs() < 1e-9);
+
+    // Passing over the top misses
+    let r = Ray::new(Point3::new(0.0, 3.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+    assert!(!cylinder.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+}
@@ -0,0 +1,123 @@
+/// A piecewise-constant density over [0, 1) proportional to a list of
+/// non-negative weights, sampled by inverting its CDF.
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    pub fn new(func: Vec<f64>) -> Self {
+        assert!(!func.is_empty(), "A distribution needs at least one piece.");
+        let n = func.len();
+        let mut cdf = Vec::with_capacity(n + 1);
+        cdf.push(0.0);
+        for i in 0..n {
+            cdf.push(cdf[i] + func[i].max(0.0) / n as f64);
+        }
+        let integral = cdf[n];
+        // With nothing to go on every piece is equally likely
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if integral > 0.0 { *c / integral } else { i as f64 / n as f64 };
+        }
+        Distribution1D { func, cdf, integral }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    /// The average of the weights, the integral of the function over [0, 1).
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    /// Maps `u` in [0, 1) to a point x in [0, 1), returning x, its density
+    /// and the index of the piece it fell in.
+    pub fn sample(&self, u: f64) -> (f64, f64, usize) {
+        // The last cdf entry at or below u
+        let index = self.cdf.partition_point(|&c| c <= u).clamp(1, self.count()) - 1;
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0.0 { (u - self.cdf[index]) / width } else { 0.0 };
+        let x = ((index as f64 + offset) / self.count() as f64).min(1.0 - f64::EPSILON);
+        (x, self.pdf(index), index)
+    }
+
+    /// Density of the piece at `index`.
+    pub fn pdf(&self, index: usize) -> f64 {
+        if self.integral > 0.0 {
+            self.func[index].max(0.0) / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+/// A piecewise-constant density over the unit square from a row-major grid of
+/// weights, sampled by picking a row from the marginal distribution and then
+/// a column within that row.
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(func: &[f64], width: usize, height: usize) -> Self {
+        assert_eq!(func.len(), width * height, "Weight count does not match the grid size.");
+        let conditional: Vec<Distribution1D> = func
+            .chunks_exact(width)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(conditional.iter().map(|c| c.integral()).collect());
+        Distribution2D {
+            conditional,
+            marginal,
+        }
+    }
+
+    /// Maps (u1, u2) to a point (s, t) of the unit square with t down the
+    /// rows, returning it and its density.
+    pub fn sample(&self, u1: f64, u2: f64) -> ((f64, f64), f64) {
+        let (t, pdf_row, row) = self.marginal.sample(u2);
+        let (s, pdf_column, _) = self.conditional[row].sample(u1);
+        ((s, t), pdf_row * pdf_column)
+    }
+
+    /// Density of picking the point (s, t).
+    pub fn pdf(&self, s: f64, t: f64) -> f64 {
+        let row = ((t * self.marginal.count() as f64) as usize).min(self.marginal.count() - 1);
+        let conditional = &self.conditional[row];
+        let column = ((s * conditional.count() as f64) as usize).min(conditional.count() - 1);
+        self.marginal.pdf(row) * conditional.pdf(column)
+    }
+}
+
+#[test]
+fn distributions_follow_their_weights() {
+    let d = Distribution1D::new(vec![1.0, 3.0, 0.0, 4.0]);
+    assert_eq!(d.integral(), 2.0);
+    assert_eq!(d.sample(0.0), (0.0, 0.5, 0));
+    // Half the mass lies past the empty third piece
+    let (x, pdf, index) = d.sample(0.5);
+    assert_eq!((x, pdf, index), (0.75, 2.0, 3));
+    let (x, _, index) = d.sample(0.25);
+    assert_eq!(index, 1);
+    assert!((x - (0.25 + 1.0 / 12.0)).abs() < 1e-12, "{x}");
+
+    // Over many samples the 2D density sums to one in each cell's share
+    let weights = [0.0, 1.0, 2.0, 5.0, 0.0, 0.0];
+    let d = Distribution2D::new(&weights, 3, 2);
+    let n = 60_000;
+    let mut counts = [0usize; 6];
+    for i in 0..n {
+        let u1 = crate::common::util::random_double();
+        let u2 = (i as f64 + 0.5) / n as f64;
+        let ((s, t), pdf) = d.sample(u1, u2);
+        assert_eq!(pdf, d.pdf(s, t));
+        counts[(t * 2.0) as usize * 3 + (s * 3.0) as usize] += 1;
+    }
+    for (count, weight) in counts.iter().zip(weights) {
+        let expected = weight / 8.0;
+        assert!((*count as f64 / n as f64 - expected).abs() < 0.01, "{counts:?}");
+    }
+}
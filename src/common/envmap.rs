@@ -0,0 +1,150 @@
+use core::f64;
+use std::sync::Arc;
+
+use crate::common::{
+    background::Background,
+    distribution::Distribution2D,
+    image::Image,
+    pdf::Pdf,
+    ray::Ray,
+    util::random_double,
+};
+use vec3::*;
+
+/// Light from every direction read out of a latitude-longitude image: the top
+/// row looks straight up, the bottom straight down and the middle column
+/// along -z before rotating. Directions are sampled in proportion to the
+/// image's brightness, so small bright features such as the sun are found
+/// by shadow rays rather than left to chance.
+pub struct EnvironmentMap {
+    image: Arc<Image>,
+    // Turn about +y, as sine and cosine
+    sin_rotation: f64,
+    cos_rotation: f64,
+    intensity: f64,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    /// The map turned `rotation` degrees counter-clockwise seen from above,
+    /// with every pixel scaled by `intensity`.
+    pub fn new(image: Arc<Image>, rotation: f64, intensity: f64) -> Self {
+        // Rows near the poles cover less of the sphere
+        let (width, height) = (image.width(), image.height());
+        let mut weights = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let sin_theta = (f64::consts::PI * (y as f64 + 0.5) / height as f64).sin();
+            for x in 0..width {
+                weights.push(luminance(image.pixel(x, y)) * sin_theta);
+            }
+        }
+        let (sin_rotation, cos_rotation) = rotation.to_radians().sin_cos();
+        EnvironmentMap {
+            distribution: Distribution2D::new(&weights, width, height),
+            image,
+            sin_rotation,
+            cos_rotation,
+            intensity,
+        }
+    }
+
+    // Image coordinates (s, t) in [0, 1) of a world direction
+    fn direction_to_st(&self, direction: &Vec3<f64>) -> (f64, f64) {
+        let d = direction.unit_vector();
+        let x = self.cos_rotation * d.x - self.sin_rotation * d.z;
+        let z = self.sin_rotation * d.x + self.cos_rotation * d.z;
+        let s = 0.5 + x.atan2(-z) / (2.0 * f64::consts::PI);
+        let t = d.y.clamp(-1.0, 1.0).acos() / f64::consts::PI;
+        (s.rem_euclid(1.0), t)
+    }
+
+    fn st_to_direction(&self, s: f64, t: f64) -> Vec3<f64> {
+        let (sin_theta, cos_theta) = (f64::consts::PI * t).sin_cos();
+        let (sin_phi, cos_phi) = (2.0 * f64::consts::PI * (s - 0.5)).sin_cos();
+        let (x, z) = (sin_theta * sin_phi, -sin_theta * cos_phi);
+        Vec3::new(
+            self.cos_rotation * x + self.sin_rotation * z,
+            cos_theta,
+            -self.sin_rotation * x + self.cos_rotation * z,
+        )
+    }
+
+    // The texel under (s, t). Not interpolated, so that the light from each
+    // texel is exactly what the piecewise-constant sampling density expects
+    // and a bright sun cannot bleed into neighbours that are rarely sampled.
+    fn lookup(&self, s: f64, t: f64) -> Color {
+        let (width, height) = (self.image.width(), self.image.height());
+        let x = ((s * width as f64) as usize).min(width - 1);
+        let y = ((t * height as f64) as usize).min(height - 1);
+        self.image.pixel(x, y)
+    }
+}
+
+impl Background for EnvironmentMap {
+    fn value(&self, r: &Ray) -> Color {
+        let (s, t) = self.direction_to_st(&r.direction());
+        self.lookup(s, t) * self.intensity
+    }
+
+    fn pdf(&self) -> Option<&dyn Pdf> {
+        Some(self)
+    }
+}
+
+impl Pdf for EnvironmentMap {
+    fn value(&self, direction: &Vec3<f64>) -> f64 {
+        let (s, t) = self.direction_to_st(direction);
+        let sin_theta = (f64::consts::PI * t).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        // The image spans 2 pi by pi radians, squeezed by sin(theta) on the sphere
+        self.distribution.pdf(s, t) / (2.0 * f64::consts::PI * f64::consts::PI * sin_theta)
+    }
+
+    fn generate(&self) -> Vec3<f64> {
+        let ((s, t), _) = self.distribution.sample(random_double(), random_double());
+        self.st_to_direction(s, t)
+    }
+}
+
+fn luminance(c: Color) -> f64 {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+#[test]
+fn environment_sampling_finds_the_sun() {
+    use crate::common::pdf::SpherePdf;
+
+    // A dim sky with one bright texel just above the horizon
+    let (width, height) = (64, 32);
+    let with_sun = |sun: f64| {
+        let mut pixels = vec![Color::from_float(0.1); width * height];
+        pixels[15 * width + 40] = Color::from_float(sun);
+        EnvironmentMap::new(Arc::new(Image::new(width, height, pixels)), 30.0, 1.0)
+    };
+    let env = with_sun(10.0);
+
+    // Directions and image coordinates map back onto each other
+    for (s, t) in [(0.1, 0.2), (0.5, 0.5), (0.9, 0.7)] {
+        let (s2, t2) = env.direction_to_st(&env.st_to_direction(s, t));
+        assert!((s - s2).abs() < 1e-9 && (t - t2).abs() < 1e-9, "{s2} {t2}");
+    }
+
+    // The density integrates to one over the sphere
+    let n = 200_000;
+    let sphere = SpherePdf;
+    let integral = (0..n)
+        .map(|_| Pdf::value(&env, &sphere.generate()) / sphere.value(&Vec3::zero()))
+        .sum::<f64>()
+        / n as f64;
+    assert!((integral - 1.0).abs() < 0.05, "{integral}");
+
+    // Most samples head for a bright sun, which holds most of the light
+    let env = with_sun(10000.0);
+    let sun = env.st_to_direction(40.5 / 64.0, 15.5 / 32.0);
+    let hits = (0..1000)
+        .filter(|_| env.generate().unit_vector().dot(&sun) > 0.99)
+        .count();
+    assert!(hits > 900, "{hits}");
+}
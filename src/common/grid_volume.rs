@@ -0,0 +1,315 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::common::hittable::{Hittable, HitRecord};
+use crate::common::material::{Material, Volumetric};
+use crate::common::phase::PhaseFunction;
+use crate::common::ray::*;
+use crate::common::util::{random_double, Interval};
+use vec3::*;
+
+/// A dense grid of density samples spanning the unit cube, stored with x
+/// varying fastest and then y.
+pub struct VoxelGrid {
+    nx: usize,
+    ny: usize,
+    nz: usize,
+    data: Vec<f32>,
+}
+
+impl VoxelGrid {
+    pub fn new(nx: usize, ny: usize, nz: usize, data: Vec<f32>) -> Self {
+        assert!(nx > 0 && ny > 0 && nz > 0, "Voxel grids need at least one voxel along each axis.");
+        assert_eq!(data.len(), nx * ny * nz, "Voxel data does not match the grid size.");
+        VoxelGrid { nx, ny, nz, data }
+    }
+
+    /// Fills the grid by evaluating `density` at the center of every voxel.
+    pub fn from_fn(nx: usize, ny: usize, nz: usize, density: impl Fn(Point3) -> f64) -> Self {
+        assert!(nx > 0 && ny > 0 && nz > 0, "Voxel grids need at least one voxel along each axis.");
+        let mut data = Vec::with_capacity(nx * ny * nz);
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    let p = Point3::new(
+                        (x as f64 + 0.5) / nx as f64,
+                        (y as f64 + 0.5) / ny as f64,
+                        (z as f64 + 0.5) / nz as f64,
+                    );
+                    data.push(density(p) as f32);
+                }
+            }
+        }
+        VoxelGrid { nx, ny, nz, data }
+    }
+
+    /// Reads a raw voxel file: the x, y and z resolution as little-endian
+    /// `u32`s followed by every density as a little-endian `f32`.
+    pub fn load_raw<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        if bytes.len() < 12 {
+            return Err(invalid("voxel file is missing its header"));
+        }
+        let dim = |i: usize| u32::from_le_bytes(bytes[4 * i..4 * i + 4].try_into().unwrap()) as usize;
+        let (nx, ny, nz) = (dim(0), dim(1), dim(2));
+
+        let body = &bytes[12..];
+        let expected = nx
+            .checked_mul(ny)
+            .and_then(|n| n.checked_mul(nz))
+            .and_then(|n| n.checked_mul(4));
+        if nx == 0 || ny == 0 || nz == 0 || expected != Some(body.len()) {
+            return Err(invalid("voxel file size does not match its header"));
+        }
+        let data: Vec<f32> = body
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        // Delta tracking needs a finite, non-negative majorant
+        if data.iter().any(|d| !d.is_finite() || *d < 0.0) {
+            return Err(invalid("voxel densities must be finite and non-negative"));
+        }
+
+        Ok(VoxelGrid { nx, ny, nz, data })
+    }
+
+    /// Writes the grid in the format read by `load_raw`.
+    pub fn save_raw<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(12 + 4 * self.data.len());
+        for dim in [self.nx, self.ny, self.nz] {
+            bytes.extend_from_slice(&(dim as u32).to_le_bytes());
+        }
+        for value in &self.data {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        fs::write(path, bytes)
+    }
+
+    pub fn max_value(&self) -> f64 {
+        self.data.iter().fold(0.0_f32, |a, &b| a.max(b)) as f64
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f64 {
+        self.data[(z * self.ny + y) * self.nx + x] as f64
+    }
+
+    /// Trilinearly interpolated density at `p` in the unit cube. Samples sit
+    /// at voxel centers and the outermost values extend to the faces.
+    pub fn lookup(&self, p: &Point3) -> f64 {
+        // Lower sample index and blend weight along one axis
+        let axis = |coord: f64, n: usize| {
+            let x = (coord * n as f64 - 0.5).clamp(0.0, (n - 1) as f64);
+            let i = (x.floor() as usize).min(n.saturating_sub(2));
+            let i1 = (i + 1).min(n - 1);
+            (i, i1, x - i as f64)
+        };
+        let (x0, x1, fx) = axis(p.x, self.nx);
+        let (y0, y1, fy) = axis(p.y, self.ny);
+        let (z0, z1, fz) = axis(p.z, self.nz);
+
+        let lerp = |a: f64, b: f64, t: f64| a * (1.0 - t) + b * t;
+        let c00 = lerp(self.voxel(x0, y0, z0), self.voxel(x1, y0, z0), fx);
+        let c10 = lerp(self.voxel(x0, y1, z0), self.voxel(x1, y1, z0), fx);
+        let c01 = lerp(self.voxel(x0, y0, z1), self.voxel(x1, y0, z1), fx);
+        let c11 = lerp(self.voxel(x0, y1, z1), self.voxel(x1, y1, z1), fx);
+        lerp(lerp(c00, c10, fy), lerp(c01, c11, fy), fz)
+    }
+}
+
+/// A heterogeneous medium such as a cloud, with density given by a voxel grid
+/// stretched over an axis-aligned box. Free-flight distances are sampled by
+/// delta tracking against the grid's maximum density, so no step size is
+/// needed and the result is unbiased.
+pub struct GridMedium {
+    bounds_min: Point3,
+    bounds_max: Point3,
+    grid: VoxelGrid,
+    density_scale: f64,
+    sigma_max: f64,
+    phase_function: Arc<dyn Material>,
+}
+
+impl GridMedium {
+    /// `density_scale` converts grid values into extinction per unit length.
+    pub fn new(
+        bounds_min: Point3,
+        bounds_max: Point3,
+        grid: VoxelGrid,
+        density_scale: f64,
+        albedo: Color,
+        phase: Arc<dyn PhaseFunction>,
+    ) -> Self {
+        let sigma_max = grid.max_value() * density_scale;
+        GridMedium {
+            bounds_min,
+            bounds_max,
+            grid,
+            density_scale,
+            sigma_max,
+            phase_function: Arc::new(Volumetric::new(albedo, phase)),
+        }
+    }
+
+    fn density(&self, p: &Point3) -> f64 {
+        let local = (*p - self.bounds_min) / (self.bounds_max - self.bounds_min);
+        self.grid.lookup(&local) * self.density_scale
+    }
+
+    /// The part of `ray_t` where the ray is inside the bounding box.
+    fn clip(&self, r: &Ray, ray_t: &Interval) -> Option<(f64, f64)> {
+        let mut t_min = ray_t.min;
+        let mut t_max = ray_t.max;
+        for axis in 0..3 {
+            let inv_d = 1.0 / r.direction()[axis];
+            let mut t0 = (self.bounds_min[axis] - r.origin()[axis]) * inv_d;
+            let mut t1 = (self.bounds_max[axis] - r.origin()[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max <= t_min {
+                return None;
+            }
+        }
+        Some((t_min, t_max))
+    }
+
+    /// Fraction of light that makes it through the medium along `ray_t`,
+    /// estimated without bias by ratio tracking.
+    pub fn transmittance(&self, r: &Ray, ray_t: Interval) -> f64 {
+        let Some((t_start, t_end)) = self.clip(r, &ray_t) else {
+            return 1.0;
+        };
+        if self.sigma_max <= 0.0 {
+            return 1.0;
+        }
+
+        let step_scale = 1.0 / (self.sigma_max * r.direction().length());
+        let mut transmittance = 1.0;
+        let mut t = t_start;
+        loop {
+            t -= (1.0 - random_double()).ln() * step_scale;
+            if t >= t_end {
+                return transmittance;
+            }
+            transmittance *= 1.0 - self.density(&r.at(t)) / self.sigma_max;
+        }
+    }
+}
+
+impl Hittable for GridMedium {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let Some((t_start, t_end)) = self.clip(r, &ray_t) else {
+            return false;
+        };
+        if self.sigma_max <= 0.0 {
+            return false;
+        }
+
+        // Take tentative collisions against the majorant and accept each with
+        // probability density / majorant, the rest being null collisions
+        let step_scale = 1.0 / (self.sigma_max * r.direction().length());
+        let mut t = t_start;
+        loop {
+            t -= (1.0 - random_double()).ln() * step_scale;
+            if t >= t_end {
+                return false;
+            }
+            if random_double() * self.sigma_max < self.density(&r.at(t)) {
+                break;
+            }
+        }
+
+        rec.t = t;
+        rec.p = r.at(rec.t);
+        rec.set_arbitrary_normal();
+        rec.mat = Some(self.phase_function.clone());
+
+        true
+    }
+}
+
+#[test]
+fn trilinear_lookup() {
+    let grid = VoxelGrid::new(2, 1, 1, vec![1.0, 3.0]);
+    // Outer values hold from the faces to the voxel centers, blend in between
+    assert_eq!(grid.lookup(&Point3::new(0.1, 0.5, 0.5)), 1.0);
+    assert_eq!(grid.lookup(&Point3::new(0.9, 0.5, 0.5)), 3.0);
+    assert!((grid.lookup(&Point3::new(0.5, 0.5, 0.5)) - 2.0).abs() < 1e-12);
+
+    // An empty axis leaves nothing to interpolate between
+    assert!(std::panic::catch_unwind(|| VoxelGrid::new(2, 0, 1, vec![])).is_err());
+    assert!(std::panic::catch_unwind(|| VoxelGrid::from_fn(0, 1, 1, |_| 1.0)).is_err());
+}
+
+#[test]
+fn tracking_matches_optical_depth() {
+    // Density runs from 1 to 3 along x, so a ray straight through sees an
+    // average of 2 and the optical depth is 2 * length * scale
+    let grid = VoxelGrid::new(2, 1, 1, vec![1.0, 3.0]);
+    let scale = 0.4;
+    let medium = GridMedium::new(
+        Point3::zero(),
+        Point3::new(2.0, 1.0, 1.0),
+        grid,
+        scale,
+        Color::one(),
+        Arc::new(crate::common::phase::IsotropicPhase),
+    );
+    let expected = (-2.0 * 2.0 * scale).exp();
+
+    let r = Ray::new(Point3::new(-1.0, 0.5, 0.5), Vec3::new(1.0, 0.0, 0.0));
+    let trials = 100_000;
+    let mut rec = HitRecord::new();
+    let escaped = (0..trials)
+        .filter(|_| !medium.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec))
+        .count() as f64
+        / trials as f64;
+    assert!((escaped - expected).abs() < 0.01, "{escaped} != {expected}");
+
+    let ratio = (0..trials)
+        .map(|_| medium.transmittance(&r, Interval::new(0.0, f64::INFINITY)))
+        .sum::<f64>()
+        / trials as f64;
+    assert!((ratio - expected).abs() < 0.01, "{ratio} != {expected}");
+}
+
+#[test]
+fn raw_round_trip() {
+    let grid = VoxelGrid::from_fn(3, 2, 2, |p| p.x + 10.0 * p.y + 100.0 * p.z);
+    let path = std::env::temp_dir().join(format!("voxel_round_trip_{}.raw", std::process::id()));
+    grid.save_raw(&path).unwrap();
+    let loaded = VoxelGrid::load_raw(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!((loaded.nx, loaded.ny, loaded.nz), (3, 2, 2));
+    assert_eq!(loaded.data, grid.data);
+}
+
+#[test]
+fn raw_rejects_bad_files() {
+    let path = std::env::temp_dir().join(format!("voxel_bad_{}.raw", std::process::id()));
+    let write = |dims: [u32; 3], values: &[f32]| {
+        let mut bytes: Vec<u8> = dims.iter().flat_map(|d| d.to_le_bytes()).collect();
+        bytes.extend(values.iter().flat_map(|v| v.to_le_bytes()));
+        fs::write(&path, bytes).unwrap();
+    };
+
+    // A header whose voxel count overflows must not wrap around to match
+    write([u32::MAX, u32::MAX, u32::MAX], &[]);
+    assert!(VoxelGrid::load_raw(&path).is_err());
+    write([0, 1, 1], &[]);
+    assert!(VoxelGrid::load_raw(&path).is_err());
+    write([2, 1, 1], &[1.0, -1.0]);
+    assert!(VoxelGrid::load_raw(&path).is_err());
+    write([2, 1, 1], &[1.0, f32::NAN]);
+    assert!(VoxelGrid::load_raw(&path).is_err());
+    write([2, 1, 1], &[1.0, 2.0]);
+    assert!(VoxelGrid::load_raw(&path).is_ok());
+    fs::remove_file(&path).unwrap();
+}
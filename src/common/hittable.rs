@@ -1,12 +1,40 @@
-use crate::common::{ray::Ray, util::Interval};
+use std::sync::Arc;
+
+use crate::common::{
+    material::Material,
+    ray::{Ray, RayDifferential},
+    util::{random_double, Interval},
+};
 use vec3::*;
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Default)]
 pub struct HitRecord {
     front_face: bool,
+    // The shading normal, which bump and normal maps may tilt away from the
+    // true surface normal kept in geometric_normal. Both face the ray.
     normal: Vec3<f64>,
+    geometric_normal: Vec3<f64>,
+    pub mat: Option<Arc<dyn Material>>,
     pub p: Point3,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
+    // Partial derivatives of the surface point with respect to u and v, zero
+    // where a primitive has no parameterization
+    pub dpdu: Vec3<f64>,
+    pub dpdv: Vec3<f64>,
+    // How the normal turns with u and v, zero where a primitive treats itself
+    // as locally flat
+    pub dndu: Vec3<f64>,
+    pub dndv: Vec3<f64>,
+    // The change in position and (u, v) from one pixel to the next, filled in
+    // by compute_differentials and zero when the ray carries none
+    pub dpdx: Vec3<f64>,
+    pub dpdy: Vec3<f64>,
+    pub dudx: f64,
+    pub dudy: f64,
+    pub dvdx: f64,
+    pub dvdy: f64,
 }
 
 impl HitRecord {
@@ -18,28 +46,271 @@ impl HitRecord {
         } else {
             -(*outward_normal)
         };
+        self.geometric_normal = self.normal;
+    }
+
+    /// Replaces the shading normal, flipping it if needed to stay on the same
+    /// side as the geometric normal.
+    pub fn set_shading_normal(&mut self, shading_normal: &Vec3<f64>) {
+        let n = shading_normal.unit_vector();
+        self.normal = if n.dot(&self.geometric_normal) < 0.0 { -n } else { n };
+    }
+
+    /// Overrides which side of the surface was hit while keeping the normal
+    /// facing the ray, for surfaces whose inside and outside were decided by
+    /// something other than their own geometry.
+    pub fn set_front_face(&mut self, front_face: bool) {
+        self.front_face = front_face;
+    }
+
+    /// For hits with no surface to speak of, such as inside a volume. The
+    /// geometric normal is zero so rays leaving the point are not offset.
+    pub fn set_arbitrary_normal(&mut self) {
+        self.front_face = true;
+        self.normal = Vec3::new(1.0, 0.0, 0.0);
+        self.geometric_normal = Vec3::zero();
+        self.dpdu = Vec3::zero();
+        self.dpdv = Vec3::zero();
+        self.dndu = Vec3::zero();
+        self.dndv = Vec3::zero();
     }
 
     pub fn new() -> Self {
         HitRecord {
+            mat: None,
             p: Point3::from_float(0.0),
             normal: Vec3::<f64>::from_float(0.0),
+            geometric_normal: Vec3::<f64>::from_float(0.0),
             t: 0.0,
+            u: 0.0,
+            v: 0.0,
+            dpdu: Vec3::<f64>::from_float(0.0),
+            dpdv: Vec3::<f64>::from_float(0.0),
+            dndu: Vec3::<f64>::from_float(0.0),
+            dndv: Vec3::<f64>::from_float(0.0),
+            dpdx: Vec3::<f64>::from_float(0.0),
+            dpdy: Vec3::<f64>::from_float(0.0),
+            dudx: 0.0,
+            dudy: 0.0,
+            dvdx: 0.0,
+            dvdy: 0.0,
             front_face: true,
         }
     }
 
-    pub fn normal(self) -> Vec3<f64> {
+    /// Moves a record found in an object's local space into world space.
+    pub fn transform(&mut self, transform: &Transform) {
+        self.p = transform.point(&self.p);
+        self.normal = transform.normal(&self.normal).unit_vector();
+        if !self.geometric_normal.near_zero() {
+            self.geometric_normal = transform.normal(&self.geometric_normal).unit_vector();
+        }
+        self.dpdu = transform.vector(&self.dpdu);
+        self.dpdv = transform.vector(&self.dpdv);
+        self.dndu = transform.normal(&self.dndu);
+        self.dndv = transform.normal(&self.dndv);
+    }
+
+    /// Estimates the pixel footprint at the hit by intersecting the ray's
+    /// differentials with the tangent plane, then expressing the offsets in
+    /// terms of dp/du and dp/dv.
+    pub fn compute_differentials(&mut self, r: &Ray) {
+        self.dpdx = Vec3::zero();
+        self.dpdy = Vec3::zero();
+        (self.dudx, self.dudy, self.dvdx, self.dvdy) = (0.0, 0.0, 0.0, 0.0);
+
+        let Some(rd) = r.differentials() else {
+            return;
+        };
+        let n = self.geometric_normal;
+        let (nx, ny) = (n.dot(&rd.rx_direction), n.dot(&rd.ry_direction));
+        if n.near_zero() || nx == 0.0 || ny == 0.0 {
+            return;
+        }
+        let d = n.dot(&self.p);
+        let px = rd.rx_origin + rd.rx_direction * ((d - n.dot(&rd.rx_origin)) / nx);
+        let py = rd.ry_origin + rd.ry_direction * ((d - n.dot(&rd.ry_origin)) / ny);
+        self.dpdx = px - self.p;
+        self.dpdy = py - self.p;
+
+        // Least squares solution of dpdx = dpdu * dudx + dpdv * dvdx
+        let ata00 = self.dpdu.dot(&self.dpdu);
+        let ata01 = self.dpdu.dot(&self.dpdv);
+        let ata11 = self.dpdv.dot(&self.dpdv);
+        let det = ata00 * ata11 - ata01 * ata01;
+        if det.abs() < 1e-20 {
+            return;
+        }
+        let inv_det = 1.0 / det;
+        let solve = |dp: &Vec3<f64>| {
+            let b0 = self.dpdu.dot(dp);
+            let b1 = self.dpdv.dot(dp);
+            (
+                (ata11 * b0 - ata01 * b1) * inv_det,
+                (ata00 * b1 - ata01 * b0) * inv_det,
+            )
+        };
+        (self.dudx, self.dvdx) = solve(&self.dpdx);
+        (self.dudy, self.dvdy) = solve(&self.dpdy);
+    }
+
+    /// The differentials of a perfectly specular reflection of `r_in` into the
+    /// unit direction `wi`, accounting for the surface's curvature.
+    pub fn reflected_differentials(&self, r_in: &Ray, wi: &Vec3<f64>) -> Option<RayDifferential> {
+        let SpecularTerms { wo, dwodx, dwody, dndx, dndy } = self.specular_terms(r_in)?;
+        let ns = self.normal;
+        let dwondx = dwodx.dot(&ns) + wo.dot(&dndx);
+        let dwondy = dwody.dot(&ns) + wo.dot(&dndy);
+        let wo_n = wo.dot(&ns);
+        Some(RayDifferential {
+            rx_origin: self.p + self.dpdx,
+            rx_direction: *wi - dwodx + (dndx * wo_n + ns * dwondx) * 2.0,
+            ry_origin: self.p + self.dpdy,
+            ry_direction: *wi - dwody + (dndy * wo_n + ns * dwondy) * 2.0,
+        })
+    }
+
+    /// The differentials of a perfectly specular refraction of `r_in` into the
+    /// unit direction `wi`, where `eta` is the incident over the transmitted
+    /// refractive index.
+    pub fn refracted_differentials(&self, r_in: &Ray, wi: &Vec3<f64>, eta: f64) -> Option<RayDifferential> {
+        let SpecularTerms { wo, dwodx, dwody, dndx, dndy } = self.specular_terms(r_in)?;
+        let ns = self.normal;
+        let dwondx = dwodx.dot(&ns) + wo.dot(&dndx);
+        let dwondy = dwody.dot(&ns) + wo.dot(&dndy);
+        let wo_n = wo.dot(&ns);
+        let wi_n = wi.dot(&ns).abs();
+        if wi_n == 0.0 {
+            return None;
+        }
+        let mu = eta * wo_n - wi_n;
+        let dmu = eta - eta * eta * wo_n / wi_n;
+        Some(RayDifferential {
+            rx_origin: self.p + self.dpdx,
+            rx_direction: *wi - dwodx * eta + dndx * mu + ns * (dmu * dwondx),
+            ry_origin: self.p + self.dpdy,
+            ry_direction: *wi - dwody * eta + dndy * mu + ns * (dmu * dwondy),
+        })
+    }
+
+    fn specular_terms(&self, r_in: &Ray) -> Option<SpecularTerms> {
+        let rd = r_in.differentials()?;
+        let wo = -r_in.direction().unit_vector();
+        let dwodx = -rd.rx_direction.unit_vector() - wo;
+        let dwody = -rd.ry_direction.unit_vector() - wo;
+        // The derivatives follow the outward normal, flip them with it
+        let sign = if self.front_face { 1.0 } else { -1.0 };
+        let dndx = (self.dndu * self.dudx + self.dndv * self.dvdx) * sign;
+        let dndy = (self.dndu * self.dudy + self.dndv * self.dvdy) * sign;
+        Some(SpecularTerms {
+            wo,
+            dwodx,
+            dwody,
+            dndx,
+            dndy,
+        })
+    }
+
+    /// An orthonormal tangent and bitangent around the shading normal, following
+    /// dp/du where the surface has one.
+    pub fn tangent_frame(&self) -> (Vec3<f64>, Vec3<f64>) {
+        let n = self.normal;
+        // Gram-Schmidt dp/du against the normal, or pick any perpendicular
+        let mut tangent = self.dpdu - n * n.dot(&self.dpdu);
+        if tangent.length_squared() < 1e-16 {
+            tangent = Onb::new(&n).u();
+        }
+        let tangent = tangent.unit_vector();
+        let mut bitangent = Vec3::cross_product(&n, &tangent);
+        // Keep the bitangent pointing along dp/dv so maps are not mirrored
+        if bitangent.dot(&self.dpdv) < 0.0 {
+            bitangent = -bitangent;
+        }
+        (tangent, bitangent)
+    }
+
+    /// A ray leaving the surface, nudged along the geometric normal onto the
+    /// side it is heading so it cannot hit the surface it started on.
+    pub fn spawn_ray(&self, r_in: &Ray, direction: Vec3<f64>) -> Ray {
+        let offset = self.geometric_normal * (1e-7 * (1.0 + self.p.length()));
+        let origin = if direction.dot(&self.geometric_normal) < 0.0 {
+            self.p - offset
+        } else {
+            self.p + offset
+        };
+        Ray::new_with_time(origin, direction, r_in.time())
+            .with_wavelengths(r_in.wavelengths().copied())
+    }
+
+    /// The same hit as found by a ray travelling along `r`, facing it the
+    /// way `r` would, for asking a material how it treats light coming from
+    /// another direction.
+    pub fn seen_along(&self, r: &Ray) -> HitRecord {
+        let mut rec = self.clone();
+        if r.direction().dot(&self.geometric_normal) > 0.0 {
+            rec.front_face = !rec.front_face;
+            rec.normal = -rec.normal;
+            rec.geometric_normal = -rec.geometric_normal;
+        }
+        rec
+    }
+
+    pub fn normal(&self) -> Vec3<f64> {
         self.normal
     }
 
-    pub fn point(self) -> Vec3<f64> {
+    pub fn geometric_normal(&self) -> Vec3<f64> {
+        self.geometric_normal
+    }
+
+    pub fn point(&self) -> Vec3<f64> {
         self.p
     }
+
+    pub fn front_face(&self) -> bool {
+        self.front_face
+    }
+}
+
+// The outgoing direction at a hit and how it and the shading normal change
+// from one pixel to the next
+struct SpecularTerms {
+    wo: Vec3<f64>,
+    dwodx: Vec3<f64>,
+    dwody: Vec3<f64>,
+    dndx: Vec3<f64>,
+    dndy: Vec3<f64>,
 }
 
-pub trait Hittable {
+pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool;
+
+    /// Density, per steradian seen from `origin`, with which `random` picks
+    /// `direction`, for the object as placed at `time`. Zero for objects that
+    /// cannot be sampled as lights.
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3<f64>, _time: f64) -> f64 {
+        0.0
+    }
+
+    /// A direction from `origin` towards a random point on the object as
+    /// placed at `time`.
+    fn random(&self, _origin: &Point3, _time: f64) -> Vec3<f64> {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    /// A point spread uniformly over the object's surface at `time`, as a hit
+    /// seen from outside, and the density per unit area of picking it. None
+    /// for objects that cannot be sampled as lights. Used to start paths on
+    /// emitters.
+    fn sample_surface(&self, _time: f64) -> Option<(HitRecord, f64)> {
+        None
+    }
+
+    /// Density, per unit area, with which `sample_surface` picks the point
+    /// seen from `origin` along `direction` at `time`.
+    fn surface_pdf(&self, _origin: &Point3, _direction: &Vec3<f64>, _time: f64) -> f64 {
+        0.0
+    }
 }
 
 pub struct HittableList {
@@ -64,10 +335,79 @@ impl Hittable for HittableList {
             if object.hit(r, Interval::new(ray_t.min, closest_so_far), &mut temp_rec) {
                 hit_anything = true;
                 closest_so_far = temp_rec.t;
-                *rec = temp_rec;
+                *rec = temp_rec.clone();
             }
         }
 
         hit_anything
     }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3<f64>, time: f64) -> f64 {
+        // random picks each object with equal chance
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+        let weight = 1.0 / self.objects.len() as f64;
+        self.objects
+            .iter()
+            .map(|object| weight * object.pdf_value(origin, direction, time))
+            .sum()
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3<f64> {
+        if self.objects.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let index = ((random_double() * self.objects.len() as f64) as usize).min(self.objects.len() - 1);
+        self.objects[index].random(origin, time)
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(HitRecord, f64)> {
+        if self.objects.is_empty() {
+            return None;
+        }
+        let index = ((random_double() * self.objects.len() as f64) as usize).min(self.objects.len() - 1);
+        let (rec, pdf) = self.objects[index].sample_surface(time)?;
+        Some((rec, pdf / self.objects.len() as f64))
+    }
+
+    fn surface_pdf(&self, origin: &Point3, direction: &Vec3<f64>, time: f64) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+        // Only the object whose surface is at origin + direction could have
+        // picked that point, not others the ray passes through on the way
+        let r = Ray::new_with_time(*origin, *direction, time);
+        let mut rec = HitRecord::new();
+        let weight = 1.0 / self.objects.len() as f64;
+        self.objects
+            .iter()
+            .filter(|object| {
+                object.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) && (rec.t - 1.0).abs() < 1e-4
+            })
+            .map(|object| weight * object.surface_pdf(origin, direction, time))
+            .sum()
+    }
+}
+
+#[test]
+fn surface_pdf_counts_only_the_light_reached() {
+    use crate::common::{material::DiffuseLight, sphere::Sphere};
+
+    // Two unit spheres in a row along -z, each picked half the time
+    let mut lights = HittableList::new();
+    for z in [-3.0, -6.0] {
+        let mat = Arc::new(DiffuseLight::new(Color::one()));
+        lights.objects.push(Box::new(Sphere::new(Point3::new(0.0, 0.0, z), 1.0, mat)));
+    }
+    let area_pdf = 1.0 / (4.0 * std::f64::consts::PI);
+
+    // Looking at the near sphere's front, the far one behind it is no
+    // candidate, while the far sphere's front is only on the far sphere
+    let origin = Point3::zero();
+    let near = lights.surface_pdf(&origin, &Vec3::new(0.0, 0.0, -2.0), 0.0);
+    assert!((near - 0.5 * area_pdf).abs() < 1e-12, "{near}");
+    let far = lights.surface_pdf(&origin, &Vec3::new(0.0, 0.0, -5.0), 0.0);
+    assert!((far - 0.5 * area_pdf).abs() < 1e-12, "{far}");
+    assert_eq!(lights.surface_pdf(&origin, &Vec3::new(0.0, 0.0, -3.0), 0.0), 0.0);
 }
@@ -0,0 +1,375 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use vec3::*;
+
+/// A grid of linear RGB pixels with row 0 at the top.
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        assert!(width > 0 && height > 0, "Images need at least one pixel.");
+        assert_eq!(pixels.len(), width * height, "Pixel count does not match the image size.");
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Reads a PNG, binary/ASCII PPM or Radiance HDR file, chosen by extension.
+    /// Eight bit files are assumed to be sRGB encoded and are converted to
+    /// linear, HDR files are linear already.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::load_with(path.as_ref(), srgb_to_linear)
+    }
+
+    fn load_with(path: &Path, decode: fn(f64) -> f64) -> io::Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("png") => Self::load_png(path, decode),
+            Some("ppm") | Some("pnm") => Self::load_ppm(path, decode),
+            Some("hdr") => Self::load_hdr(path),
+            _ => Err(invalid_data("unsupported image format")),
+        }
+    }
+
+    fn load_png(path: &Path, decode: fn(f64) -> f64) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        // Expand palettes and low bit depths, and reduce 16 bit to 8 bit
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(|e| invalid_data(&e.to_string()))?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buf)
+            .map_err(|e| invalid_data(&e.to_string()))?;
+
+        let channels = match info.color_type {
+            png::ColorType::Grayscale => 1,
+            png::ColorType::GrayscaleAlpha => 2,
+            png::ColorType::Rgb => 3,
+            png::ColorType::Rgba => 4,
+            png::ColorType::Indexed => return Err(invalid_data("unexpanded palette")),
+        };
+
+        let (width, height) = (info.width as usize, info.height as usize);
+        let mut pixels = Vec::with_capacity(value_count(width, height, 1)?);
+        for row in buf[..info.buffer_size()].chunks_exact(info.line_size) {
+            for texel in row.chunks_exact(channels).take(width) {
+                let decode = |b: u8| decode(b as f64 / 255.0);
+                pixels.push(if channels < 3 {
+                    Color::from_float(decode(texel[0]))
+                } else {
+                    Color::new(decode(texel[0]), decode(texel[1]), decode(texel[2]))
+                });
+            }
+        }
+
+        Ok(Image::new(width, height, pixels))
+    }
+
+    fn load_ppm(path: &Path, decode: fn(f64) -> f64) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+
+        // The header is whitespace separated tokens with # comments, and for
+        // binary files a single whitespace byte separates it from the data
+        let mut pos = 0;
+        let mut next_token = || -> io::Result<String> {
+            loop {
+                while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                    pos += 1;
+                }
+                if pos < bytes.len() && bytes[pos] == b'#' {
+                    while pos < bytes.len() && bytes[pos] != b'\n' {
+                        pos += 1;
+                    }
+                    continue;
+                }
+                break;
+            }
+            let start = pos;
+            while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if start == pos {
+                return Err(invalid_data("truncated PPM file"));
+            }
+            Ok(String::from_utf8_lossy(&bytes[start..pos]).into_owned())
+        };
+        let number = |token: String| {
+            token
+                .parse::<usize>()
+                .map_err(|_| invalid_data("bad number in PPM file"))
+        };
+
+        let magic = next_token()?;
+        let width = number(next_token()?)?;
+        let height = number(next_token()?)?;
+        let max_value = number(next_token()?)?.max(1) as f64;
+
+        let count = value_count(width, height, 3)?;
+        let samples: Vec<usize> = match magic.as_str() {
+            // Every sample takes at least a byte, which bounds the allocation
+            "P3" if count > bytes.len() => return Err(invalid_data("truncated PPM file")),
+            "P3" => (0..count)
+                .map(|_| next_token().and_then(number))
+                .collect::<io::Result<_>>()?,
+            "P6" => {
+                let start = pos + 1;
+                let wide = max_value > 255.0;
+                let size = if wide { count.checked_mul(2) } else { Some(count) };
+                let data = size
+                    .and_then(|size| bytes.get(start..start.checked_add(size)?))
+                    .ok_or_else(|| invalid_data("truncated PPM file"))?;
+                if wide {
+                    data.chunks_exact(2)
+                        .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+                        .collect()
+                } else {
+                    data.iter().map(|&b| b as usize).collect()
+                }
+            }
+            _ => return Err(invalid_data("not a P3 or P6 PPM file")),
+        };
+
+        let pixels = samples
+            .chunks_exact(3)
+            .map(|c| {
+                let decode = |v: usize| decode(v as f64 / max_value);
+                Color::new(decode(c[0]), decode(c[1]), decode(c[2]))
+            })
+            .collect();
+        Ok(Image::new(width, height, pixels))
+    }
+
+    fn load_hdr(path: &Path) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let mut pos = 0;
+        let mut next_line = || -> io::Result<String> {
+            let start = pos;
+            while pos < bytes.len() && bytes[pos] != b'\n' {
+                pos += 1;
+            }
+            if pos == bytes.len() {
+                return Err(invalid_data("truncated HDR header"));
+            }
+            pos += 1;
+            Ok(String::from_utf8_lossy(&bytes[start..pos - 1]).into_owned())
+        };
+
+        // Variable lines up to a blank one, then the resolution
+        let magic = next_line()?;
+        if !magic.starts_with("#?") {
+            return Err(invalid_data("not a Radiance HDR file"));
+        }
+        loop {
+            let line = next_line()?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some(format) = line.strip_prefix("FORMAT=")
+                && format != "32-bit_rle_rgbe"
+            {
+                return Err(invalid_data("unsupported HDR pixel format"));
+            }
+        }
+        let resolution = next_line()?;
+        let fields: Vec<&str> = resolution.split_whitespace().collect();
+        let (height, width) = match fields.as_slice() {
+            ["-Y", h, "+X", w] => (h.parse::<usize>(), w.parse::<usize>()),
+            _ => return Err(invalid_data("unsupported HDR orientation")),
+        };
+        let (height, width) = match (height, width) {
+            (Ok(h), Ok(w)) => (h, w),
+            _ => return Err(invalid_data("bad HDR resolution")),
+        };
+
+        let count = value_count(width, height, 1)?;
+        let row_size = value_count(width, 1, 4)?;
+
+        // Check the file can hold every row before allocating for them, so a
+        // corrupt header is an error rather than an enormous allocation
+        let mut data = &bytes[pos..];
+        let min_row_size = if (8..0x8000).contains(&width) {
+            // Runs of up to 127 pixels, two bytes per channel
+            4 + 8 * width.div_ceil(127)
+        } else {
+            row_size
+        };
+        if min_row_size.checked_mul(height).is_none_or(|size| size > data.len()) {
+            return Err(invalid_data("truncated HDR pixel data"));
+        }
+        let mut pixels = Vec::with_capacity(count);
+        let mut scanline = vec![0u8; row_size];
+        for _ in 0..height {
+            data = read_rgbe_scanline(data, &mut scanline)?;
+            for rgbe in scanline.chunks_exact(4) {
+                pixels.push(rgbe_to_color(rgbe));
+            }
+        }
+        Ok(Image::new(width, height, pixels))
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+}
+
+// Fills `scanline` with the next row of RGBE bytes, in order per pixel, and
+// returns the data after it. Rows are either flat or, in files written since
+// the early nineties, run length encoded one channel at a time.
+fn read_rgbe_scanline<'a>(data: &'a [u8], scanline: &mut [u8]) -> io::Result<&'a [u8]> {
+    let width = scanline.len() / 4;
+    let truncated = || invalid_data("truncated HDR pixel data");
+    let encoded = (8..0x8000).contains(&width)
+        && data.len() >= 4
+        && data[0] == 2
+        && data[1] == 2
+        && ((data[2] as usize) << 8 | data[3] as usize) == width;
+    if !encoded {
+        let row = data.get(..4 * width).ok_or_else(truncated)?;
+        scanline.copy_from_slice(row);
+        return Ok(&data[4 * width..]);
+    }
+
+    let mut pos = 4;
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = *data.get(pos).ok_or_else(truncated)? as usize;
+            pos += 1;
+            if count > 128 {
+                // A run of one repeated byte
+                let count = count - 128;
+                let value = *data.get(pos).ok_or_else(truncated)?;
+                pos += 1;
+                if x + count > width {
+                    return Err(invalid_data("HDR run overflows the scanline"));
+                }
+                for i in x..x + count {
+                    scanline[4 * i + channel] = value;
+                }
+                x += count;
+            } else {
+                // That many literal bytes
+                if count == 0 || x + count > width {
+                    return Err(invalid_data("bad HDR run length"));
+                }
+                let literal = data.get(pos..pos + count).ok_or_else(truncated)?;
+                for (i, &value) in literal.iter().enumerate() {
+                    scanline[4 * (x + i) + channel] = value;
+                }
+                pos += count;
+                x += count;
+            }
+        }
+    }
+    Ok(&data[pos..])
+}
+
+// Shared exponent encoding: each mantissa byte scaled by 2^(e - 128 - 8)
+fn rgbe_to_color(rgbe: &[u8]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::zero();
+    }
+    let scale = 2f64.powi(rgbe[3] as i32 - 136);
+    Color::new(rgbe[0] as f64, rgbe[1] as f64, rgbe[2] as f64) * scale
+}
+
+// The number of values in a `width` by `height` image with `channels` per
+// pixel, rejecting empty images and sizes that do not fit in memory
+fn value_count(width: usize, height: usize, channels: usize) -> io::Result<usize> {
+    if width == 0 || height == 0 {
+        return Err(invalid_data("image has no pixels"));
+    }
+    width
+        .checked_mul(height)
+        .and_then(|n| n.checked_mul(channels))
+        .ok_or_else(|| invalid_data("image is too large"))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+#[test]
+fn load_ascii_ppm() {
+    let path = std::env::temp_dir().join(format!("image_test_{}.ppm", std::process::id()));
+    fs::write(&path, "P3\n# a comment\n2 1\n255\n255 0 0\n0 0 255\n").unwrap();
+    let image = Image::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!((image.width(), image.height()), (2, 1));
+    assert_eq!(image.pixel(0, 0), Color::new(1.0, 0.0, 0.0));
+    assert_eq!(image.pixel(1, 0), Color::new(0.0, 0.0, 1.0));
+
+    // Empty, overflowing and truncated sizes are rejected rather than loaded,
+    // wrapped or allocated for
+    for header in [
+        "P3\n0 0\n255\n",
+        "P6\n18446744073709551615 2\n255\n",
+        "P3\n1000000000 1000000000\n255\n0 0 0\n",
+    ] {
+        fs::write(&path, header).unwrap();
+        let error = Image::load(&path).err().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
+
+#[test]
+fn load_radiance_hdr() {
+    // One flat row and one run length encoded row of eight pixels
+    let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 8\n".to_vec();
+    for x in 0..8u8 {
+        bytes.extend_from_slice(&[128, x * 16, 0, 129]);
+    }
+    bytes.extend_from_slice(&[2, 2, 0, 8]);
+    bytes.extend_from_slice(&[136, 128]);
+    bytes.extend_from_slice(&[4, 1, 2, 3, 4, 132, 0]);
+    bytes.extend_from_slice(&[136, 0]);
+    bytes.extend_from_slice(&[136, 131]);
+
+    let path = std::env::temp_dir().join(format!("image_test_{}.hdr", std::process::id()));
+    fs::write(&path, bytes).unwrap();
+    let image = Image::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!((image.width(), image.height()), (8, 2));
+    assert_eq!(image.pixel(0, 0), Color::new(1.0, 0.0, 0.0));
+    assert_eq!(image.pixel(4, 0), Color::new(1.0, 0.5, 0.0));
+    assert_eq!(image.pixel(2, 1), Color::new(4.0, 0.09375, 0.0));
+    assert_eq!(image.pixel(6, 1), Color::new(4.0, 0.0, 0.0));
+
+    // Empty, overflowing and truncated resolutions are errors, not panics
+    // or huge allocations later on
+    for resolution in [
+        "-Y 0 +X 8",
+        "-Y 2 +X 0",
+        "-Y 2 +X 9223372036854775807",
+        "-Y 1 +X 1152921504606846976",
+        "-Y 1000000000 +X 1000",
+    ] {
+        let header = format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n{resolution}\n");
+        fs::write(&path, header).unwrap();
+        let error = Image::load(&path).err().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
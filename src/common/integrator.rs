@@ -0,0 +1,313 @@
+use core::f64;
+
+use crate::common::{
+    hittable::{HitRecord, Hittable},
+    light::Light,
+    material::{Material, ScatterRecord},
+    pdf::{cosine_hemisphere, HittablePdf, Pdf},
+    ray::Ray,
+    sampler::Sampler,
+    scene::Scene,
+    spectrum::Radiance,
+    util::{power_heuristic, Interval},
+};
+use vec3::*;
+
+/// A way of working out the light arriving at the camera, given what is in
+/// the scene.
+pub trait Integrator: Send + Sync {
+    /// Radiance arriving at the camera along `r`.
+    fn li(&self, r: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Color;
+
+    /// The sum of every sample taken for each pixel, row by row from the top
+    /// left. Integrators whose samples can land in pixels other than the one
+    /// being sampled override this.
+    fn render(&self, scene: &Scene, sampler: &mut dyn Sampler) -> Vec<Color> {
+        let cam = &scene.camera;
+        let mut pixels = Vec::with_capacity((cam.image_width * cam.image_height()) as usize);
+        for j in 0..cam.image_height() {
+            eprint!("\r Scanlines remaining: {j}");
+            for i in 0..cam.image_width {
+                let mut pixel_color = Color::zero();
+                for _sample in 0..cam.samples_per_pixel {
+                    let r = cam.get_ray(i, j, sampler);
+                    pixel_color += self.li(&r, scene, sampler);
+                }
+                pixels.push(pixel_color);
+            }
+        }
+        pixels
+    }
+}
+
+/// Shows the outward shading normal at each hit, each component mapped from
+/// [-1, 1] to [0, 1], for checking geometry. Rays that miss see the
+/// background.
+pub struct Normals;
+
+impl Integrator for Normals {
+    fn li(&self, r: &Ray, scene: &Scene, _sampler: &mut dyn Sampler) -> Color {
+        let mut rec = HitRecord::new();
+        if !scene.world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return scene.background.value(r);
+        }
+        let outward = if rec.front_face() { rec.normal() } else { -rec.normal() };
+        (outward + Color::one()) * 0.5
+    }
+}
+
+/// Shows how far each hit is from the camera, white up close fading to
+/// black at `max_distance` and beyond.
+pub struct Depth {
+    pub max_distance: f64,
+}
+
+impl Integrator for Depth {
+    fn li(&self, r: &Ray, scene: &Scene, _sampler: &mut dyn Sampler) -> Color {
+        let mut rec = HitRecord::new();
+        if !scene.world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return Color::zero();
+        }
+        let distance = rec.t * r.direction().length();
+        Color::from_float(1.0 - (distance / self.max_distance).min(1.0))
+    }
+}
+
+/// How much of the hemisphere above each hit is open out to `distance`,
+/// estimated with `samples` cosine weighted rays. Materials and lights are
+/// ignored: open sky is white and creases and contact shadows darken.
+pub struct AmbientOcclusion {
+    pub distance: f64,
+    pub samples: usize,
+}
+
+impl Integrator for AmbientOcclusion {
+    fn li(&self, r: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Color {
+        let mut rec = HitRecord::new();
+        if !scene.world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return Color::one();
+        }
+        let uvw = Onb::new(&rec.normal());
+        let mut open = 0;
+        for _ in 0..self.samples {
+            let (u1, u2) = sampler.get_2d();
+            let direction = uvw.transform(&cosine_hemisphere(u1, u2));
+            let shadow_ray = rec.spawn_ray(r, direction);
+            let mut blocker = HitRecord::new();
+            if !scene.world.hit(&shadow_ray, Interval::new(0.001, self.distance), &mut blocker) {
+                open += 1;
+            }
+        }
+        Color::from_float(open as f64 / self.samples.max(1) as f64)
+    }
+}
+
+/// Light reaching each surface straight from its sources, with no light
+/// bouncing between surfaces. Specular bounces are still followed, up to
+/// `max_depth`, so mirrors and glass show what is behind them.
+pub struct DirectLighting {
+    pub max_depth: i64,
+}
+
+impl DirectLighting {
+    fn radiance(&self, r: &Ray, scene: &Scene, depth: i64) -> Color {
+        if depth <= 0 {
+            return Color::zero();
+        }
+        let mut rec = HitRecord::new();
+        if !scene.world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return scene.background.value(r);
+        }
+        rec.compute_differentials(r);
+        let Some(mat) = rec.mat.clone() else {
+            return Color::zero();
+        };
+
+        let color = mat.emitted(r, &rec);
+        let mut srec = ScatterRecord::default();
+        if !mat.scatter(r, &rec, &mut srec) {
+            return color;
+        }
+        let Some(bsdf) = srec.pdf else {
+            return color + srec.attenuation * self.radiance(&srec.specular_ray, scene, depth - 1);
+        };
+
+        color + estimate_direct(r, &rec, mat.as_ref(), bsdf.as_ref(), scene)
+    }
+}
+
+impl Integrator for DirectLighting {
+    fn li(&self, r: &Ray, scene: &Scene, _sampler: &mut dyn Sampler) -> Color {
+        self.radiance(r, scene, self.max_depth)
+    }
+}
+
+/// Light reaching a non-specular hit straight from every source in the
+/// scene: a sample from each kind of light, plus one direction from `bsdf`
+/// weighted against them, which finds lights too big or too glossy to sample
+/// well.
+pub fn estimate_direct(r: &Ray, rec: &HitRecord, mat: &dyn Material, bsdf: &dyn Pdf, scene: &Scene) -> Color {
+    let mut color = Color::zero();
+    if !scene.lights.objects.is_empty() {
+        color += sample_light::<Color>(r, rec, mat, bsdf, scene);
+    }
+    if let Some(background_pdf) = scene.background.pdf() {
+        color += sample_background::<Color>(r, rec, mat, bsdf, background_pdf, scene);
+    }
+    for light in &scene.analytic_lights {
+        color += sample_analytic_light::<Color>(r, rec, mat, light.as_ref(), scene);
+    }
+
+    let scattered = rec.spawn_ray(r, bsdf.generate());
+    let pdf = bsdf.value(&scattered.direction());
+    if pdf <= 0.0 {
+        return color;
+    }
+    let mut light_rec = HitRecord::new();
+    let incoming = if !scene.world.hit(&scattered, Interval::new(0.001, f64::INFINITY), &mut light_rec) {
+        let mut background = scene.background.value(&scattered);
+        if let Some(background_pdf) = scene.background.pdf() {
+            background *= power_heuristic(pdf, background_pdf.value(&scattered.direction()));
+        }
+        background
+    } else if let Some(light_mat) = &light_rec.mat {
+        let mut emitted = light_mat.emitted(&scattered, &light_rec);
+        if !scene.lights.objects.is_empty() {
+            let light_pdf = scene.lights.pdf_value(&scattered.origin(), &scattered.direction(), scattered.time());
+            emitted *= power_heuristic(pdf, light_pdf);
+        }
+        emitted
+    } else {
+        Color::zero()
+    };
+    color + mat.eval(r, rec, &scattered) * incoming / pdf
+}
+
+/// Light reaching the hit directly from a point sampled on the scene's
+/// lights, weighted against `bsdf` having chosen the same direction.
+pub fn sample_light<S: Radiance>(r: &Ray, rec: &HitRecord, mat: &dyn Material, bsdf: &dyn Pdf, scene: &Scene) -> S {
+    let light = HittablePdf::new(&scene.lights, rec.p, r.time());
+    let direction = light.generate();
+    let shadow_ray = rec.spawn_ray(r, direction);
+    let f = mat.eval(r, rec, &shadow_ray);
+    if f == Color::zero() {
+        return S::zero();
+    }
+    let light_pdf = light.value(&direction);
+    if light_pdf <= 0.0 {
+        return S::zero();
+    }
+
+    // Whatever the shadow ray hits first is what it sees
+    let mut light_rec = HitRecord::new();
+    if !scene.world.hit(&shadow_ray, Interval::new(0.001, f64::INFINITY), &mut light_rec) {
+        return S::zero();
+    }
+    let Some(light_mat) = &light_rec.mat else {
+        return S::zero();
+    };
+    let emitted = light_mat.emitted(&shadow_ray, &light_rec);
+
+    let weight = power_heuristic(light_pdf, bsdf.value(&direction));
+    S::from_rgb(f, r) * S::from_rgb(emitted, r) * (weight / light_pdf)
+}
+
+/// Light reaching the hit from a direction drawn from the background's
+/// distribution, if nothing in the scene is in the way.
+pub fn sample_background<S: Radiance>(
+    r: &Ray,
+    rec: &HitRecord,
+    mat: &dyn Material,
+    bsdf: &dyn Pdf,
+    background_pdf: &dyn Pdf,
+    scene: &Scene,
+) -> S {
+    let direction = background_pdf.generate();
+    let shadow_ray = rec.spawn_ray(r, direction);
+    let f = mat.eval(r, rec, &shadow_ray);
+    if f == Color::zero() {
+        return S::zero();
+    }
+    let pdf = background_pdf.value(&direction);
+    if pdf <= 0.0 {
+        return S::zero();
+    }
+
+    let mut blocker = HitRecord::new();
+    if scene.world.hit(&shadow_ray, Interval::new(0.001, f64::INFINITY), &mut blocker) {
+        return S::zero();
+    }
+    let weight = power_heuristic(pdf, bsdf.value(&direction));
+    S::from_rgb(f, r) * S::from_rgb(scene.background.value(&shadow_ray), r) * (weight / pdf)
+}
+
+/// Light reaching the hit from `light` unless something in the scene is in
+/// the way. No other strategy can find a delta light, so no weighting.
+pub fn sample_analytic_light<S: Radiance>(
+    r: &Ray,
+    rec: &HitRecord,
+    mat: &dyn Material,
+    light: &dyn Light,
+    scene: &Scene,
+) -> S {
+    let Some(sample) = light.sample(&rec.p) else {
+        return S::zero();
+    };
+    let shadow_ray = rec.spawn_ray(r, sample.direction);
+    let f = mat.eval(r, rec, &shadow_ray);
+    if f == Color::zero() {
+        return S::zero();
+    }
+
+    // Stop short of the light so a surface right behind it doesn't count
+    let mut blocker = HitRecord::new();
+    let reach = Interval::new(0.001, sample.distance * (1.0 - 1e-6));
+    if scene.world.hit(&shadow_ray, reach, &mut blocker) {
+        return S::zero();
+    }
+    S::from_rgb(f, r) * S::from_rgb(sample.irradiance, r)
+}
+
+#[test]
+fn debug_integrators_see_a_corner() {
+    use std::sync::Arc;
+
+    use crate::common::{camera::Camera, hittable::HittableList, material::Lambertian, quad::Quad, sampler::IndependentSampler};
+
+    // A floor meeting a wall two units ahead
+    let grey = Arc::new(Lambertian::new(Color::from_float(0.5)));
+    let mut world = HittableList::new();
+    world.objects.push(Box::new(Quad::new(
+        Point3::new(-5.0, -1.0, 0.0),
+        Vec3::new(10.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -2.0),
+        grey.clone(),
+    )));
+    world.objects.push(Box::new(Quad::new(
+        Point3::new(-5.0, -1.0, -2.0),
+        Vec3::new(10.0, 0.0, 0.0),
+        Vec3::new(0.0, 10.0, 0.0),
+        grey,
+    )));
+    let scene = Scene::new(Camera::default(), world);
+    let mut sampler = IndependentSampler;
+
+    // Straight ahead is the wall, facing the camera, and down is the floor
+    let ahead = Ray::new(Point3::zero(), Vec3::new(0.0, 0.0, -1.0));
+    let down = Ray::new(Point3::zero(), Vec3::new(0.0, -1.0, -0.5));
+    assert_eq!(Normals.li(&ahead, &scene, &mut sampler), Color::new(0.5, 0.5, 1.0));
+    assert_eq!(Normals.li(&down, &scene, &mut sampler), Color::new(0.5, 1.0, 0.5));
+
+    let depth = Depth { max_distance: 4.0 };
+    assert!((depth.li(&ahead, &scene, &mut sampler).x - 0.5).abs() < 1e-9);
+
+    // Away from the corner the whole hemisphere is open, in it about half
+    let ao = AmbientOcclusion {
+        distance: 1.0,
+        samples: 2000,
+    };
+    let open = ao.li(&ahead, &scene, &mut sampler).x;
+    let corner = ao.li(&Ray::new(Point3::zero(), Vec3::new(0.0, -0.999, -1.999)), &scene, &mut sampler).x;
+    assert_eq!(open, 1.0);
+    assert!(corner < 0.8, "{corner}");
+}
@@ -0,0 +1,171 @@
+use vec3::*;
+
+/// Light arriving at a point from an analytic light.
+pub struct LightSample {
+    /// Unit direction from the lit point towards the light.
+    pub direction: Vec3<f64>,
+    /// How far the light is along `direction`, infinite for directional lights.
+    pub distance: f64,
+    /// Irradiance on a surface facing the light, before any cosine.
+    pub irradiance: Color,
+}
+
+/// A light with no surface, such as a point or the sun. Rays can never hit
+/// one, so they only contribute through explicit shadow rays towards them.
+pub trait Light: Send + Sync {
+    /// The light reaching `p`, ignoring occlusion, or None if it cannot.
+    fn sample(&self, p: &Point3) -> Option<LightSample>;
+}
+
+/// Gives off `intensity` equally in every direction from a point, falling off
+/// with the inverse square of the distance.
+pub struct PointLight {
+    position: Point3,
+    intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Color) -> Self {
+        PointLight { position, intensity }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, p: &Point3) -> Option<LightSample> {
+        let to_light = self.position - *p;
+        let distance_squared = to_light.length_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        Some(LightSample {
+            direction: to_light / distance,
+            distance,
+            irradiance: self.intensity / distance_squared,
+        })
+    }
+}
+
+/// A point light shining only within a cone around `direction`. It is at full
+/// intensity out to `falloff_start` degrees from the axis and fades smoothly
+/// to nothing at `cone_angle` degrees.
+pub struct SpotLight {
+    position: Point3,
+    direction: Vec3<f64>,
+    intensity: Color,
+    cos_total_width: f64,
+    cos_falloff_start: f64,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Point3,
+        direction: Vec3<f64>,
+        intensity: Color,
+        cone_angle: f64,
+        falloff_start: f64,
+    ) -> Self {
+        SpotLight {
+            position,
+            direction: direction.unit_vector(),
+            intensity,
+            cos_total_width: cone_angle.to_radians().cos(),
+            cos_falloff_start: falloff_start.min(cone_angle).to_radians().cos(),
+        }
+    }
+
+    // Fraction of the intensity sent out at cos_theta off the axis
+    fn falloff(&self, cos_theta: f64) -> f64 {
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+        if cos_theta <= self.cos_total_width {
+            return 0.0;
+        }
+        let t = (cos_theta - self.cos_total_width) / (self.cos_falloff_start - self.cos_total_width);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, p: &Point3) -> Option<LightSample> {
+        let to_light = self.position - *p;
+        let distance_squared = to_light.length_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        let direction = to_light / distance;
+        let falloff = self.falloff(-direction.dot(&self.direction));
+        if falloff == 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            irradiance: self.intensity * (falloff / distance_squared),
+        })
+    }
+}
+
+/// Parallel light travelling along `direction` from infinitely far away, like
+/// the sun. `irradiance` is what a surface square on to it receives.
+pub struct DirectionalLight {
+    direction: Vec3<f64>,
+    irradiance: Color,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3<f64>, irradiance: Color) -> Self {
+        DirectionalLight {
+            direction: direction.unit_vector(),
+            irradiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _p: &Point3) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -self.direction,
+            distance: f64::INFINITY,
+            irradiance: self.irradiance,
+        })
+    }
+}
+
+#[test]
+fn analytic_lights_fall_off() {
+    let p = Point3::zero();
+
+    // Twice as far gets a quarter of the light
+    let near = PointLight::new(Point3::new(0.0, 1.0, 0.0), Color::from_float(4.0));
+    let far = PointLight::new(Point3::new(0.0, 2.0, 0.0), Color::from_float(4.0));
+    let near = near.sample(&p).unwrap();
+    assert_eq!(near.irradiance, Color::from_float(4.0));
+    assert_eq!(near.direction, Vec3::new(0.0, 1.0, 0.0));
+    assert_eq!(far.sample(&p).unwrap().irradiance, Color::from_float(1.0));
+
+    // A spot pointing down lights the point below it fully, one 30 degrees
+    // off partly, and one 45 degrees off not at all
+    let spot = SpotLight::new(
+        Point3::new(0.0, 1.0, 0.0),
+        Vec3::new(0.0, -1.0, 0.0),
+        Color::from_float(1.0),
+        40.0,
+        20.0,
+    );
+    assert_eq!(spot.sample(&p).unwrap().irradiance, Color::from_float(1.0));
+    let off = 30f64.to_radians().tan();
+    let partial = spot.sample(&Point3::new(off, 0.0, 0.0)).unwrap().irradiance.x;
+    let full = 30f64.to_radians().cos().powi(2);
+    assert!(partial > 0.0 && partial < full, "{partial}");
+    assert!(spot.sample(&Point3::new(1.0, 0.0, 0.0)).is_none());
+
+    // The sun is the same everywhere and comes from where it shines from
+    let sun = DirectionalLight::new(Vec3::new(0.0, -2.0, 0.0), Color::from_float(3.0));
+    let s = sun.sample(&Point3::new(100.0, 5.0, -7.0)).unwrap();
+    assert_eq!(s.direction, Vec3::new(0.0, 1.0, 0.0));
+    assert_eq!(s.irradiance, Color::from_float(3.0));
+    assert!(s.distance.is_infinite());
+}
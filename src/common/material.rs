@@ -0,0 +1,164 @@
+use core::f64;
+use std::sync::Arc;
+
+use crate::common::{
+    hittable::HitRecord,
+    pdf::{CosinePdf, Pdf, PhasePdf, SpherePdf},
+    phase::PhaseFunction,
+    ray::Ray,
+    texture::{SolidColor, Texture},
+};
+use vec3::*;
+
+/// How a material scatters a hit. A specular material picks one exact
+/// direction, `specular_ray`, dimmed by `attenuation`. Any other sets `pdf`,
+/// the distribution it would like directions drawn from; light arriving
+/// along a drawn direction is then weighted by `Material::eval` over the pdf.
+/// When rendering spectrally, a material whose response depends on the
+/// wavelength gives it for the hero alone and sets `hero_only`, and the
+/// camera drops the path's other wavelengths.
+#[derive(Default)]
+pub struct ScatterRecord {
+    pub attenuation: Color,
+    pub pdf: Option<Box<dyn Pdf>>,
+    pub specular_ray: Ray,
+    pub hero_only: bool,
+}
+
+pub trait Material: Send + Sync {
+    /// Returns false if the ray is absorbed, otherwise fills in `srec`.
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool;
+
+    /// The BSDF times the cosine at the surface, or the phase function inside
+    /// a medium, for light arriving along `scattered` and leaving back along
+    /// `r_in`. Zero for purely specular materials, which can only be sampled.
+    fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> Color {
+        Color::zero()
+    }
+
+    /// Density per steradian with which `scatter` sends light leaving back
+    /// along `r_in` off along `scattered`, over every lobe it might pick. Zero
+    /// for specular directions. Materials that choose a lobe at random must
+    /// override this, the default asks one call to `scatter`.
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let mut srec = ScatterRecord::default();
+        if !self.scatter(r_in, rec, &mut srec) {
+            return 0.0;
+        }
+        srec.pdf.map_or(0.0, |pdf| pdf.value(&scattered.direction()))
+    }
+
+    /// Light given off at a hit, black unless the material is a light source.
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord) -> Color {
+        Color::zero()
+    }
+}
+
+/// An ideal diffuse reflector.
+pub struct Lambertian {
+    tex: Arc<dyn Texture>,
+}
+
+impl Lambertian {
+    pub fn new(albedo: Color) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Lambertian { tex }
+    }
+}
+
+impl Material for Lambertian {
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        srec.attenuation = self.tex.sample(rec);
+        srec.pdf = Some(Box::new(CosinePdf::new(&rec.normal())));
+        true
+    }
+
+    fn eval(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        let cosine = rec.normal().dot(&scattered.direction().unit_vector());
+        self.tex.sample(rec) * (cosine.max(0.0) / f64::consts::PI)
+    }
+}
+
+/// An emitter that gives off light from the front of a surface and scatters
+/// none. Any shape with this material becomes an area light.
+pub struct DiffuseLight {
+    tex: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(emit)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        DiffuseLight { tex }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _srec: &mut ScatterRecord) -> bool {
+        false
+    }
+
+    fn emitted(&self, _r_in: &Ray, rec: &HitRecord) -> Color {
+        if !rec.front_face() {
+            return Color::zero();
+        }
+        self.tex.sample(rec)
+    }
+}
+
+/// Scatters equally in every direction, used as the phase function inside
+/// participating media.
+pub struct Isotropic {
+    albedo: Color,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Color) -> Self {
+        Isotropic { albedo }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        srec.attenuation = self.albedo;
+        srec.pdf = Some(Box::new(SpherePdf));
+        true
+    }
+
+    fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> Color {
+        self.albedo / (4.0 * f64::consts::PI)
+    }
+}
+
+/// Scatters according to an arbitrary phase function inside a medium.
+pub struct Volumetric {
+    albedo: Color,
+    phase: Arc<dyn PhaseFunction>,
+}
+
+impl Volumetric {
+    pub fn new(albedo: Color, phase: Arc<dyn PhaseFunction>) -> Self {
+        Volumetric { albedo, phase }
+    }
+}
+
+impl Material for Volumetric {
+    fn scatter(&self, r_in: &Ray, _rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        srec.attenuation = self.albedo;
+        srec.pdf = Some(Box::new(PhasePdf::new(self.phase.clone(), &r_in.direction())));
+        true
+    }
+
+    fn eval(&self, r_in: &Ray, _rec: &HitRecord, scattered: &Ray) -> Color {
+        self.albedo
+            * self.phase.p(
+                &r_in.direction().unit_vector(),
+                &scattered.direction().unit_vector(),
+            )
+    }
+}
@@ -0,0 +1,444 @@
+use core::f64;
+
+use crate::common::{
+    hittable::HitRecord,
+    material::{Material, ScatterRecord},
+    pdf::Pdf,
+    ray::Ray,
+    specular,
+    util::random_double,
+};
+use vec3::*;
+
+// Below this alpha a surface is treated as perfectly smooth
+const SMOOTH_ALPHA: f64 = 1e-3;
+
+/// The Trowbridge-Reitz (GGX) distribution of microfacet normals with Smith's
+/// shadowing-masking. Directions are in a frame with the macro surface normal
+/// along +z.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrowbridgeReitz {
+    alpha: f64,
+}
+
+impl TrowbridgeReitz {
+    pub fn new(alpha: f64) -> Self {
+        TrowbridgeReitz { alpha }
+    }
+
+    /// Alpha from a perceptual roughness in [0, 1], squared so that
+    /// roughness changes look even.
+    pub fn from_roughness(roughness: f64) -> Self {
+        Self::new(roughness.clamp(0.0, 1.0).powi(2))
+    }
+
+    pub fn is_smooth(&self) -> bool {
+        self.alpha < SMOOTH_ALPHA
+    }
+
+    /// Density of microfacet normal `wm` per unit projected area.
+    pub fn d(&self, wm: &Vec3<f64>) -> f64 {
+        let cos2 = wm.z * wm.z;
+        if cos2 == 0.0 {
+            return 0.0;
+        }
+        let a2 = self.alpha * self.alpha;
+        let e = (wm.x * wm.x + wm.y * wm.y) / (a2 * cos2);
+        1.0 / (f64::consts::PI * a2 * cos2 * cos2 * (1.0 + e) * (1.0 + e))
+    }
+
+    // Smith's auxiliary function, the masked area over the visible area
+    fn lambda(&self, w: &Vec3<f64>) -> f64 {
+        let cos2 = w.z * w.z;
+        if cos2 == 0.0 {
+            return f64::INFINITY;
+        }
+        let tan2 = (w.x * w.x + w.y * w.y) / cos2;
+        ((1.0 + self.alpha * self.alpha * tan2).sqrt() - 1.0) / 2.0
+    }
+
+    /// Fraction of microfacets visible from `w`.
+    pub fn g1(&self, w: &Vec3<f64>) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Fraction of microfacets visible from both `wo` and `wi`, height
+    /// correlated.
+    pub fn g(&self, wo: &Vec3<f64>, wi: &Vec3<f64>) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Density of the normals visible from `w`, per solid angle of `wm`.
+    pub fn pdf(&self, w: &Vec3<f64>, wm: &Vec3<f64>) -> f64 {
+        if w.z == 0.0 {
+            return 0.0;
+        }
+        self.g1(w) / w.z.abs() * self.d(wm) * w.dot(wm).abs()
+    }
+
+    /// A microfacet normal visible from `w`, following Heitz's 2018 method of
+    /// sampling the projected hemisphere of the stretched configuration.
+    pub fn sample_wm(&self, w: &Vec3<f64>, u1: f64, u2: f64) -> Vec3<f64> {
+        // Stretch to the configuration where alpha is one
+        let mut wh = Vec3::new(self.alpha * w.x, self.alpha * w.y, w.z).unit_vector();
+        if wh.z < 0.0 {
+            wh = -wh;
+        }
+        let t1 = if wh.z < 0.99999 {
+            Vec3::cross_product(&Vec3::new(0.0, 0.0, 1.0), &wh).unit_vector()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = Vec3::cross_product(&wh, &t1);
+
+        // A point on the disk, squeezed onto the part of it the visible
+        // hemisphere projects to
+        let r = u1.sqrt();
+        let phi = 2.0 * f64::consts::PI * u2;
+        let px = r * phi.cos();
+        let h = (1.0 - px * px).max(0.0).sqrt();
+        let s = 0.5 * (1.0 + wh.z);
+        let py = (1.0 - s) * h + s * r * phi.sin();
+        let pz = (1.0 - px * px - py * py).max(0.0).sqrt();
+        let nh = t1 * px + t2 * py + wh * pz;
+
+        // And back again
+        Vec3::new(self.alpha * nh.x, self.alpha * nh.y, nh.z.max(1e-6)).unit_vector()
+    }
+}
+
+/// Fresnel reflectance of a smooth boundary into a medium `eta` times as
+/// dense, for light arriving at `cos_theta_i` to the normal on the incident
+/// side. Negative cosines arrive from inside.
+pub fn fresnel_dielectric(cos_theta_i: f64, eta: f64) -> f64 {
+    let (mut cos_i, mut eta) = (cos_theta_i.clamp(-1.0, 1.0), eta);
+    if cos_i < 0.0 {
+        eta = 1.0 / eta;
+        cos_i = -cos_i;
+    }
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.0
+}
+
+/// Fresnel reflectance of a metal with complex refractive index eta + ik, per
+/// channel.
+pub fn fresnel_conductor(cos_theta_i: f64, eta: &Color, k: &Color) -> Color {
+    let cos2 = cos_theta_i.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let channel = |eta: f64, k: f64| {
+        let t0 = eta * eta - k * k - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t2 = 2.0 * cos2.sqrt() * a;
+        let rs = (t1 - t2) / (t1 + t2);
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+        0.5 * (rp + rs)
+    };
+    Color::new(channel(eta.x, k.x), channel(eta.y, k.y), channel(eta.z, k.z))
+}
+
+/// A hit's shading frame, with the normal along +z, and the direction back
+/// along `r_in` in it.
+pub fn local_frame(r_in: &Ray, rec: &HitRecord) -> (Onb, Vec3<f64>) {
+    let frame = Onb::new(&rec.normal());
+    let wo = frame.to_local(&-r_in.direction().unit_vector());
+    (frame, wo)
+}
+
+fn reflect(wo: &Vec3<f64>, wm: &Vec3<f64>) -> Vec3<f64> {
+    -*wo + *wm * (2.0 * wo.dot(wm))
+}
+
+/// A rough metal with complex refractive index `eta` + i`k`.
+pub struct RoughConductor {
+    eta: Color,
+    k: Color,
+    distribution: TrowbridgeReitz,
+}
+
+impl RoughConductor {
+    pub fn new(eta: Color, k: Color, distribution: TrowbridgeReitz) -> Self {
+        RoughConductor { eta, k, distribution }
+    }
+}
+
+/// Mirror directions of `wo` about visible microfacet normals, in the frame
+/// `frame` that `wo` is given in.
+pub struct ReflectionPdf {
+    frame: Onb,
+    wo: Vec3<f64>,
+    distribution: TrowbridgeReitz,
+}
+
+impl ReflectionPdf {
+    pub fn new(frame: Onb, wo: Vec3<f64>, distribution: TrowbridgeReitz) -> Self {
+        ReflectionPdf {
+            frame,
+            wo,
+            distribution,
+        }
+    }
+}
+
+impl Pdf for ReflectionPdf {
+    fn value(&self, direction: &Vec3<f64>) -> f64 {
+        let wi = self.frame.to_local(&direction.unit_vector());
+        if wi.z <= 0.0 {
+            return 0.0;
+        }
+        let wm = (self.wo + wi).unit_vector();
+        self.distribution.pdf(&self.wo, &wm) / (4.0 * self.wo.dot(&wm).abs())
+    }
+
+    fn generate(&self) -> Vec3<f64> {
+        let wm = self.distribution.sample_wm(&self.wo, random_double(), random_double());
+        self.frame.transform(&reflect(&self.wo, &wm))
+    }
+}
+
+impl Material for RoughConductor {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        let (frame, wo) = local_frame(r_in, rec);
+        if wo.z <= 0.0 {
+            return false;
+        }
+        if self.distribution.is_smooth() {
+            let direction = specular::reflect(&r_in.direction().unit_vector(), &rec.normal());
+            srec.specular_ray = rec
+                .spawn_ray(r_in, direction)
+                .with_differentials(rec.reflected_differentials(r_in, &direction));
+            srec.attenuation = fresnel_conductor(wo.z, &self.eta, &self.k);
+            srec.pdf = None;
+            return true;
+        }
+        srec.attenuation = Color::one();
+        srec.pdf = Some(Box::new(ReflectionPdf::new(frame, wo, self.distribution)));
+        true
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        let (frame, wo) = local_frame(r_in, rec);
+        let wi = frame.to_local(&scattered.direction().unit_vector());
+        if wo.z <= 0.0 || wi.z <= 0.0 || self.distribution.is_smooth() {
+            return Color::zero();
+        }
+        let wm = (wo + wi).unit_vector();
+        let f = fresnel_conductor(wo.dot(&wm).abs(), &self.eta, &self.k);
+        // D F G / (4 cos_o cos_i), times cos_i
+        f * (self.distribution.d(&wm) * self.distribution.g(&wo, &wi) / (4.0 * wo.z))
+    }
+}
+
+/// A rough boundary into glass or another clear medium of refractive index
+/// `eta`, both reflecting and transmitting through the microfacets.
+pub struct RoughDielectric {
+    eta: f64,
+    distribution: TrowbridgeReitz,
+}
+
+impl RoughDielectric {
+    pub fn new(eta: f64, distribution: TrowbridgeReitz) -> Self {
+        RoughDielectric { eta, distribution }
+    }
+
+    // The index on the far side over the index on the near side
+    fn relative_eta(&self, rec: &HitRecord) -> f64 {
+        if rec.front_face() { self.eta } else { 1.0 / self.eta }
+    }
+}
+
+// The microfacet normal that takes wo to wi, facing the same side as the
+// macro normal, or None if no facet could
+fn half_vector(wo: &Vec3<f64>, wi: &Vec3<f64>, etap: f64) -> Option<Vec3<f64>> {
+    let reflect = wi.z > 0.0;
+    let wm = if reflect { *wo + *wi } else { *wi * etap + *wo };
+    if wm.near_zero() {
+        return None;
+    }
+    let wm = if wm.z < 0.0 { -wm.unit_vector() } else { wm.unit_vector() };
+    // Facets seen from behind by either direction contribute nothing
+    if wm.dot(wi) * wi.z < 0.0 || wm.dot(wo) * wo.z < 0.0 {
+        return None;
+    }
+    Some(wm)
+}
+
+/// Reflections and refractions of `wo` through visible microfacet normals,
+/// chosen between by the Fresnel term, for a boundary with relative index
+/// `etap`.
+pub struct DielectricPdf {
+    frame: Onb,
+    wo: Vec3<f64>,
+    etap: f64,
+    distribution: TrowbridgeReitz,
+}
+
+impl DielectricPdf {
+    pub fn new(frame: Onb, wo: Vec3<f64>, etap: f64, distribution: TrowbridgeReitz) -> Self {
+        DielectricPdf {
+            frame,
+            wo,
+            etap,
+            distribution,
+        }
+    }
+}
+
+impl Pdf for DielectricPdf {
+    fn value(&self, direction: &Vec3<f64>) -> f64 {
+        let wi = self.frame.to_local(&direction.unit_vector());
+        let Some(wm) = half_vector(&self.wo, &wi, self.etap) else {
+            return 0.0;
+        };
+        let r = fresnel_dielectric(self.wo.dot(&wm), self.etap);
+        let pdf_wm = self.distribution.pdf(&self.wo, &wm);
+        if wi.z > 0.0 {
+            r * pdf_wm / (4.0 * self.wo.dot(&wm).abs())
+        } else {
+            let denom = wi.dot(&wm) + self.wo.dot(&wm) / self.etap;
+            (1.0 - r) * pdf_wm * wi.dot(&wm).abs() / (denom * denom)
+        }
+    }
+
+    fn generate(&self) -> Vec3<f64> {
+        let wm = self.distribution.sample_wm(&self.wo, random_double(), random_double());
+        let cos_o = self.wo.dot(&wm);
+        let r = fresnel_dielectric(cos_o, self.etap);
+        if random_double() < r {
+            return self.frame.transform(&reflect(&self.wo, &wm));
+        }
+        // Snell's law through the facet; total internal reflection has r = 1
+        let sin2_t = (1.0 - cos_o * cos_o) / (self.etap * self.etap);
+        let cos_t = (1.0 - sin2_t).max(0.0).sqrt();
+        let wi = -self.wo / self.etap + wm * (cos_o / self.etap - cos_t);
+        self.frame.transform(&wi)
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        let (frame, wo) = local_frame(r_in, rec);
+        let etap = self.relative_eta(rec);
+        if self.distribution.is_smooth() {
+            // A plain glass boundary, choosing by the exact Fresnel term
+            let unit_direction = r_in.direction().unit_vector();
+            srec.attenuation = Color::one();
+            srec.pdf = None;
+            srec.specular_ray = if random_double() < fresnel_dielectric(wo.z, etap) {
+                let direction = specular::reflect(&unit_direction, &rec.normal());
+                rec.spawn_ray(r_in, direction)
+                    .with_differentials(rec.reflected_differentials(r_in, &direction))
+            } else {
+                let direction = specular::refract(&unit_direction, &rec.normal(), 1.0 / etap);
+                srec.attenuation = Color::from_float(1.0 / (etap * etap));
+                rec.spawn_ray(r_in, direction)
+                    .with_differentials(rec.refracted_differentials(r_in, &direction, 1.0 / etap))
+            };
+            return true;
+        }
+        srec.attenuation = Color::one();
+        srec.pdf = Some(Box::new(DielectricPdf::new(frame, wo, etap, self.distribution)));
+        true
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        if self.distribution.is_smooth() {
+            return Color::zero();
+        }
+        let (frame, wo) = local_frame(r_in, rec);
+        let wi = frame.to_local(&scattered.direction().unit_vector());
+        Color::from_float(dielectric_bsdf(&self.distribution, &wo, &wi, self.relative_eta(rec)))
+    }
+}
+
+/// The rough dielectric BSDF times |cos_i| for local directions `wo` and `wi`
+/// across a boundary with relative index `etap`.
+pub fn dielectric_bsdf(distribution: &TrowbridgeReitz, wo: &Vec3<f64>, wi: &Vec3<f64>, etap: f64) -> f64 {
+    let Some(wm) = half_vector(wo, wi, etap) else {
+        return 0.0;
+    };
+    let r = fresnel_dielectric(wo.dot(&wm), etap);
+    let d = distribution.d(&wm);
+    let g = distribution.g(wo, wi);
+    if wi.z > 0.0 {
+        // D F G / (4 cos_o cos_i), times cos_i
+        d * r * g / (4.0 * wo.z.abs())
+    } else {
+        // Walter et al.'s transmission term times |cos_i|, with radiance
+        // compressed into the smaller solid angle on the dense side
+        let denom = wi.dot(&wm) + wo.dot(&wm) / etap;
+        d * (1.0 - r) * g * (wi.dot(&wm) * wo.dot(&wm)).abs() / (wo.z.abs() * denom * denom * etap * etap)
+    }
+}
+
+/// Estimates how much light `mat` returns from a hit on the z = 0 plane seen
+/// at `cos_o`, by averaging f / pdf over sampled directions. Light refracted
+/// into a medium of relative index `etap` has its radiance scaling undone so
+/// the result counts energy.
+#[cfg(test)]
+pub fn furnace_albedo(mat: &dyn Material, cos_o: f64, etap: f64) -> Color {
+    let sin_o = (1.0 - cos_o * cos_o).sqrt();
+    let r = Ray::new(Point3::new(-sin_o, 0.0, cos_o), Vec3::new(sin_o, 0.0, -cos_o));
+    let mut rec = HitRecord::new();
+    rec.set_face_normal(&r, &Vec3::new(0.0, 0.0, 1.0));
+    let mut srec = ScatterRecord::default();
+    assert!(mat.scatter(&r, &rec, &mut srec));
+    let pdf = srec.pdf.unwrap();
+    let n = 20_000;
+    let mut sum = Color::zero();
+    for _ in 0..n {
+        let scattered = rec.spawn_ray(&r, pdf.generate());
+        let p = pdf.value(&scattered.direction());
+        if p <= 0.0 {
+            continue;
+        }
+        let scale = if scattered.direction().z < 0.0 { etap * etap } else { 1.0 };
+        sum += mat.eval(&r, &rec, &scattered) * (scale / p);
+    }
+    sum / n as f64
+}
+
+#[test]
+fn white_furnace() {
+    use std::sync::Arc;
+
+    let albedo = |mat: &dyn Material, cos_o: f64, etap: f64| furnace_albedo(mat, cos_o, etap).x;
+
+    // A metal reflecting everything loses light only to single scattering's
+    // neglect of light bouncing between facets, which grows with roughness
+    let mirror = |roughness| {
+        Arc::new(RoughConductor::new(
+            Color::zero(),
+            Color::from_float(1e6),
+            TrowbridgeReitz::from_roughness(roughness),
+        ))
+    };
+    for cos_o in [1.0, 0.7, 0.3] {
+        let smooth = albedo(mirror(0.2).as_ref(), cos_o, 1.0);
+        assert!(smooth > 0.97 && smooth < 1.01, "smooth at {cos_o}: {smooth}");
+        let medium = albedo(mirror(0.5).as_ref(), cos_o, 1.0);
+        assert!(medium > 0.8 && medium < smooth, "medium at {cos_o}: {medium}");
+        let rough = albedo(mirror(0.9).as_ref(), cos_o, 1.0);
+        assert!(rough > 0.3 && rough < medium, "rough at {cos_o}: {rough}");
+    }
+
+    // Glass splits the light but, counting energy, keeps it
+    let glass = RoughDielectric::new(1.5, TrowbridgeReitz::from_roughness(0.2));
+    for cos_o in [1.0, 0.7, 0.3] {
+        let total = albedo(&glass, cos_o, 1.5);
+        assert!(total > 0.95 && total < 1.02, "glass at {cos_o}: {total}");
+    }
+
+    // A normal-incidence Fresnel check against the textbook 4%
+    assert!((fresnel_dielectric(1.0, 1.5) - 0.04).abs() < 1e-12);
+    assert_eq!(fresnel_dielectric(0.1, 1.0 / 1.5), 1.0);
+}
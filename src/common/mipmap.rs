@@ -0,0 +1,288 @@
+use std::sync::Arc;
+
+use crate::common::{image::Image, texture::WrapMode};
+use vec3::*;
+
+/// How an image texture averages over a pixel's footprint.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FilterMode {
+    /// Bilinear interpolation of the full resolution image, ignoring the footprint.
+    Bilinear,
+    /// Bilinear lookups in the two MIP levels bracketing the footprint's width.
+    Trilinear,
+    /// An elliptically weighted average over the footprint, which stays sharp
+    /// along the ellipse's long axis at grazing angles.
+    Ewa,
+}
+
+// Caps how stretched an EWA footprint may get, bounding the texels it reads
+const MAX_ANISOTROPY: f64 = 8.0;
+
+/// An image and successively halved copies of it, built once up front.
+pub struct MipMap {
+    levels: Vec<Arc<Image>>,
+    wrap: WrapMode,
+}
+
+impl MipMap {
+    pub fn new(image: Arc<Image>, wrap: WrapMode) -> Self {
+        let mut levels = vec![image];
+        loop {
+            let last = &levels[levels.len() - 1];
+            if last.width() <= 1 && last.height() <= 1 {
+                break;
+            }
+            let next = Self::downsample(last, wrap);
+            levels.push(Arc::new(next));
+        }
+        MipMap { levels, wrap }
+    }
+
+    // Box filters 2x2 blocks, wrapping at the edges of odd sized images
+    fn downsample(image: &Image, wrap: WrapMode) -> Image {
+        let width = image.width().div_ceil(2);
+        let height = image.height().div_ceil(2);
+        let fetch = |x: usize, y: usize| {
+            let x = wrap.apply(x as i64, image.width());
+            let y = wrap.apply(y as i64, image.height());
+            image.pixel(x, y)
+        };
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let sum = fetch(2 * x, 2 * y)
+                    + fetch(2 * x + 1, 2 * y)
+                    + fetch(2 * x, 2 * y + 1)
+                    + fetch(2 * x + 1, 2 * y + 1);
+                pixels.push(sum * 0.25);
+            }
+        }
+        Image::new(width, height, pixels)
+    }
+
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn level(&self, level: usize) -> &Image {
+        &self.levels[level.min(self.levels.len() - 1)]
+    }
+
+    fn texel(&self, level: usize, x: i64, y: i64) -> Color {
+        let image = self.level(level);
+        let x = self.wrap.apply(x, image.width());
+        let y = self.wrap.apply(y, image.height());
+        image.pixel(x, y)
+    }
+
+    /// Bilinear interpolation at (s, t), with t = 0 at the top row.
+    pub fn bilinear(&self, level: usize, s: f64, t: f64) -> Color {
+        let image = self.level(level);
+        // Continuous texel coordinates with samples at texel centers
+        let x = s * image.width() as f64 - 0.5;
+        let y = t * image.height() as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = self.texel(level, x0, y0) * (1.0 - fx) + self.texel(level, x0 + 1, y0) * fx;
+        let bottom =
+            self.texel(level, x0, y0 + 1) * (1.0 - fx) + self.texel(level, x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    /// Filters at (s, t) over the footprint spanned by the offsets `dst0` and
+    /// `dst1`, both in the same normalized coordinates as (s, t).
+    pub fn filter(&self, filter: FilterMode, s: f64, t: f64, dst0: (f64, f64), dst1: (f64, f64)) -> Color {
+        // Measure the footprint in texels of the full resolution image
+        let (w, h) = (self.levels[0].width() as f64, self.levels[0].height() as f64);
+        let mut dst0 = (dst0.0 * w, dst0.1 * h);
+        let mut dst1 = (dst1.0 * w, dst1.1 * h);
+        let length = |d: (f64, f64)| (d.0 * d.0 + d.1 * d.1).sqrt();
+
+        match filter {
+            FilterMode::Bilinear => self.bilinear(0, s, t),
+            FilterMode::Trilinear => {
+                let width = length(dst0).max(length(dst1));
+                self.between_levels(width, |level| self.bilinear(level, s, t))
+            }
+            FilterMode::Ewa => {
+                if length(dst0) < length(dst1) {
+                    std::mem::swap(&mut dst0, &mut dst1);
+                }
+                let major = length(dst0);
+                let mut minor = length(dst1);
+                if minor == 0.0 {
+                    return self.bilinear(0, s, t);
+                }
+                // Fatten overly eccentric ellipses, trading blur for speed
+                if minor * MAX_ANISOTROPY < major {
+                    let scale = major / (minor * MAX_ANISOTROPY);
+                    dst1 = (dst1.0 * scale, dst1.1 * scale);
+                    minor *= scale;
+                }
+                self.between_levels(minor, |level| {
+                    // Texels at this level are 2^level times wider
+                    let shrink = 1.0 / (1u64 << level.min(62)) as f64;
+                    let d0 = (dst0.0 * shrink, dst0.1 * shrink);
+                    let d1 = (dst1.0 * shrink, dst1.1 * shrink);
+                    self.ewa(level, s, t, d0, d1)
+                })
+            }
+        }
+    }
+
+    // Blends lookups in the two levels whose texels are nearest `width` level 0
+    // texels across
+    fn between_levels(&self, width: f64, lookup: impl Fn(usize) -> Color) -> Color {
+        let lod = width.max(1e-8).log2().max(0.0);
+        let top = (self.levels.len() - 1) as f64;
+        if lod >= top {
+            return lookup(self.levels.len() - 1);
+        }
+        let base = lod.floor();
+        let frac = lod - base;
+        let base = base as usize;
+        if frac == 0.0 {
+            return lookup(base);
+        }
+        lookup(base) * (1.0 - frac) + lookup(base + 1) * frac
+    }
+
+    // Gaussian weighted average over the ellipse with axes d0 and d1, given in
+    // texels of the chosen level
+    fn ewa(&self, level: usize, s: f64, t: f64, d0: (f64, f64), d1: (f64, f64)) -> Color {
+        let image = self.level(level);
+        let x = s * image.width() as f64 - 0.5;
+        let y = t * image.height() as f64 - 0.5;
+
+        // Implicit ellipse A s^2 + B s t + C t^2 = 1, padded by one texel so
+        // it never falls between texel centers
+        let mut a = d0.1 * d0.1 + d1.1 * d1.1 + 1.0;
+        let mut b = -2.0 * (d0.0 * d0.1 + d1.0 * d1.1);
+        let mut c = d0.0 * d0.0 + d1.0 * d1.0 + 1.0;
+        let inv_f = 1.0 / (a * c - b * b * 0.25);
+        a *= inv_f;
+        b *= inv_f;
+        c *= inv_f;
+
+        // Bounding box of the ellipse
+        let det = -b * b + 4.0 * a * c;
+        let inv_det = 1.0 / det;
+        let u_sqrt = (det * c).sqrt();
+        let v_sqrt = (a * det).sqrt();
+        let x0 = (x - 2.0 * inv_det * u_sqrt).ceil() as i64;
+        let x1 = (x + 2.0 * inv_det * u_sqrt).floor() as i64;
+        let y0 = (y - 2.0 * inv_det * v_sqrt).ceil() as i64;
+        let y1 = (y + 2.0 * inv_det * v_sqrt).floor() as i64;
+
+        let falloff = 2.0;
+        let mut sum = Color::zero();
+        let mut weight_sum = 0.0;
+        for iy in y0..=y1 {
+            let dy = iy as f64 - y;
+            for ix in x0..=x1 {
+                let dx = ix as f64 - x;
+                let r2 = a * dx * dx + b * dx * dy + c * dy * dy;
+                if r2 < 1.0 {
+                    let weight = (-falloff * r2).exp() - (-falloff).exp();
+                    sum += self.texel(level, ix, iy) * weight;
+                    weight_sum += weight;
+                }
+            }
+        }
+        if weight_sum > 0.0 {
+            sum / weight_sum
+        } else {
+            self.bilinear(level, s, t)
+        }
+    }
+}
+
+#[test]
+fn pyramid_preserves_the_mean() {
+    // A 4x2 checkerboard of single texels
+    let pixels = (0..8usize)
+        .map(|i| if (i % 4 + i / 4).is_multiple_of(2) { Color::one() } else { Color::zero() })
+        .collect();
+    let mip = MipMap::new(Arc::new(Image::new(4, 2, pixels)), WrapMode::Repeat);
+    assert_eq!(mip.levels(), 3);
+    assert_eq!((mip.level(1).width(), mip.level(1).height()), (2, 1));
+    assert_eq!(mip.level(2).pixel(0, 0), Color::from_float(0.5));
+
+    // A footprint a few texels wide blurs the checks to grey, while a point
+    // lookup at a texel center keeps them
+    let wide = (2.0 / 4.0, 0.0);
+    let tall = (0.0, 2.0 / 2.0);
+    for filter in [FilterMode::Trilinear, FilterMode::Ewa] {
+        let c = mip.filter(filter, 0.125, 0.25, wide, tall);
+        assert!((c.x - 0.5).abs() < 0.05, "{filter:?} gave {}", c.x);
+    }
+    assert_eq!(mip.filter(FilterMode::Bilinear, 0.125, 0.25, wide, tall).x, 1.0);
+}
+
+#[test]
+fn curved_mirrors_and_lenses_pick_coarser_levels() {
+    use crate::common::{
+        cuboid::Cuboid,
+        hittable::{HitRecord, Hittable},
+        material::Lambertian,
+        ray::{Ray, RayDifferential},
+        specular::refract,
+        sphere::Sphere,
+        util::Interval,
+    };
+
+    // A pinhole ray whose neighbours diverge by delta, hitting a unit sphere
+    // or the flat top of a box face on after travelling distance d
+    let (delta, d) = (1e-3, 10.0);
+    let origin = Point3::new(0.0, 0.0, 1.0 + d);
+    let r = Ray::new(origin, Vec3::new(0.0, 0.0, -1.0)).with_differentials(Some(RayDifferential {
+        rx_origin: origin,
+        rx_direction: Vec3::new(delta, 0.0, -1.0),
+        ry_origin: origin,
+        ry_direction: Vec3::new(0.0, delta, -1.0),
+    }));
+    let mat = Arc::new(Lambertian::new(Color::one()));
+    let sphere = Sphere::new(Point3::zero(), 1.0, mat.clone());
+    let slab = Cuboid::new(Point3::new(-2.0, -2.0, 0.0), Point3::new(2.0, 2.0, 1.0), mat);
+    let hit = |surface: &dyn Hittable| {
+        let mut rec = HitRecord::new();
+        assert!(surface.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+        rec.compute_differentials(&r);
+        rec
+    };
+    let (curved, flat) = (hit(&sphere), hit(&slab));
+
+    // How far the x neighbour's direction strays from the main one
+    let reflected = Vec3::new(0.0, 0.0, 1.0);
+    let refracted = refract(&r.direction(), &curved.normal(), 1.0 / 1.5);
+    let spread = |rd: Option<RayDifferential>, wi: Vec3<f64>| rd.unwrap().rx_direction.x - wi.x;
+    let flat_mirror = spread(flat.reflected_differentials(&r, &reflected), reflected);
+    let curved_mirror = spread(curved.reflected_differentials(&r, &reflected), reflected);
+    let curved_lens = spread(curved.refracted_differentials(&r, &refracted, 1.0 / 1.5), refracted);
+
+    // A flat mirror keeps the spread, a convex one adds twice the normal's
+    // turn d * delta, and the lens bends the spread by Snell's law
+    assert!((flat_mirror - delta).abs() < 1e-6 * delta, "{flat_mirror}");
+    assert!((curved_mirror - (1.0 + 2.0 * d) * delta).abs() < 1e-2 * delta, "{curved_mirror}");
+    let lens = (1.0 - 0.5 * d) * delta / 1.5;
+    assert!((curved_lens - lens).abs() < 1e-2 * delta, "{curved_lens}");
+
+    // Carried to a 64 texel checkerboard far enough away for the flat
+    // mirror's footprint to stay under a texel, the curved surfaces' wider
+    // footprints blend in the grey levels above the first
+    let pixels = (0..64 * 64usize)
+        .map(|i| if (i % 64 + i / 64).is_multiple_of(2) { Color::one() } else { Color::zero() })
+        .collect();
+    let mip = MipMap::new(Arc::new(Image::new(64, 64, pixels)), WrapMode::Repeat);
+    let distance = 0.5 / (64.0 * delta);
+    let lookup = |spread: f64| {
+        let footprint = spread.abs() * distance;
+        mip.filter(FilterMode::Trilinear, 0.5 / 64.0, 0.5 / 64.0, (footprint, 0.0), (0.0, footprint)).x
+    };
+    assert_eq!(lookup(flat_mirror), 1.0);
+    assert!((lookup(curved_mirror) - 0.5).abs() < 1e-9);
+    let blurred = lookup(curved_lens);
+    assert!(blurred > 0.5 && blurred < 0.9, "{blurred}");
+}
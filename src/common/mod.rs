@@ -1,5 +1,41 @@
+pub mod background;
+pub mod bdpt;
+pub mod bump;
 pub mod camera;
+pub mod cone;
+pub mod constant_medium;
+pub mod csg;
+pub mod cuboid;
+pub mod cylinder;
+pub mod distribution;
+pub mod envmap;
+pub mod grid_volume;
 pub mod hittable;
+pub mod image;
+pub mod integrator;
+pub mod light;
+pub mod material;
+pub mod microfacet;
+pub mod mipmap;
+pub mod paraboloid;
+pub mod path;
+pub mod pdf;
+pub mod perlin;
+pub mod phase;
+pub mod photon;
+pub mod principled;
+pub mod quad;
 pub mod ray;
+pub mod sampler;
+pub mod scene;
+pub mod sdf;
+pub mod sky;
+pub mod spectrum;
+pub mod specular;
 pub mod sphere;
+pub mod subsurface;
+pub mod texture;
+pub mod thin_film;
+pub mod torus;
+pub mod transformed;
 pub mod util;
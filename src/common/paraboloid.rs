@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use crate::common::csg::Solid;
+use crate::common::hittable::{Hittable, HitRecord};
+use crate::common::material::Material;
+use crate::common::ray::*;
+use crate::common::util::{azimuth_u, radial_direction, solve_quadratic, Interval};
+use vec3::*;
+
+/// A paraboloid opening up the y axis from its vertex at `center`, reaching
+/// `radius` at `height` where it is closed by a disk.
+pub struct Paraboloid {
+    center: Point3,
+    radius: f64,
+    height: f64,
+    mat: Arc<dyn Material>,
+}
+
+impl Paraboloid {
+    pub fn new(c: Point3, r: f64, h: f64, mat: Arc<dyn Material>) -> Self {
+        Paraboloid {
+            center: c,
+            radius: r,
+            height: h,
+            mat,
+        }
+    }
+}
+
+impl Hittable for Paraboloid {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let oc = r.origin() - self.center;
+        let dir = r.direction();
+        let mut closest_so_far = ray_t.max;
+        // (t, outward normal, u, v, dp/dv) of the nearest accepted intersection
+        let mut nearest = None;
+
+        // Side: x^2 + z^2 = k y with 0 <= y <= height
+        let k = self.radius * self.radius / self.height;
+        let a = dir.x * dir.x + dir.z * dir.z;
+        let b = 2.0 * (oc.x * dir.x + oc.z * dir.z) - k * dir.y;
+        let c = oc.x * oc.x + oc.z * oc.z - k * oc.y;
+        if let Some((t0, t1)) = solve_quadratic(a, b, c) {
+            for root in [t0, t1] {
+                let p = oc + dir * root;
+                if Interval::new(ray_t.min, closest_so_far).surrounds(root)
+                    && (0.0..=self.height).contains(&p.y)
+                {
+                    let outward_normal = Vec3::new(2.0 * p.x, -k, 2.0 * p.z).unit_vector();
+                    let u = azimuth_u(p.x, p.z);
+                    let v = p.y / self.height;
+                    // rho = sqrt(k * v * height), so d(rho)/dv = k * height / (2 rho)
+                    let rho = (p.x * p.x + p.z * p.z).sqrt();
+                    let dpdv = if rho > 0.0 {
+                        radial_direction(&p) * (k * self.height / (2.0 * rho))
+                            + Vec3::new(0.0, self.height, 0.0)
+                    } else {
+                        Vec3::zero()
+                    };
+                    closest_so_far = root;
+                    nearest = Some((root, outward_normal, u, v, dpdv));
+                }
+            }
+        }
+
+        // Top cap
+        if dir.y != 0.0 {
+            let root = (self.height - oc.y) / dir.y;
+            let p = oc + dir * root;
+            let rho2 = p.x * p.x + p.z * p.z;
+            if Interval::new(ray_t.min, closest_so_far).surrounds(root)
+                && rho2 <= self.radius * self.radius
+            {
+                let u = azimuth_u(p.x, p.z);
+                let v = rho2.sqrt() / self.radius;
+                let dpdv = radial_direction(&p) * self.radius;
+                nearest = Some((root, Vec3::new(0.0, 1.0, 0.0), u, v, dpdv));
+            }
+        }
+
+        let Some((t, outward_normal, u, v, dpdv)) = nearest else {
+            return false;
+        };
+        rec.t = t;
+        rec.p = r.at(rec.t);
+        rec.u = u;
+        rec.v = v;
+        let local = rec.p - self.center;
+        rec.dpdu = Vec3::new(local.z, 0.0, -local.x) * (2.0 * std::f64::consts::PI);
+        rec.dpdv = dpdv;
+        // Treated as locally flat when propagating ray differentials
+        rec.dndu = Vec3::zero();
+        rec.dndv = Vec3::zero();
+        rec.set_face_normal(r, &outward_normal);
+        rec.mat = Some(self.mat.clone());
+
+        true
+    }
+}
+
+impl Solid for Paraboloid {}
+
+#[test]
+fn paraboloid_hits_side_and_cap() {
+    let mat = Arc::new(crate::common::material::Lambertian::new(Color::from_float(0.5)));
+    let paraboloid = Paraboloid::new(Point3::zero(), 1.0, 1.0, mat);
+    let mut rec = HitRecord::new();
+
+    // A quarter of the way up the radius is a half, where the wall slopes at
+    // 45 degrees
+    let r = Ray::new(Point3::new(0.0, 0.25, 5.0), Vec3::new(0.0, 0.0, -1.0));
+    assert!(paraboloid.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+    assert!((rec.t - 4.5).abs() < 1e-9);
+    assert!((rec.normal() - Vec3::new(0.0, -1.0, 1.0).unit_vector()).length() < 1e-9);
+    assert!((rec.u - 0.25).abs() < 1e-9 && (rec.v - 0.25).abs() < 1e-9);
+
+    // The cap over the open end
+    let r = Ray::new(Point3::new(0.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+    assert!(paraboloid.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+    assert!((rec.t - 4.0).abs() < 1e-9);
+    assert!((rec.normal() - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-9);
+    assert!((rec.u - 0.5).abs() < 1e-9 && (rec.v - 0.5).abs() < 1e-9);
+
+    // Wide of the wall at that height misses
+    let r = Ray::new(Point3::new(0.8, 0.25, 5.0), Vec3::new(0.0, 0.0, -1.0));
+    assert!(!paraboloid.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+}
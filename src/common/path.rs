@@ -0,0 +1,181 @@
+use core::f64;
+
+use crate::common::{
+    hittable::{HitRecord, Hittable},
+    integrator::{sample_analytic_light, sample_background, sample_light, Integrator},
+    material::ScatterRecord,
+    ray::Ray,
+    sampler::Sampler,
+    scene::Scene,
+    spectrum::{Radiance, SampledSpectrum, Wavelengths, SPECTRUM_SAMPLES},
+    util::{power_heuristic, Interval},
+};
+use vec3::*;
+
+/// Unidirectional path tracing with next-event estimation: at every diffuse
+/// hit a direction towards one of the scene's lights is sampled as well as
+/// one from the material, and the two are combined by multiple importance
+/// sampling.
+pub struct PathTracer {
+    pub max_depth: i64,
+    // Follow a handful of wavelengths along each path instead of RGB, so
+    // dispersive glass can split light into colours
+    pub spectral: bool,
+}
+
+impl PathTracer {
+    pub fn new(max_depth: i64) -> Self {
+        PathTracer {
+            max_depth,
+            spectral: false,
+        }
+    }
+
+    /// Radiance arriving along `r`. `bsdf_pdf` is the density with which the
+    /// previous hit's material chose `r`, or None for camera rays and specular
+    /// bounces, which light sampling could never have produced.
+    fn ray_color<S: Radiance>(&self, r: &Ray, depth: i64, scene: &Scene, bsdf_pdf: Option<f64>) -> S {
+        // If we've exceeded the ray bounce limit, no more light is gathered
+        if depth <= 0 {
+            return S::zero();
+        }
+
+        let mut rec = HitRecord::new();
+        // Start slightly off the surface to avoid re-hitting it through round off
+        if !scene.world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            let mut color = S::from_rgb(scene.background.value(r), r);
+            if let Some(bsdf_pdf) = bsdf_pdf
+                && let Some(background_pdf) = scene.background.pdf()
+            {
+                color *= power_heuristic(bsdf_pdf, background_pdf.value(&r.direction()));
+            }
+            return color;
+        }
+        rec.compute_differentials(r);
+
+        let Some(mat) = rec.mat.clone() else {
+            return S::zero();
+        };
+
+        // Emission found by following the material, weighted against the
+        // chance light sampling at the previous hit found it too
+        let mut color = S::from_rgb(mat.emitted(r, &rec), r);
+        if let Some(bsdf_pdf) = bsdf_pdf
+            && !scene.lights.objects.is_empty()
+        {
+            let light_pdf = scene.lights.pdf_value(&r.origin(), &r.direction(), r.time());
+            color *= power_heuristic(bsdf_pdf, light_pdf);
+        }
+
+        let mut srec = ScatterRecord::default();
+        if !mat.scatter(r, &rec, &mut srec) {
+            return color;
+        }
+
+        // A material that answered for the hero wavelength alone drops the
+        // others, and the hero stands in for them from here on
+        let hero_ray;
+        let (r, hero_weight) = match r.wavelengths() {
+            Some(w) if srec.hero_only && !w.secondary_terminated() => {
+                hero_ray = r.with_wavelengths(Some(w.terminate_secondary()));
+                (&hero_ray, SPECTRUM_SAMPLES as f64)
+            }
+            _ => (r, 1.0),
+        };
+
+        let Some(bsdf) = srec.pdf else {
+            // Specular bounces have just the one direction to follow
+            let specular_ray = srec.specular_ray.with_wavelengths(r.wavelengths().copied());
+            let attenuation = S::from_rgb(srec.attenuation, &specular_ray) * hero_weight;
+            return color + attenuation * self.ray_color::<S>(&specular_ray, depth - 1, scene, None);
+        };
+
+        let mut scattered_light = S::zero();
+        if !scene.lights.objects.is_empty() {
+            scattered_light += sample_light(r, &rec, mat.as_ref(), bsdf.as_ref(), scene);
+        }
+        if let Some(background_pdf) = scene.background.pdf() {
+            scattered_light += sample_background(r, &rec, mat.as_ref(), bsdf.as_ref(), background_pdf, scene);
+        }
+        for light in &scene.analytic_lights {
+            scattered_light += sample_analytic_light(r, &rec, mat.as_ref(), light.as_ref(), scene);
+        }
+
+        // Continue the path in a direction the material chose
+        let scattered = rec.spawn_ray(r, bsdf.generate());
+        let pdf = bsdf.value(&scattered.direction());
+        if pdf > 0.0 {
+            let f = S::from_rgb(mat.eval(r, &rec, &scattered), r);
+            scattered_light += f * self.ray_color::<S>(&scattered, depth - 1, scene, Some(pdf)) / pdf;
+        }
+        color + scattered_light * hero_weight
+    }
+}
+
+impl Integrator for PathTracer {
+    fn li(&self, r: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Color {
+        if !self.spectral {
+            return self.ray_color(r, self.max_depth, scene, None);
+        }
+        let wavelengths = Wavelengths::sample_visible(sampler.get_1d());
+        let r = r.with_wavelengths(Some(wavelengths));
+        let radiance: SampledSpectrum = self.ray_color(&r, self.max_depth, scene, None);
+        radiance.to_rgb(&wavelengths)
+    }
+}
+
+#[test]
+fn light_sampling_reduces_variance() {
+    use std::sync::Arc;
+
+    use crate::common::{
+        background::Solid,
+        camera::Camera,
+        hittable::HittableList,
+        material::{DiffuseLight, Lambertian},
+        quad::Quad,
+        sphere::Sphere,
+    };
+
+    // A white floor lit only by a small sphere one unit above it
+    let floor = Arc::new(Lambertian::new(Color::from_float(0.5)));
+    let light = Arc::new(DiffuseLight::new(Color::from_float(50.0)));
+    let light_center = Point3::new(0.0, 1.0, 0.0);
+    let mut world = HittableList::new();
+    world.objects.push(Box::new(Quad::new(
+        Point3::new(-10.0, 0.0, 10.0),
+        Vec3::new(20.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -20.0),
+        floor,
+    )));
+    world.objects.push(Box::new(Sphere::new(light_center, 0.05, light.clone())));
+    let mut scene = Scene::new(Camera::default(), world);
+    scene.background = Arc::new(Solid(Color::zero()));
+    let mut lights = HittableList::new();
+    lights.objects.push(Box::new(Sphere::new(light_center, 0.05, light)));
+
+    let tracer = PathTracer::new(5);
+    let r = Ray::new(Point3::new(0.0, 0.5, 0.0), Vec3::new(0.0, -1.0, 0.0));
+
+    // Mean and variance of the red channel over many estimates
+    let n = 20000;
+    let estimate = |scene: &Scene| {
+        let samples: Vec<f64> = (0..n)
+            .map(|_| tracer.ray_color::<Color>(&r, 5, scene, None).x)
+            .collect();
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        let variance = samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / n as f64;
+        (mean, variance)
+    };
+    let (brute_mean, brute_variance) = estimate(&scene);
+    scene.lights = lights;
+    let (nee_mean, nee_variance) = estimate(&scene);
+
+    // Radiance off the floor below a small sphere: albedo * Le * (r / d)^2
+    let expected = 0.5 * 50.0 * 0.05 * 0.05;
+    assert!((nee_mean - expected).abs() < 0.05 * expected, "{nee_mean} vs {expected}");
+    // Brute force only finds the light now and then, allow five standard errors
+    let brute_error = 5.0 * (brute_variance / n as f64).sqrt();
+    assert!((brute_mean - expected).abs() < brute_error, "{brute_mean} vs {expected}");
+    assert!(nee_variance * 100.0 < brute_variance, "{nee_variance} vs {brute_variance}");
+}
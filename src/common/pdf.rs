@@ -0,0 +1,187 @@
+use core::f64;
+use std::sync::Arc;
+
+use crate::common::{hittable::Hittable, phase::PhaseFunction, util::random_double};
+use vec3::*;
+
+/// Maps a point of the unit square to a direction about +z with density
+/// cos(theta) / pi.
+pub fn cosine_hemisphere(u1: f64, u2: f64) -> Vec3<f64> {
+    let phi = 2.0 * f64::consts::PI * u1;
+    let r = u2.sqrt();
+    Vec3::new(r * phi.cos(), r * phi.sin(), (1.0 - u2).max(0.0).sqrt())
+}
+
+/// Maps a point of the unit square to a direction about +z with density
+/// 1 / (2 pi).
+pub fn uniform_hemisphere(u1: f64, u2: f64) -> Vec3<f64> {
+    let phi = 2.0 * f64::consts::PI * u1;
+    let z = u2;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Maps a point of the unit square to a direction with density 1 / (4 pi).
+pub fn uniform_sphere(u1: f64, u2: f64) -> Vec3<f64> {
+    let phi = 2.0 * f64::consts::PI * u1;
+    let z = 1.0 - 2.0 * u2;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Maps a point of the unit square to a direction about +z within the cone
+/// of half-angle acos(`cos_theta_max`), uniformly over its solid angle.
+pub fn uniform_cone(u1: f64, u2: f64, cos_theta_max: f64) -> Vec3<f64> {
+    let cos_theta = 1.0 + u2 * (cos_theta_max - 1.0);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * f64::consts::PI * u1;
+    Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}
+
+/// A distribution over directions that can both be sampled and evaluated.
+pub trait Pdf {
+    /// Density, per steradian, of picking `direction`.
+    fn value(&self, direction: &Vec3<f64>) -> f64;
+
+    /// A direction drawn from the distribution, not necessarily unit length.
+    fn generate(&self) -> Vec3<f64>;
+}
+
+/// Every direction equally likely.
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: &Vec3<f64>) -> f64 {
+        1.0 / (4.0 * f64::consts::PI)
+    }
+
+    fn generate(&self) -> Vec3<f64> {
+        uniform_sphere(random_double(), random_double())
+    }
+}
+
+/// Directions about a normal weighted by the cosine to it, the ideal
+/// distribution for a Lambertian surface.
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(w: &Vec3<f64>) -> Self {
+        CosinePdf { uvw: Onb::new(w) }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: &Vec3<f64>) -> f64 {
+        let cosine = direction.unit_vector().dot(&self.uvw.w());
+        cosine.max(0.0) / f64::consts::PI
+    }
+
+    fn generate(&self) -> Vec3<f64> {
+        self.uvw
+            .transform(&cosine_hemisphere(random_double(), random_double()))
+    }
+}
+
+/// Directions from `origin` towards points on some objects, usually lights,
+/// as they are placed at `time`.
+pub struct HittablePdf<'a> {
+    objects: &'a dyn Hittable,
+    origin: Point3,
+    time: f64,
+}
+
+impl<'a> HittablePdf<'a> {
+    pub fn new(objects: &'a dyn Hittable, origin: Point3, time: f64) -> Self {
+        HittablePdf { objects, origin, time }
+    }
+}
+
+impl Pdf for HittablePdf<'_> {
+    fn value(&self, direction: &Vec3<f64>) -> f64 {
+        self.objects.pdf_value(&self.origin, direction, self.time)
+    }
+
+    fn generate(&self) -> Vec3<f64> {
+        self.objects.random(&self.origin, self.time)
+    }
+}
+
+/// Picks from either of two distributions with equal chance.
+pub struct MixturePdf<'a> {
+    p: [&'a dyn Pdf; 2],
+}
+
+impl<'a> MixturePdf<'a> {
+    pub fn new(p0: &'a dyn Pdf, p1: &'a dyn Pdf) -> Self {
+        MixturePdf { p: [p0, p1] }
+    }
+}
+
+impl Pdf for MixturePdf<'_> {
+    fn value(&self, direction: &Vec3<f64>) -> f64 {
+        0.5 * self.p[0].value(direction) + 0.5 * self.p[1].value(direction)
+    }
+
+    fn generate(&self) -> Vec3<f64> {
+        if random_double() < 0.5 {
+            self.p[0].generate()
+        } else {
+            self.p[1].generate()
+        }
+    }
+}
+
+/// The scattering directions of a phase function for light travelling along
+/// `incoming`.
+pub struct PhasePdf {
+    phase: Arc<dyn PhaseFunction>,
+    incoming: Vec3<f64>,
+}
+
+impl PhasePdf {
+    pub fn new(phase: Arc<dyn PhaseFunction>, incoming: &Vec3<f64>) -> Self {
+        PhasePdf {
+            phase,
+            incoming: incoming.unit_vector(),
+        }
+    }
+}
+
+impl Pdf for PhasePdf {
+    fn value(&self, direction: &Vec3<f64>) -> f64 {
+        self.phase.p(&self.incoming, &direction.unit_vector())
+    }
+
+    fn generate(&self) -> Vec3<f64> {
+        self.phase.sample(&self.incoming)
+    }
+}
+
+#[test]
+fn pdfs_match_their_samples() {
+    // Estimating E[cos] under the cosine density gives 2/3 exactly, and each
+    // density integrates to one over the sphere when estimated uniformly
+    let n = 100_000;
+    let cosine = CosinePdf::new(&Vec3::new(0.0, 1.0, 0.0));
+    let mean_cos = (0..n).map(|_| cosine.generate().y).sum::<f64>() / n as f64;
+    assert!((mean_cos - 2.0 / 3.0).abs() < 0.01, "{mean_cos}");
+
+    let sphere = SpherePdf;
+    let mixture = MixturePdf::new(&cosine, &sphere);
+    for pdf in [&cosine as &dyn Pdf, &sphere, &mixture] {
+        let integral = (0..n)
+            .map(|_| pdf.value(&sphere.generate()) / sphere.value(&Vec3::zero()))
+            .sum::<f64>()
+            / n as f64;
+        assert!((integral - 1.0).abs() < 0.02, "{integral}");
+    }
+
+    // The uniform hemisphere stays above the plane with mean height 1/2
+    let mean_z = (0..n)
+        .map(|_| uniform_hemisphere(random_double(), random_double()).z)
+        .sum::<f64>()
+        / n as f64;
+    assert!((mean_z - 0.5).abs() < 0.01, "{mean_z}");
+}
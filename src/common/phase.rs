@@ -0,0 +1,89 @@
+use core::f64;
+
+use crate::common::util::random_double;
+use vec3::*;
+
+/// Describes how light is redistributed when it scatters inside a medium.
+/// `incoming` is the direction the light was travelling and `outgoing` the
+/// direction it leaves in, both unit length.
+pub trait PhaseFunction: Send + Sync {
+    /// Probability density of scattering into `outgoing`, per steradian.
+    fn p(&self, incoming: &Vec3<f64>, outgoing: &Vec3<f64>) -> f64;
+
+    /// Samples an outgoing direction proportionally to `p`.
+    fn sample(&self, incoming: &Vec3<f64>) -> Vec3<f64>;
+}
+
+pub struct IsotropicPhase;
+
+impl PhaseFunction for IsotropicPhase {
+    fn p(&self, _incoming: &Vec3<f64>, _outgoing: &Vec3<f64>) -> f64 {
+        1.0 / (4.0 * f64::consts::PI)
+    }
+
+    fn sample(&self, _incoming: &Vec3<f64>) -> Vec3<f64> {
+        Vec3::random_unit_vector()
+    }
+}
+
+/// The Henyey-Greenstein lobe. `g` in (-1, 1) is the mean cosine of the
+/// scattering angle: positive values scatter forwards, negative backwards and
+/// zero is isotropic.
+pub struct HenyeyGreenstein {
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(g: f64) -> Self {
+        HenyeyGreenstein {
+            g: g.clamp(-0.999, 0.999),
+        }
+    }
+
+    fn p_cos(&self, cos_theta: f64) -> f64 {
+        let g = self.g;
+        let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+        (1.0 - g * g) / (4.0 * f64::consts::PI * denom * denom.sqrt())
+    }
+}
+
+impl PhaseFunction for HenyeyGreenstein {
+    fn p(&self, incoming: &Vec3<f64>, outgoing: &Vec3<f64>) -> f64 {
+        self.p_cos(incoming.dot(outgoing))
+    }
+
+    fn sample(&self, incoming: &Vec3<f64>) -> Vec3<f64> {
+        let g = self.g;
+        let xi = random_double();
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * xi
+        } else {
+            let sqr_term = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+            (1.0 + g * g - sqr_term * sqr_term) / (2.0 * g)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * f64::consts::PI * random_double();
+
+        let local = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        Onb::new(incoming).transform(&local)
+    }
+}
+
+#[test]
+fn henyey_greenstein_mean_cosine() {
+    // Sampled directions should have an average cosine of g and the density
+    // should integrate to one over the sphere
+    let incoming = Vec3::new(0.0, 0.0, 1.0);
+    for g in [-0.6, 0.0, 0.3, 0.8] {
+        let hg = HenyeyGreenstein::new(g);
+        let n = 100_000;
+        let mean_cos = (0..n).map(|_| hg.sample(&incoming).dot(&incoming)).sum::<f64>() / n as f64;
+        assert!((mean_cos - g).abs() < 0.01, "g = {g}, mean cosine = {mean_cos}");
+
+        let integral = (0..n)
+            .map(|_| hg.p(&incoming, &Vec3::random_unit_vector()) * 4.0 * f64::consts::PI)
+            .sum::<f64>()
+            / n as f64;
+        assert!((integral - 1.0).abs() < 0.05, "g = {g}, integral = {integral}");
+    }
+}
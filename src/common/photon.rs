@@ -0,0 +1,473 @@
+use core::f64;
+use std::sync::Arc;
+
+use crate::common::{
+    hittable::{HitRecord, Hittable},
+    integrator::{estimate_direct, Integrator},
+    material::{Material, ScatterRecord},
+    pdf::{CosinePdf, Pdf},
+    ray::Ray,
+    sampler::Sampler,
+    scene::Scene,
+    util::Interval,
+};
+use vec3::*;
+
+/// Photon mapping. Photons are shot from the scene's lights and left where
+/// they land on non-specular surfaces; camera rays follow specular bounces
+/// to the first other surface, which takes its light straight from the
+/// sources as the direct lighting integrator does and everything that
+/// bounced on the way there from the photons within `radius`.
+///
+/// Caustics arrive through glass and mirrors that light sampling cannot see
+/// through, so they are all photons and come out smooth, if blurred by the
+/// radius. Only area lights shoot photons: light from the background and
+/// analytic lights reaches surfaces directly but does not bounce.
+pub struct PhotonMapping {
+    pub max_depth: i64,
+    pub photons: usize,
+    pub radius: f64,
+}
+
+impl PhotonMapping {
+    pub fn new(max_depth: i64, photons: usize, radius: f64) -> Self {
+        PhotonMapping {
+            max_depth,
+            photons,
+            radius,
+        }
+    }
+
+    fn radiance(&self, r: &Ray, scene: &Scene, map: &PhotonMap) -> Color {
+        let (mut color, point) = visible_point(r, scene, self.max_depth);
+        if let Some(point) = point
+            && !map.is_empty()
+        {
+            let (flux, _) = point.gather(map, self.radius);
+            let area = f64::consts::PI * self.radius * self.radius;
+            color += point.beta * flux / (self.photons as f64 * area);
+        }
+        color
+    }
+}
+
+impl Integrator for PhotonMapping {
+    /// Leaves out light that bounced off other surfaces; the photons for it
+    /// are only shot by `render`.
+    fn li(&self, r: &Ray, scene: &Scene, _sampler: &mut dyn Sampler) -> Color {
+        self.radiance(r, scene, &PhotonMap::new(Vec::new()))
+    }
+
+    /// Shoots the photons once, then gathers from them for every pixel.
+    fn render(&self, scene: &Scene, sampler: &mut dyn Sampler) -> Vec<Color> {
+        eprint!("\r Shooting {} photons", self.photons);
+        let map = PhotonMap::new(trace_photons(scene, self.photons, self.max_depth, sampler));
+
+        let cam = &scene.camera;
+        let mut pixels = Vec::with_capacity((cam.image_width * cam.image_height()) as usize);
+        for j in 0..cam.image_height() {
+            eprint!("\r Scanlines remaining: {j}");
+            for i in 0..cam.image_width {
+                let mut pixel_color = Color::zero();
+                for _sample in 0..cam.samples_per_pixel {
+                    let r = cam.get_ray(i, j, sampler);
+                    pixel_color += self.radiance(&r, scene, &map);
+                }
+                pixels.push(pixel_color);
+            }
+        }
+        pixels
+    }
+}
+
+/// Stochastic progressive photon mapping. Each of the camera's samples per
+/// pixel is an iteration: a fresh camera ray finds a visible point in every
+/// pixel, a fresh batch of `photons_per_iteration` photons is shot, and the
+/// ones near each pixel's point are added to what it has gathered so far.
+/// Each pixel's radius then shrinks so that only the fraction `alpha` of the
+/// new photons counts as added, starting from `initial_radius`. The blur of
+/// the density estimates fades as the iterations go on, so the image
+/// converges to the right answer with bounded memory.
+pub struct ProgressivePhotonMapping {
+    pub max_depth: i64,
+    pub photons_per_iteration: usize,
+    pub initial_radius: f64,
+    pub alpha: f64,
+}
+
+impl ProgressivePhotonMapping {
+    pub fn new(max_depth: i64, photons_per_iteration: usize, initial_radius: f64) -> Self {
+        ProgressivePhotonMapping {
+            max_depth,
+            photons_per_iteration,
+            initial_radius,
+            alpha: 2.0 / 3.0,
+        }
+    }
+}
+
+// What a pixel has gathered over the iterations so far
+#[derive(Clone, Copy)]
+struct PixelStatistics {
+    radius: f64,
+    // Photons counted, after shrinking
+    count: f64,
+    // Photon flux times camera throughput, over the current radius
+    tau: Color,
+    // Light reaching the visible points by other means, summed
+    direct: Color,
+}
+
+impl Integrator for ProgressivePhotonMapping {
+    /// One iteration's worth for a single ray, with no photons.
+    fn li(&self, r: &Ray, scene: &Scene, _sampler: &mut dyn Sampler) -> Color {
+        visible_point(r, scene, self.max_depth).0
+    }
+
+    fn render(&self, scene: &Scene, sampler: &mut dyn Sampler) -> Vec<Color> {
+        let cam = &scene.camera;
+        let width = cam.image_width;
+        let statistics = PixelStatistics {
+            radius: self.initial_radius,
+            count: 0.0,
+            tau: Color::zero(),
+            direct: Color::zero(),
+        };
+        let mut pixels = vec![statistics; (width * cam.image_height()) as usize];
+
+        let iterations = cam.samples_per_pixel;
+        for iteration in 0..iterations {
+            eprint!("\r Iterations remaining: {}", iterations - iteration);
+            let mut points = Vec::with_capacity(pixels.len());
+            for j in 0..cam.image_height() {
+                for i in 0..width {
+                    let r = cam.get_ray(i, j, sampler);
+                    let (direct, point) = visible_point(&r, scene, self.max_depth);
+                    pixels[(j * width + i) as usize].direct += direct;
+                    points.push(point);
+                }
+            }
+
+            let photons = trace_photons(scene, self.photons_per_iteration, self.max_depth, sampler);
+            let map = PhotonMap::new(photons);
+            for (pixel, point) in pixels.iter_mut().zip(&points) {
+                let Some(point) = point else {
+                    continue;
+                };
+                let (flux, found) = point.gather(&map, pixel.radius);
+                if found == 0 {
+                    continue;
+                }
+                let found = found as f64;
+                let count = pixel.count + self.alpha * found;
+                let radius = pixel.radius * (count / (pixel.count + found)).sqrt();
+                let shrink = (radius * radius) / (pixel.radius * pixel.radius);
+                pixel.tau = (pixel.tau + point.beta * flux) * shrink;
+                pixel.count = count;
+                pixel.radius = radius;
+            }
+        }
+
+        // The photon estimate is an average over every photon shot, so it
+        // is scaled up to a sum over samples like the rest
+        let photons = (self.photons_per_iteration as i64 * iterations) as f64;
+        pixels
+            .iter()
+            .map(|pixel| {
+                let area = f64::consts::PI * pixel.radius * pixel.radius;
+                pixel.direct + pixel.tau * (iterations as f64 / (photons * area))
+            })
+            .collect()
+    }
+}
+
+// Where a camera path first meets a surface that is not a perfect mirror or
+// glass, and what it carries back from there
+struct VisiblePoint {
+    r: Ray,
+    rec: HitRecord,
+    mat: Arc<dyn Material>,
+    beta: Color,
+}
+
+impl VisiblePoint {
+    // The photon flux within `radius`, as reflected along the camera ray,
+    // and how many photons there were
+    fn gather(&self, map: &PhotonMap, radius: f64) -> (Color, usize) {
+        let mut flux = Color::zero();
+        let mut found = 0;
+        map.for_each_within(&self.rec.p, radius, |photon| {
+            // Photons on the far side of a thin wall or around a corner
+            // belong to other surfaces
+            if photon.normal.dot(&self.rec.geometric_normal()) < 0.9 {
+                return;
+            }
+            found += 1;
+            let incoming = -photon.direction;
+            let cosine = incoming.dot(&self.rec.normal()).abs();
+            if cosine <= 0.0 {
+                return;
+            }
+            let scattered = self.rec.spawn_ray(&self.r, incoming);
+            flux += self.mat.eval(&self.r, &self.rec, &scattered) * photon.power / cosine;
+        });
+        (flux, found)
+    }
+}
+
+// Follows `r` through specular bounces to a visible point, returning the
+// light gathered on the way and at the point other than from photons
+fn visible_point(r: &Ray, scene: &Scene, max_depth: i64) -> (Color, Option<VisiblePoint>) {
+    let mut r = *r;
+    let mut beta = Color::one();
+    let mut color = Color::zero();
+    for _ in 0..max_depth {
+        let mut rec = HitRecord::new();
+        if !scene.world.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            color += beta * scene.background.value(&r);
+            break;
+        }
+        rec.compute_differentials(&r);
+        let Some(mat) = rec.mat.clone() else {
+            break;
+        };
+
+        color += beta * mat.emitted(&r, &rec);
+        let mut srec = ScatterRecord::default();
+        if !mat.scatter(&r, &rec, &mut srec) {
+            break;
+        }
+        let Some(bsdf) = srec.pdf else {
+            beta *= srec.attenuation;
+            r = srec.specular_ray;
+            continue;
+        };
+
+        color += beta * estimate_direct(&r, &rec, mat.as_ref(), bsdf.as_ref(), scene);
+        return (color, Some(VisiblePoint { r, rec, mat, beta }));
+    }
+    (color, None)
+}
+
+/// Light arriving at a surface, left there by a photon.
+#[derive(Clone, Copy, Debug)]
+pub struct Photon {
+    pub p: Point3,
+    // Geometric normal on the side the photon arrived from
+    pub normal: Vec3<f64>,
+    // Unit direction of travel
+    pub direction: Vec3<f64>,
+    // Flux carried, as a single sample of all the light leaving the lights
+    pub power: Color,
+}
+
+/// Shoots `count` photons from the scene's lights and returns those that
+/// land on a non-specular surface after at least one bounce. Light arriving
+/// directly is left to light sampling. Each photon leaves at its own moment
+/// while the camera's shutter is open, so moving objects blur the map as
+/// they do the camera's view.
+pub fn trace_photons(scene: &Scene, count: usize, max_depth: i64, sampler: &mut dyn Sampler) -> Vec<Photon> {
+    let mut photons = Vec::new();
+    for _ in 0..count {
+        let time = scene.camera.sample_time(sampler);
+        let Some((rec, pdf_pos)) = scene.lights.sample_surface(time) else {
+            continue;
+        };
+        let Some(mat) = rec.mat.clone() else {
+            continue;
+        };
+        let cosine_pdf = CosinePdf::new(&rec.normal());
+        let direction = cosine_pdf.generate();
+        let pdf_dir = cosine_pdf.value(&direction);
+        if pdf_dir <= 0.0 {
+            continue;
+        }
+        let emitted = mat.emitted(&Ray::new_with_time(rec.p + direction, -direction, time), &rec);
+        let cosine = rec.normal().dot(&direction.unit_vector());
+        let power = emitted * (cosine / (pdf_pos * pdf_dir));
+        let r = rec.spawn_ray(&Ray::new_with_time(rec.p, direction, time), direction);
+        trace_photon(scene, r, power, max_depth, &mut photons);
+    }
+    photons
+}
+
+fn trace_photon(scene: &Scene, mut r: Ray, mut power: Color, max_depth: i64, photons: &mut Vec<Photon>) {
+    for bounce in 0..max_depth {
+        if power == Color::zero() {
+            return;
+        }
+        let mut rec = HitRecord::new();
+        if !scene.world.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return;
+        }
+        let Some(mat) = rec.mat.clone() else {
+            return;
+        };
+        let mut srec = ScatterRecord::default();
+        if !mat.scatter(&r, &rec, &mut srec) {
+            return;
+        }
+        let Some(bsdf) = srec.pdf else {
+            power *= srec.attenuation;
+            r = srec.specular_ray;
+            continue;
+        };
+
+        if bounce > 0 {
+            photons.push(Photon {
+                p: rec.p,
+                normal: rec.geometric_normal(),
+                direction: r.direction().unit_vector(),
+                power,
+            });
+        }
+        let scattered = rec.spawn_ray(&r, bsdf.generate());
+        let pdf = bsdf.value(&scattered.direction());
+        if pdf <= 0.0 {
+            return;
+        }
+        power = power * mat.eval(&r, &rec, &scattered) / pdf;
+        r = scattered;
+    }
+}
+
+/// Photons arranged as a kd-tree for finding those near a point. Each range
+/// of the list is split at its middle photon, along the axis the range is
+/// widest in, with the photons below it on that axis before it and those
+/// above after.
+pub struct PhotonMap {
+    photons: Vec<Photon>,
+    axes: Vec<usize>,
+}
+
+impl PhotonMap {
+    pub fn new(mut photons: Vec<Photon>) -> Self {
+        let mut axes = vec![0; photons.len()];
+        build(&mut photons, &mut axes);
+        PhotonMap { photons, axes }
+    }
+
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+
+    /// Calls `f` with every photon no further than `radius` from `p`.
+    pub fn for_each_within(&self, p: &Point3, radius: f64, mut f: impl FnMut(&Photon)) {
+        within(&self.photons, &self.axes, p, radius, &mut f);
+    }
+}
+
+fn build(photons: &mut [Photon], axes: &mut [usize]) {
+    if photons.len() <= 1 {
+        return;
+    }
+    let mut min = photons[0].p;
+    let mut max = photons[0].p;
+    for photon in photons.iter() {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(photon.p[axis]);
+            max[axis] = max[axis].max(photon.p[axis]);
+        }
+    }
+    let extent = max - min;
+    let axis = (0..3).max_by(|&a, &b| extent[a].total_cmp(&extent[b])).unwrap_or(0);
+
+    let mid = photons.len() / 2;
+    photons.select_nth_unstable_by(mid, |a, b| a.p[axis].total_cmp(&b.p[axis]));
+    axes[mid] = axis;
+    let (below, above) = photons.split_at_mut(mid);
+    let (below_axes, above_axes) = axes.split_at_mut(mid);
+    build(below, below_axes);
+    build(&mut above[1..], &mut above_axes[1..]);
+}
+
+fn within(photons: &[Photon], axes: &[usize], p: &Point3, radius: f64, f: &mut impl FnMut(&Photon)) {
+    if photons.is_empty() {
+        return;
+    }
+    let mid = photons.len() / 2;
+    let photon = &photons[mid];
+    if (photon.p - *p).length_squared() <= radius * radius {
+        f(photon);
+    }
+
+    // Only look on a side of the split the sphere reaches into
+    let axis = axes[mid];
+    let offset = p[axis] - photon.p[axis];
+    if offset <= radius {
+        within(&photons[..mid], &axes[..mid], p, radius, f);
+    }
+    if offset >= -radius {
+        within(&photons[mid + 1..], &axes[mid + 1..], p, radius, f);
+    }
+}
+
+#[test]
+fn photon_map_finds_neighbours() {
+    use crate::common::util::random_double;
+
+    let photons: Vec<Photon> = (0..2000)
+        .map(|_| Photon {
+            p: Point3::new(random_double(), random_double(), random_double() * 0.1),
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            direction: Vec3::new(0.0, 0.0, -1.0),
+            power: Color::one(),
+        })
+        .collect();
+    let map = PhotonMap::new(photons.clone());
+    assert_eq!(map.len(), photons.len());
+
+    for _ in 0..20 {
+        let p = Point3::new(random_double(), random_double(), 0.05);
+        let radius = random_double() * 0.2;
+        let mut found = Vec::new();
+        map.for_each_within(&p, radius, |photon| found.push(photon.p));
+        let mut expected: Vec<Point3> = photons
+            .iter()
+            .map(|photon| photon.p)
+            .filter(|q| (*q - p).length_squared() <= radius * radius)
+            .collect();
+        let key = |q: &Point3| (q.x, q.y, q.z);
+        found.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+        expected.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+        assert_eq!(found, expected);
+    }
+}
+
+#[test]
+fn photon_mapping_agrees_with_bidirectional() {
+    use crate::common::{
+        bdpt::{wall_mean, Bidirectional},
+        integrator::DirectLighting,
+        specular::Dielectric,
+    };
+
+    // A glass ball focusing a small light onto a wall, both out of frame so
+    // that the camera sees only the wall
+    let mean = |integrator: &dyn Integrator, samples_per_pixel: i64| {
+        let glass = Arc::new(Dielectric::new(1.5));
+        wall_mean(Point3::new(0.0, 2.0, -0.5), Point3::new(0.0, 1.5, -1.1), glass, integrator, samples_per_pixel)
+    };
+
+    let bidirectional = mean(&Bidirectional::new(6), 256);
+    let photon_mapping = mean(&PhotonMapping::new(6, 100_000, 0.2), 16);
+    assert!(
+        (photon_mapping - bidirectional).abs() < 0.1 * bidirectional,
+        "{photon_mapping} vs {bidirectional}"
+    );
+    let progressive = mean(&ProgressivePhotonMapping::new(6, 2000, 0.2), 64);
+    assert!(
+        (progressive - bidirectional).abs() < 0.1 * bidirectional,
+        "{progressive} vs {bidirectional}"
+    );
+
+    // Light sampling is blocked by the ball, so the caustic, about a quarter
+    // of the light on the wall, comes from the photons alone
+    let direct = mean(&DirectLighting { max_depth: 6 }, 64);
+    assert!(photon_mapping > 1.2 * direct, "{photon_mapping} vs {direct}");
+    assert!(progressive > 1.2 * direct, "{progressive} vs {direct}");
+}
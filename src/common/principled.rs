@@ -0,0 +1,294 @@
+use core::f64;
+use std::sync::Arc;
+
+use crate::common::{
+    hittable::HitRecord,
+    material::{Material, ScatterRecord},
+    microfacet::{dielectric_bsdf, local_frame, DielectricPdf, ReflectionPdf, TrowbridgeReitz},
+    pdf::{CosinePdf, Pdf},
+    ray::Ray,
+    texture::{SolidColor, Texture},
+    util::random_double,
+};
+use vec3::*;
+
+/// A texture of one value everywhere, for the principled material's scalar
+/// parameters.
+pub fn constant(value: f64) -> Arc<dyn Texture> {
+    Arc::new(SolidColor::new(Color::from_float(value)))
+}
+
+/// One material for most surfaces, after Burley's 2015 Disney BSDF: a
+/// retro-reflective diffuse base with sheen, a GGX specular layer, a rough
+/// glass transmission lobe and a clear coat, blended by the parameters below.
+/// Every parameter is a texture, and the scalar ones read its red channel.
+pub struct Principled {
+    pub base_color: Arc<dyn Texture>,
+    /// 0 for a dielectric, 1 for a metal tinted by the base color.
+    pub metallic: Arc<dyn Texture>,
+    /// Perceptual roughness of the specular and transmission lobes.
+    pub roughness: Arc<dyn Texture>,
+    /// Dielectric reflectance at normal incidence, 0.5 giving 4%.
+    pub specular: Arc<dyn Texture>,
+    /// Extra grazing reflection for cloth, tinted towards the base color by
+    /// `sheen_tint`.
+    pub sheen: Arc<dyn Texture>,
+    pub sheen_tint: Arc<dyn Texture>,
+    /// Strength of a second, colorless specular layer on top.
+    pub clearcoat: Arc<dyn Texture>,
+    /// 1 for a glossy clear coat, 0 for a satin one.
+    pub clearcoat_gloss: Arc<dyn Texture>,
+    /// How much of the dielectric base is clear glass rather than diffuse.
+    pub transmission: Arc<dyn Texture>,
+    /// Refractive index for transmission.
+    pub ior: f64,
+    /// Light given off from the front of the surface.
+    pub emission: Arc<dyn Texture>,
+}
+
+impl Default for Principled {
+    fn default() -> Self {
+        Principled {
+            base_color: Arc::new(SolidColor::new(Color::from_float(0.8))),
+            metallic: constant(0.0),
+            roughness: constant(0.5),
+            specular: constant(0.5),
+            sheen: constant(0.0),
+            sheen_tint: constant(0.5),
+            clearcoat: constant(0.0),
+            clearcoat_gloss: constant(1.0),
+            transmission: constant(0.0),
+            ior: 1.5,
+            emission: Arc::new(SolidColor::new(Color::zero())),
+        }
+    }
+}
+
+// The parameters looked up at one hit, with the derived lobe weights
+struct Lobes {
+    base_color: Color,
+    roughness: f64,
+    specular: Color,
+    sheen: Color,
+    clearcoat: f64,
+    diffuse_weight: f64,
+    transmission_weight: f64,
+    distribution: TrowbridgeReitz,
+    clearcoat_distribution: TrowbridgeReitz,
+    etap: f64,
+}
+
+// Alpha small enough to look smooth while keeping the lobes evaluable
+const MIN_ALPHA: f64 = 2e-3;
+
+fn schlick(f0: Color, cosine: f64) -> Color {
+    let m = (1.0 - cosine.clamp(0.0, 1.0)).powi(5);
+    f0 + (Color::one() - f0) * m
+}
+
+fn luminance(c: Color) -> f64 {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+impl Principled {
+    fn lobes(&self, rec: &HitRecord) -> Lobes {
+        let scalar = |t: &Arc<dyn Texture>| t.sample(rec).x.clamp(0.0, 1.0);
+        let base_color = self.base_color.sample(rec);
+        let metallic = scalar(&self.metallic);
+        let roughness = scalar(&self.roughness);
+        let transmission = scalar(&self.transmission);
+
+        let dielectric_f0 = Color::from_float(0.08 * scalar(&self.specular));
+        let tint = if luminance(base_color) > 0.0 {
+            base_color / luminance(base_color)
+        } else {
+            Color::one()
+        };
+        let sheen_tint = scalar(&self.sheen_tint);
+        let sheen = (Color::one() * (1.0 - sheen_tint) + tint * sheen_tint) * scalar(&self.sheen);
+        let gloss = scalar(&self.clearcoat_gloss);
+
+        Lobes {
+            base_color,
+            roughness,
+            specular: dielectric_f0 * (1.0 - metallic) + base_color * metallic,
+            sheen,
+            clearcoat: scalar(&self.clearcoat),
+            diffuse_weight: (1.0 - metallic) * (1.0 - transmission),
+            transmission_weight: (1.0 - metallic) * transmission,
+            distribution: TrowbridgeReitz::new((roughness * roughness).max(MIN_ALPHA)),
+            clearcoat_distribution: TrowbridgeReitz::new((0.1 * (1.0 - gloss) + 0.001 * gloss).max(MIN_ALPHA)),
+            etap: if rec.front_face() { self.ior } else { 1.0 / self.ior },
+        }
+    }
+}
+
+impl Lobes {
+    // The BSDF times |cos_i| for local directions
+    fn eval(&self, wo: &Vec3<f64>, wi: &Vec3<f64>) -> Color {
+        if wo.z <= 0.0 {
+            return Color::zero();
+        }
+        let mut f = Color::zero();
+        if wi.z > 0.0 {
+            let wm = (*wo + *wi).unit_vector();
+            let cos_d = wi.dot(&wm);
+
+            if self.diffuse_weight > 0.0 {
+                // Burley's diffuse, brightening at grazing angles on rough
+                // surfaces, plus sheen. Light the specular layer reflects
+                // on the way in or out never reaches the base.
+                let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+                let fl = (1.0 - wi.z).powi(5);
+                let fv = (1.0 - wo.z).powi(5);
+                let diffuse = self.base_color / f64::consts::PI
+                    * ((1.0 + (fd90 - 1.0) * fl) * (1.0 + (fd90 - 1.0) * fv));
+                let sheen = self.sheen * (1.0 - cos_d).powi(5);
+                let layer = (1.0 - luminance(schlick(self.specular, wi.z)))
+                    * (1.0 - luminance(schlick(self.specular, wo.z)));
+                f += (diffuse * layer + sheen) * (self.diffuse_weight * wi.z);
+            }
+
+            // D F G / (4 cos_o cos_i), times cos_i
+            let d = self.distribution.d(&wm);
+            let g = self.distribution.g(wo, wi);
+            let specular = schlick(self.specular, wo.dot(&wm)) * (d * g / (4.0 * wo.z));
+            f += specular * (1.0 - self.transmission_weight);
+
+            if self.clearcoat > 0.0 {
+                let d = self.clearcoat_distribution.d(&wm);
+                let g = self.clearcoat_distribution.g(wo, wi);
+                let fc = schlick(Color::from_float(0.04), wo.dot(&wm));
+                f += fc * (0.25 * self.clearcoat * d * g / (4.0 * wo.z));
+            }
+        }
+
+        if self.transmission_weight > 0.0 {
+            let t = dielectric_bsdf(&self.distribution, wo, wi, self.etap);
+            // Light passing through picks up the base color
+            let tint = if wi.z < 0.0 { self.base_color } else { Color::one() };
+            f += tint * (t * self.transmission_weight);
+        }
+        f
+    }
+
+    // Chances of sampling each lobe, roughly following how much each reflects
+    fn sampling_weights(&self, wo: &Vec3<f64>) -> [f64; 4] {
+        let diffuse = self.diffuse_weight * (luminance(self.base_color) + luminance(self.sheen)).max(0.05);
+        let specular = (1.0 - self.transmission_weight) * luminance(schlick(self.specular, wo.z)).max(0.05);
+        let transmission = self.transmission_weight;
+        let clearcoat = 0.25 * self.clearcoat * luminance(schlick(Color::from_float(0.04), wo.z));
+        let total = diffuse + specular + transmission + clearcoat;
+        [diffuse / total, specular / total, transmission / total, clearcoat / total]
+    }
+}
+
+// Picks one lobe's distribution at random by weight
+struct LobePdf {
+    lobes: Vec<(f64, Box<dyn Pdf>)>,
+}
+
+impl Pdf for LobePdf {
+    fn value(&self, direction: &Vec3<f64>) -> f64 {
+        self.lobes.iter().map(|(w, pdf)| w * pdf.value(direction)).sum()
+    }
+
+    fn generate(&self) -> Vec3<f64> {
+        let mut u = random_double();
+        for (w, pdf) in &self.lobes {
+            if u < *w {
+                return pdf.generate();
+            }
+            u -= w;
+        }
+        self.lobes[self.lobes.len() - 1].1.generate()
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        let (frame, wo) = local_frame(r_in, rec);
+        if wo.z <= 0.0 {
+            return false;
+        }
+        let lobes = self.lobes(rec);
+        let [diffuse, specular, transmission, clearcoat] = lobes.sampling_weights(&wo);
+
+        let mut pdfs: Vec<(f64, Box<dyn Pdf>)> = Vec::new();
+        if diffuse > 0.0 {
+            pdfs.push((diffuse, Box::new(CosinePdf::new(&rec.normal()))));
+        }
+        if specular > 0.0 {
+            pdfs.push((specular, Box::new(ReflectionPdf::new(frame, wo, lobes.distribution))));
+        }
+        if transmission > 0.0 {
+            let pdf = DielectricPdf::new(frame, wo, lobes.etap, lobes.distribution);
+            pdfs.push((transmission, Box::new(pdf)));
+        }
+        if clearcoat > 0.0 {
+            let pdf = ReflectionPdf::new(frame, wo, lobes.clearcoat_distribution);
+            pdfs.push((clearcoat, Box::new(pdf)));
+        }
+        srec.attenuation = lobes.base_color;
+        srec.pdf = Some(Box::new(LobePdf { lobes: pdfs }));
+        true
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        let (frame, wo) = local_frame(r_in, rec);
+        let wi = frame.to_local(&scattered.direction().unit_vector());
+        self.lobes(rec).eval(&wo, &wi)
+    }
+
+    fn emitted(&self, _r_in: &Ray, rec: &HitRecord) -> Color {
+        if !rec.front_face() {
+            return Color::zero();
+        }
+        self.emission.sample(rec)
+    }
+}
+
+#[test]
+fn principled_lobes_conserve_energy() {
+    use crate::common::microfacet::furnace_albedo;
+
+    let albedo = |mat: &Principled, cos_o: f64| furnace_albedo(mat, cos_o, mat.ior).y;
+    let white = || Arc::new(SolidColor::new(Color::one())) as Arc<dyn Texture>;
+
+    // A white plastic reflects about everything, give or take Burley's
+    // diffuse gaining a little on rough surfaces
+    let plastic = Principled {
+        base_color: white(),
+        ..Default::default()
+    };
+    // A white polished metal and clear glass lose only what single
+    // scattering microfacets do
+    let metal = Principled {
+        base_color: white(),
+        metallic: constant(1.0),
+        roughness: constant(0.2),
+        ..Default::default()
+    };
+    let glass = Principled {
+        base_color: white(),
+        transmission: constant(1.0),
+        roughness: constant(0.2),
+        ..Default::default()
+    };
+    // A clear coat over black only adds a little reflection
+    let coated = Principled {
+        base_color: Arc::new(SolidColor::new(Color::zero())),
+        clearcoat: constant(1.0),
+        ..Default::default()
+    };
+    for cos_o in [1.0, 0.6, 0.3] {
+        let a = albedo(&plastic, cos_o);
+        assert!(a > 0.8 && a < 1.05, "plastic at {cos_o}: {a}");
+        let a = albedo(&metal, cos_o);
+        assert!(a > 0.95 && a < 1.01, "metal at {cos_o}: {a}");
+        let a = albedo(&glass, cos_o);
+        assert!(a > 0.95 && a < 1.02, "glass at {cos_o}: {a}");
+        let a = albedo(&coated, cos_o);
+        assert!(a > 0.0 && a < 0.2, "clear coat at {cos_o}: {a}");
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use crate::common::hittable::{Hittable, HitRecord};
+use crate::common::material::Material;
+use crate::common::ray::*;
+use crate::common::util::{random_double, Interval};
+use vec3::*;
+
+/// A parallelogram with one corner at `q` and sides `u` and `v`. Its front
+/// faces along u x v.
+pub struct Quad {
+    q: Point3,
+    u: Vec3<f64>,
+    v: Vec3<f64>,
+    // Caches the plane, n . p = d, and the vector used to find the planar
+    // coordinates of a point
+    normal: Vec3<f64>,
+    d: f64,
+    w: Vec3<f64>,
+    area: f64,
+    mat: Arc<dyn Material>,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3<f64>, v: Vec3<f64>, mat: Arc<dyn Material>) -> Self {
+        let n = Vec3::cross_product(&u, &v);
+        let normal = n.unit_vector();
+        Quad {
+            q,
+            u,
+            v,
+            normal,
+            d: normal.dot(&q),
+            w: n / n.dot(&n),
+            area: n.length(),
+            mat,
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        // No hit if the ray is parallel to the plane
+        let denom = self.normal.dot(&r.direction());
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        let t = (self.d - self.normal.dot(&r.origin())) / denom;
+        if !ray_t.surrounds(t) {
+            return false;
+        }
+
+        // Express the hit point in the quad's own coordinates
+        let p = r.at(t);
+        let planar = p - self.q;
+        let alpha = self.w.dot(&Vec3::cross_product(&planar, &self.v));
+        let beta = self.w.dot(&Vec3::cross_product(&self.u, &planar));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = p;
+        rec.u = alpha;
+        rec.v = beta;
+        rec.set_face_normal(r, &self.normal);
+        rec.dpdu = self.u;
+        rec.dpdv = self.v;
+        rec.dndu = Vec3::zero();
+        rec.dndv = Vec3::zero();
+        rec.mat = Some(self.mat.clone());
+        true
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3<f64>, time: f64) -> f64 {
+        let mut rec = HitRecord::new();
+        let r = Ray::new_with_time(*origin, *direction, time);
+        if !self.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
+
+        // Convert the uniform density over the area to one over solid angle
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = (direction.dot(&self.normal) / direction.length()).abs();
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: &Point3, _time: f64) -> Vec3<f64> {
+        let p = self.q + self.u * random_double() + self.v * random_double();
+        p - *origin
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(HitRecord, f64)> {
+        // Look back at a uniformly chosen point from in front of it
+        let p = self.q + self.u * random_double() + self.v * random_double();
+        let mut rec = HitRecord::new();
+        let r = Ray::new_with_time(p + self.normal, -self.normal, time);
+        if !self.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec) {
+            return None;
+        }
+        Some((rec, 1.0 / self.area))
+    }
+
+    fn surface_pdf(&self, origin: &Point3, direction: &Vec3<f64>, time: f64) -> f64 {
+        let mut rec = HitRecord::new();
+        let r = Ray::new_with_time(*origin, *direction, time);
+        if !self.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
+        1.0 / self.area
+    }
+}
+
+#[test]
+fn quad_hit_coordinates() {
+    let mat = Arc::new(crate::common::material::Lambertian::new(Color::from_float(0.5)));
+    let quad = Quad::new(
+        Point3::new(-1.0, -1.0, -2.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(0.0, 4.0, 0.0),
+        mat,
+    );
+    let mut rec = HitRecord::new();
+    let r = Ray::new(Point3::zero(), Vec3::new(0.5, 0.0, -2.0));
+    assert!(quad.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+    assert!((rec.t - 1.0).abs() < 1e-12);
+    assert!((rec.u - 0.75).abs() < 1e-12 && (rec.v - 0.25).abs() < 1e-12);
+    assert!(rec.front_face());
+
+    // Just past the far edge
+    let r = Ray::new(Point3::zero(), Vec3::new(1.01, 0.0, -1.0));
+    assert!(!quad.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+}
@@ -1,14 +1,51 @@
+use crate::common::spectrum::Wavelengths;
 use vec3::*;
 
+/// Two extra rays offset by one pixel in x and in y, used to estimate how much
+/// of a surface a sample covers.
+#[derive(Clone, Copy, Default)]
+pub struct RayDifferential {
+    pub rx_origin: Point3,
+    pub rx_direction: Direction,
+    pub ry_origin: Point3,
+    pub ry_direction: Direction,
+}
+
+#[derive(Clone, Copy, Default)]
 pub struct Ray {
     orig: Point3,
     dir: Direction,
+    tm: f64,
+    differentials: Option<RayDifferential>,
+    // Set when rendering spectrally
+    wavelengths: Option<Wavelengths>,
 }
 
 impl Ray {
     pub fn new(p: Point3, d: Direction) -> Ray {
-        Ray { orig: p, dir: d }
+        Ray::new_with_time(p, d, 0.0)
+    }
+
+    pub fn new_with_time(p: Point3, d: Direction, time: f64) -> Ray {
+        Ray {
+            orig: p,
+            dir: d,
+            tm: time,
+            differentials: None,
+            wavelengths: None,
+        }
+    }
+
+    pub fn with_differentials(mut self, differentials: Option<RayDifferential>) -> Ray {
+        self.differentials = differentials;
+        self
+    }
+
+    pub fn with_wavelengths(mut self, wavelengths: Option<Wavelengths>) -> Ray {
+        self.wavelengths = wavelengths;
+        self
     }
+
     pub fn origin(&self) -> Point3 {
         self.orig
     }
@@ -17,6 +54,18 @@ impl Ray {
         self.dir
     }
 
+    pub fn time(&self) -> f64 {
+        self.tm
+    }
+
+    pub fn differentials(&self) -> Option<&RayDifferential> {
+        self.differentials.as_ref()
+    }
+
+    pub fn wavelengths(&self) -> Option<&Wavelengths> {
+        self.wavelengths.as_ref()
+    }
+
     pub fn at(&self, t: f64) -> Vec3<f64> {
         self.orig + self.dir * t
     }
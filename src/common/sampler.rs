@@ -0,0 +1,22 @@
+use crate::common::util::random_double;
+
+/// Where an integrator and the camera get the random numbers they decide
+/// things with. Materials and lights still draw their own.
+pub trait Sampler {
+    /// A number in [0, 1).
+    fn get_1d(&mut self) -> f64;
+
+    /// A point in the unit square.
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+/// Uniform numbers with no relation to each other.
+pub struct IndependentSampler;
+
+impl Sampler for IndependentSampler {
+    fn get_1d(&mut self) -> f64 {
+        random_double()
+    }
+}
@@ -0,0 +1,638 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::{
+    background::{Background, Gradient, Solid},
+    bdpt::Bidirectional,
+    camera::Camera,
+    cuboid::Cuboid,
+    envmap::EnvironmentMap,
+    hittable::{Hittable, HittableList},
+    image::Image,
+    integrator::{AmbientOcclusion, Depth, DirectLighting, Integrator, Normals},
+    light::Light,
+    material::{DiffuseLight, Lambertian, Material},
+    microfacet::{RoughConductor, RoughDielectric, TrowbridgeReitz},
+    path::PathTracer,
+    photon::{PhotonMapping, ProgressivePhotonMapping},
+    principled::Principled,
+    quad::Quad,
+    sampler::IndependentSampler,
+    sky::PreethamSky,
+    spectrum::Ior,
+    specular::{Dielectric, Metal},
+    sphere::Sphere,
+    texture::{CheckerTexture, ImageTexture, SolidColor, Texture, WrapMode},
+};
+use vec3::*;
+
+fn vec3(v: [f64; 3]) -> Vec3<f64> {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+/// A texture in a scene file: a bare number for a grey, an [r, g, b] array,
+/// an image file relative to the scene, or a checkerboard of two textures.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TextureDesc {
+    Value(f64),
+    Color([f64; 3]),
+    Image { image: String },
+    Checker { scale: f64, even: Box<TextureDesc>, odd: Box<TextureDesc> },
+}
+
+impl TextureDesc {
+    fn build(&self, dir: &Path) -> io::Result<Arc<dyn Texture>> {
+        Ok(match self {
+            TextureDesc::Value(v) => Arc::new(SolidColor::new(Color::from_float(*v))),
+            TextureDesc::Color(c) => Arc::new(SolidColor::new(vec3(*c))),
+            TextureDesc::Image { image } => {
+                let image = Image::load(dir.join(image))?;
+                Arc::new(ImageTexture::new(Arc::new(image), WrapMode::Repeat))
+            }
+            TextureDesc::Checker { scale, even, odd } => {
+                Arc::new(CheckerTexture::new(*scale, even.build(dir)?, odd.build(dir)?))
+            }
+        })
+    }
+
+    fn is_black(&self) -> bool {
+        match self {
+            TextureDesc::Value(v) => *v == 0.0,
+            TextureDesc::Color(c) => c.iter().all(|&x| x == 0.0),
+            _ => false,
+        }
+    }
+}
+
+/// The principled material's parameters, each defaulting as in `Principled`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrincipledDesc {
+    pub base_color: TextureDesc,
+    pub metallic: TextureDesc,
+    pub roughness: TextureDesc,
+    pub specular: TextureDesc,
+    pub sheen: TextureDesc,
+    pub sheen_tint: TextureDesc,
+    pub clearcoat: TextureDesc,
+    pub clearcoat_gloss: TextureDesc,
+    pub transmission: TextureDesc,
+    pub ior: f64,
+    pub emission: TextureDesc,
+}
+
+impl Default for PrincipledDesc {
+    fn default() -> Self {
+        PrincipledDesc {
+            base_color: TextureDesc::Value(0.8),
+            metallic: TextureDesc::Value(0.0),
+            roughness: TextureDesc::Value(0.5),
+            specular: TextureDesc::Value(0.5),
+            sheen: TextureDesc::Value(0.0),
+            sheen_tint: TextureDesc::Value(0.5),
+            clearcoat: TextureDesc::Value(0.0),
+            clearcoat_gloss: TextureDesc::Value(1.0),
+            transmission: TextureDesc::Value(0.0),
+            ior: 1.5,
+            emission: TextureDesc::Value(0.0),
+        }
+    }
+}
+
+impl PrincipledDesc {
+    fn build(&self, dir: &Path) -> io::Result<Principled> {
+        Ok(Principled {
+            base_color: self.base_color.build(dir)?,
+            metallic: self.metallic.build(dir)?,
+            roughness: self.roughness.build(dir)?,
+            specular: self.specular.build(dir)?,
+            sheen: self.sheen.build(dir)?,
+            sheen_tint: self.sheen_tint.build(dir)?,
+            clearcoat: self.clearcoat.build(dir)?,
+            clearcoat_gloss: self.clearcoat_gloss.build(dir)?,
+            transmission: self.transmission.build(dir)?,
+            ior: self.ior,
+            emission: self.emission.build(dir)?,
+        })
+    }
+}
+
+/// A refractive index: a bare number, a named glass ("bk7", "sf11" or
+/// "diamond"), or Cauchy or Sellmeier coefficients for wavelengths in
+/// micrometres.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum IorDesc {
+    Value(f64),
+    Named(String),
+    Cauchy { a: f64, b: f64 },
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl IorDesc {
+    fn build(&self) -> io::Result<Ior> {
+        Ok(match self {
+            IorDesc::Value(n) => Ior::Constant(*n),
+            IorDesc::Named(name) => match name.as_str() {
+                "bk7" => Ior::BK7,
+                "sf11" => Ior::SF11,
+                "diamond" => Ior::DIAMOND,
+                _ => {
+                    let msg = format!("unknown glass {name}");
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+                }
+            },
+            IorDesc::Cauchy { a, b } => Ior::Cauchy { a: *a, b: *b },
+            IorDesc::Sellmeier { b, c } => Ior::Sellmeier { b: *b, c: *c },
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaterialDesc {
+    Lambertian { albedo: TextureDesc },
+    Metal { albedo: [f64; 3], fuzz: f64 },
+    Dielectric { ior: IorDesc },
+    DiffuseLight { emit: TextureDesc },
+    RoughConductor { eta: [f64; 3], k: [f64; 3], roughness: f64 },
+    RoughDielectric { ior: f64, roughness: f64 },
+    Principled(Box<PrincipledDesc>),
+}
+
+impl MaterialDesc {
+    fn build(&self, dir: &Path) -> io::Result<Arc<dyn Material>> {
+        Ok(match self {
+            MaterialDesc::Lambertian { albedo } => Arc::new(Lambertian::from_texture(albedo.build(dir)?)),
+            MaterialDesc::Metal { albedo, fuzz } => Arc::new(Metal::new(vec3(*albedo), *fuzz)),
+            MaterialDesc::Dielectric { ior } => Arc::new(Dielectric::dispersive(ior.build()?)),
+            MaterialDesc::DiffuseLight { emit } => Arc::new(DiffuseLight::from_texture(emit.build(dir)?)),
+            MaterialDesc::RoughConductor { eta, k, roughness } => Arc::new(RoughConductor::new(
+                vec3(*eta),
+                vec3(*k),
+                TrowbridgeReitz::from_roughness(*roughness),
+            )),
+            MaterialDesc::RoughDielectric { ior, roughness } => Arc::new(RoughDielectric::new(
+                *ior,
+                TrowbridgeReitz::from_roughness(*roughness),
+            )),
+            MaterialDesc::Principled(desc) => Arc::new(desc.build(dir)?),
+        })
+    }
+
+    // Whether objects made of it should be sampled as lights
+    fn is_emissive(&self) -> bool {
+        match self {
+            MaterialDesc::DiffuseLight { emit } => !emit.is_black(),
+            MaterialDesc::Principled(desc) => !desc.emission.is_black(),
+            _ => false,
+        }
+    }
+}
+
+/// A shape, naming its material from the scene's material table.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ObjectDesc {
+    Sphere { center: [f64; 3], radius: f64, material: String },
+    Quad { q: [f64; 3], u: [f64; 3], v: [f64; 3], material: String },
+    Cuboid { min: [f64; 3], max: [f64; 3], material: String },
+}
+
+impl ObjectDesc {
+    fn material(&self) -> &str {
+        match self {
+            ObjectDesc::Sphere { material, .. }
+            | ObjectDesc::Quad { material, .. }
+            | ObjectDesc::Cuboid { material, .. } => material,
+        }
+    }
+
+    fn build(&self, mat: Arc<dyn Material>) -> Box<dyn Hittable> {
+        match self {
+            ObjectDesc::Sphere { center, radius, .. } => Box::new(Sphere::new(vec3(*center), *radius, mat)),
+            ObjectDesc::Quad { q, u, v, .. } => Box::new(Quad::new(vec3(*q), vec3(*u), vec3(*v), mat)),
+            ObjectDesc::Cuboid { min, max, .. } => Box::new(Cuboid::new(vec3(*min), vec3(*max), mat)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackgroundDesc {
+    Gradient { bottom: [f64; 3], top: [f64; 3] },
+    Solid { color: [f64; 3] },
+    Sky { sun: [f64; 3], turbidity: f64, ground_albedo: [f64; 3] },
+    Environment { image: String, rotation: f64, intensity: f64 },
+}
+
+impl BackgroundDesc {
+    fn build(&self, dir: &Path) -> io::Result<Arc<dyn Background>> {
+        Ok(match self {
+            BackgroundDesc::Gradient { bottom, top } => Arc::new(Gradient {
+                bottom: vec3(*bottom),
+                top: vec3(*top),
+            }),
+            BackgroundDesc::Solid { color } => Arc::new(Solid(vec3(*color))),
+            BackgroundDesc::Sky {
+                sun,
+                turbidity,
+                ground_albedo,
+            } => Arc::new(PreethamSky::new(vec3(*sun), *turbidity, vec3(*ground_albedo))),
+            BackgroundDesc::Environment {
+                image,
+                rotation,
+                intensity,
+            } => {
+                let image = Image::load(dir.join(image))?;
+                Arc::new(EnvironmentMap::new(Arc::new(image), *rotation, *intensity))
+            }
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraDesc {
+    pub aspect_ratio: f64,
+    pub image_width: i64,
+    pub samples_per_pixel: i64,
+}
+
+impl Default for CameraDesc {
+    fn default() -> Self {
+        CameraDesc {
+            aspect_ratio: 16.0 / 9.0,
+            image_width: 400,
+            samples_per_pixel: 100,
+        }
+    }
+}
+
+fn default_max_depth() -> i64 {
+    50
+}
+
+fn default_distance() -> f64 {
+    1.0
+}
+
+fn default_samples() -> usize {
+    16
+}
+
+fn default_photons() -> usize {
+    100_000
+}
+
+fn default_alpha() -> f64 {
+    2.0 / 3.0
+}
+
+/// Which integrator renders the scene. Unset parameters take their defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IntegratorDesc {
+    Normals,
+    Depth {
+        max_distance: f64,
+    },
+    AmbientOcclusion {
+        #[serde(default = "default_distance")]
+        distance: f64,
+        #[serde(default = "default_samples")]
+        samples: usize,
+    },
+    Direct {
+        #[serde(default = "default_max_depth")]
+        max_depth: i64,
+    },
+    Path {
+        #[serde(default = "default_max_depth")]
+        max_depth: i64,
+        #[serde(default)]
+        spectral: bool,
+    },
+    Bidirectional {
+        #[serde(default = "default_max_depth")]
+        max_depth: i64,
+    },
+    PhotonMapping {
+        #[serde(default = "default_max_depth")]
+        max_depth: i64,
+        #[serde(default = "default_photons")]
+        photons: usize,
+        radius: f64,
+    },
+    ProgressivePhotonMapping {
+        #[serde(default = "default_max_depth")]
+        max_depth: i64,
+        #[serde(default = "default_photons")]
+        photons_per_iteration: usize,
+        initial_radius: f64,
+        #[serde(default = "default_alpha")]
+        alpha: f64,
+    },
+}
+
+impl Default for IntegratorDesc {
+    fn default() -> Self {
+        IntegratorDesc::Path {
+            max_depth: default_max_depth(),
+            spectral: false,
+        }
+    }
+}
+
+impl IntegratorDesc {
+    /// Depth ranges, photon counts and radii must be positive and progressive
+    /// photon mapping's `alpha` must lie strictly between 0 and 1, or the
+    /// image comes out black or NaN.
+    pub fn build(&self) -> io::Result<Box<dyn Integrator>> {
+        let invalid = |msg: &str| Err(io::Error::new(io::ErrorKind::InvalidData, msg.to_string()));
+        let positive = |x: f64| x.is_finite() && x > 0.0;
+        Ok(match *self {
+            IntegratorDesc::Normals => Box::new(Normals),
+            IntegratorDesc::Depth { max_distance } => {
+                if !positive(max_distance) {
+                    return invalid("depth needs a positive max_distance");
+                }
+                Box::new(Depth { max_distance })
+            }
+            IntegratorDesc::AmbientOcclusion { distance, samples } => Box::new(AmbientOcclusion { distance, samples }),
+            IntegratorDesc::Direct { max_depth } => Box::new(DirectLighting { max_depth }),
+            IntegratorDesc::Path { max_depth, spectral } => Box::new(PathTracer { max_depth, spectral }),
+            IntegratorDesc::Bidirectional { max_depth } => Box::new(Bidirectional { max_depth }),
+            IntegratorDesc::PhotonMapping {
+                max_depth,
+                photons,
+                radius,
+            } => {
+                if photons == 0 || !positive(radius) {
+                    return invalid("photon mapping needs photons and a positive radius");
+                }
+                Box::new(PhotonMapping {
+                    max_depth,
+                    photons,
+                    radius,
+                })
+            }
+            IntegratorDesc::ProgressivePhotonMapping {
+                max_depth,
+                photons_per_iteration,
+                initial_radius,
+                alpha,
+            } => {
+                if photons_per_iteration == 0 || !positive(initial_radius) || !(alpha > 0.0 && alpha < 1.0) {
+                    return invalid("progressive photon mapping needs photons, a positive radius and 0 < alpha < 1");
+                }
+                Box::new(ProgressivePhotonMapping {
+                    max_depth,
+                    photons_per_iteration,
+                    initial_radius,
+                    alpha,
+                })
+            }
+        })
+    }
+}
+
+/// Everything needed to render a picture, as stored in a JSON scene file.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneDesc {
+    pub camera: CameraDesc,
+    pub integrator: IntegratorDesc,
+    pub background: Option<BackgroundDesc>,
+    pub materials: BTreeMap<String, MaterialDesc>,
+    pub objects: Vec<ObjectDesc>,
+}
+
+/// Everything an integrator looks at. Emissive objects are in `lights` as
+/// well as `world`, so integrators can aim rays at them.
+pub struct Scene {
+    pub camera: Camera,
+    pub world: HittableList,
+    pub lights: HittableList,
+    // Seen by rays that escape the scene
+    pub background: Arc<dyn Background>,
+    // Point, spot and directional lights, summed at every non-specular hit
+    pub analytic_lights: Vec<Arc<dyn Light>>,
+    // Encode the output as sRGB rather than writing linear values
+    pub srgb: bool,
+}
+
+impl Scene {
+    /// A scene with no lights to sample under the default sky.
+    pub fn new(camera: Camera, world: HittableList) -> Self {
+        Scene {
+            camera,
+            world,
+            lights: HittableList::new(),
+            background: Arc::new(Gradient::default()),
+            analytic_lights: Vec::new(),
+            srgb: false,
+        }
+    }
+
+    /// Renders with `integrator` and writes the image to stdout as a PPM.
+    pub fn render(&mut self, integrator: &dyn Integrator) {
+        let pixels = self.render_pixels(integrator);
+
+        println!("P3\n{0} {1}\n255", self.camera.image_width, self.camera.image_height());
+        for pixel_color in pixels {
+            if self.srgb {
+                pixel_color.write_color_srgb(self.camera.samples_per_pixel);
+            } else {
+                pixel_color.write_color_aa(self.camera.samples_per_pixel);
+            }
+        }
+
+        eprintln!("\rDone rendering!");
+    }
+
+    /// The sum of every sample taken for each pixel, row by row from the top
+    /// left.
+    pub fn render_pixels(&mut self, integrator: &dyn Integrator) -> Vec<Color> {
+        self.camera.initialize();
+        integrator.render(self, &mut IndependentSampler)
+    }
+}
+
+impl SceneDesc {
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        serde_json::from_str(json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("scene descriptions always serialize")
+    }
+
+    /// Reads and builds a scene file and the integrator it asks for. Image
+    /// paths in it are relative to the file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<(Scene, Box<dyn Integrator>)> {
+        let path = path.as_ref();
+        let desc = Self::from_json(&fs::read_to_string(path)?)?;
+        let scene = desc.build(path.parent().unwrap_or(Path::new(".")))?;
+        Ok((scene, desc.integrator.build()?))
+    }
+
+    /// Fails on unknown materials, unreadable images and cameras that would
+    /// render no pixels or no samples.
+    pub fn build(&self, dir: &Path) -> io::Result<Scene> {
+        if !(self.camera.aspect_ratio.is_finite() && self.camera.aspect_ratio > 0.0)
+            || self.camera.image_width < 1
+            || self.camera.samples_per_pixel < 1
+        {
+            let msg = "camera needs a positive aspect ratio, image width and sample count";
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        }
+
+        let mut materials = BTreeMap::new();
+        for (name, desc) in &self.materials {
+            materials.insert(name.as_str(), (desc.build(dir)?, desc.is_emissive()));
+        }
+
+        let mut world = HittableList::new();
+        let mut lights = HittableList::new();
+        for object in &self.objects {
+            let Some((mat, emissive)) = materials.get(object.material()) else {
+                let msg = format!("unknown material {}", object.material());
+                return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+            };
+            world.objects.push(object.build(mat.clone()));
+            if *emissive {
+                lights.objects.push(object.build(mat.clone()));
+            }
+        }
+
+        let camera = Camera::new(
+            self.camera.aspect_ratio,
+            self.camera.image_width,
+            self.camera.samples_per_pixel,
+        );
+        let mut scene = Scene::new(camera, world);
+        scene.lights = lights;
+        scene.srgb = true;
+        if let Some(background) = &self.background {
+            scene.background = background.build(dir)?;
+        }
+        Ok(scene)
+    }
+}
+
+#[test]
+fn scenes_round_trip_through_json() {
+    let json = r#"{
+        "camera": { "image_width": 64, "samples_per_pixel": 4 },
+        "integrator": { "type": "bidirectional" },
+        "background": { "type": "solid", "color": [0, 0, 0] },
+        "materials": {
+            "lamp": { "type": "diffuse_light", "emit": 4 },
+            "prism": { "type": "dielectric", "ior": "sf11" },
+            "paint": {
+                "type": "principled",
+                "base_color": [0.8, 0.1, 0.1],
+                "clearcoat": 1,
+                "roughness": { "scale": 0.5, "even": 0.2, "odd": 0.6 }
+            }
+        },
+        "objects": [
+            { "type": "sphere", "center": [0, 0, -2], "radius": 0.5, "material": "paint" },
+            { "type": "quad", "q": [-1, 2, -3], "u": [2, 0, 0], "v": [0, 0, 2], "material": "lamp" }
+        ]
+    }"#;
+    let desc = SceneDesc::from_json(json).unwrap();
+
+    // Unset fields take their defaults
+    assert_eq!(desc.camera.aspect_ratio, 16.0 / 9.0);
+    assert_eq!(desc.integrator, IntegratorDesc::Bidirectional { max_depth: 50 });
+    let MaterialDesc::Principled(paint) = &desc.materials["paint"] else {
+        panic!("paint is not principled");
+    };
+    assert_eq!(paint.base_color, TextureDesc::Color([0.8, 0.1, 0.1]));
+    assert_eq!(paint.ior, 1.5);
+    assert!(matches!(paint.roughness, TextureDesc::Checker { .. }));
+    let MaterialDesc::Dielectric { ior } = &desc.materials["prism"] else {
+        panic!("prism is not a dielectric");
+    };
+    assert_eq!(ior.build().unwrap(), Ior::SF11);
+
+    // Writing it back out and reading it again changes nothing
+    assert_eq!(SceneDesc::from_json(&desc.to_json()).unwrap(), desc);
+
+    // Only the lamp is sampled as a light, and missing materials are errors
+    let scene = desc.build(Path::new(".")).unwrap();
+    assert_eq!(scene.world.objects.len(), 2);
+    assert_eq!(scene.lights.objects.len(), 1);
+    let mut broken = desc.clone();
+    broken.objects.push(ObjectDesc::Sphere {
+        center: [0.0; 3],
+        radius: 1.0,
+        material: "chrome".to_string(),
+    });
+    assert!(broken.build(Path::new(".")).is_err());
+}
+
+#[test]
+fn scenes_reject_empty_cameras_and_depth_ranges() {
+    let camera = |aspect_ratio, image_width, samples_per_pixel| SceneDesc {
+        camera: CameraDesc {
+            aspect_ratio,
+            image_width,
+            samples_per_pixel,
+        },
+        ..SceneDesc::default()
+    };
+    assert!(camera(1.5, 64, 4).build(Path::new(".")).is_ok());
+    for desc in [
+        camera(0.0, 64, 4),
+        camera(-1.5, 64, 4),
+        camera(f64::INFINITY, 64, 4),
+        camera(f64::NAN, 64, 4),
+        camera(1.5, 0, 4),
+        camera(1.5, 64, 0),
+        camera(1.5, 64, -1),
+    ] {
+        let error = desc.build(Path::new(".")).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    assert!(IntegratorDesc::Depth { max_distance: 5.0 }.build().is_ok());
+    for max_distance in [0.0, -5.0, f64::NAN] {
+        let error = IntegratorDesc::Depth { max_distance }.build().err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
+
+#[test]
+fn photon_integrators_reject_bad_parameters() {
+    let photon_mapping = |photons, radius| IntegratorDesc::PhotonMapping {
+        max_depth: 8,
+        photons,
+        radius,
+    };
+    assert!(photon_mapping(1000, 0.1).build().is_ok());
+    assert!(photon_mapping(0, 0.1).build().is_err());
+    assert!(photon_mapping(1000, 0.0).build().is_err());
+    assert!(photon_mapping(1000, f64::NAN).build().is_err());
+
+    let progressive = |photons_per_iteration, initial_radius, alpha| IntegratorDesc::ProgressivePhotonMapping {
+        max_depth: 8,
+        photons_per_iteration,
+        initial_radius,
+        alpha,
+    };
+    assert!(progressive(1000, 0.1, 0.7).build().is_ok());
+    assert!(progressive(0, 0.1, 0.7).build().is_err());
+    assert!(progressive(1000, -0.1, 0.7).build().is_err());
+    for alpha in [0.0, 1.0, f64::NAN] {
+        assert!(progressive(1000, 0.1, alpha).build().is_err());
+    }
+}
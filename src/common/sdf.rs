@@ -0,0 +1,269 @@
+use core::f64;
+use std::sync::Arc;
+
+use crate::common::hittable::{Hittable, HitRecord};
+use crate::common::material::Material;
+use crate::common::ray::*;
+use crate::common::util::{azimuth_u, Interval};
+use vec3::*;
+
+/// A signed distance function: negative inside the surface, positive outside,
+/// and never larger in magnitude than the true distance to the surface.
+pub trait Sdf: Send + Sync {
+    fn distance(&self, p: &Point3) -> f64;
+}
+
+impl<F: Fn(&Point3) -> f64 + Send + Sync> Sdf for F {
+    fn distance(&self, p: &Point3) -> f64 {
+        self(p)
+    }
+}
+
+/// Renders any signed distance function by sphere tracing: step along the ray
+/// by the distance to the nearest surface until close enough to call it a hit.
+pub struct SdfHittable {
+    sdf: Arc<dyn Sdf>,
+    mat: Arc<dyn Material>,
+    // Give up after this many steps or this far along the ray
+    pub max_steps: usize,
+    pub max_distance: f64,
+    // Distance below which the surface counts as reached
+    pub epsilon: f64,
+    // Fraction of the distance to step, lowered for functions such as twists
+    // that can overestimate the true distance
+    pub step_scale: f64,
+}
+
+impl SdfHittable {
+    pub fn new(sdf: Arc<dyn Sdf>, mat: Arc<dyn Material>) -> Self {
+        SdfHittable {
+            sdf,
+            mat,
+            max_steps: 256,
+            max_distance: 100.0,
+            epsilon: 1e-4,
+            step_scale: 1.0,
+        }
+    }
+
+    fn normal_at(&self, p: &Point3) -> Vec3<f64> {
+        // Central differences of the distance field
+        let h = self.epsilon;
+        let dx = Vec3::new(h, 0.0, 0.0);
+        let dy = Vec3::new(0.0, h, 0.0);
+        let dz = Vec3::new(0.0, 0.0, h);
+        Vec3::new(
+            self.sdf.distance(&(*p + dx)) - self.sdf.distance(&(*p - dx)),
+            self.sdf.distance(&(*p + dy)) - self.sdf.distance(&(*p - dy)),
+            self.sdf.distance(&(*p + dz)) - self.sdf.distance(&(*p - dz)),
+        )
+        .unit_vector()
+    }
+}
+
+impl Hittable for SdfHittable {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let length = r.direction().length();
+        let t_max = ray_t.max.min(self.max_distance / length);
+        let mut t = ray_t.min.max(0.0);
+
+        // Rays that start inside march towards the surface from the other side
+        let sign = if self.sdf.distance(&r.at(t)) < 0.0 { -1.0 } else { 1.0 };
+
+        let mut steps = 0;
+        loop {
+            if t > t_max || steps >= self.max_steps {
+                return false;
+            }
+            let distance = sign * self.sdf.distance(&r.at(t));
+            if distance < self.epsilon && t > ray_t.min {
+                break;
+            }
+            // Step at least epsilon, so a march starting within epsilon of a
+            // surface moves past ray_t.min before it can converge
+            t += (self.step_scale * distance).max(self.epsilon) / length;
+            steps += 1;
+        }
+
+        if !ray_t.surrounds(t) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = r.at(t);
+        let outward_normal = self.normal_at(&rec.p);
+        rec.set_face_normal(r, &outward_normal);
+        rec.u = azimuth_u(outward_normal.x, outward_normal.z);
+        rec.v = (-outward_normal.y).acos() / f64::consts::PI;
+        // No parameterization to differentiate; tangent_frame picks a frame
+        rec.dpdu = Vec3::zero();
+        rec.dpdv = Vec3::zero();
+        rec.dndu = Vec3::zero();
+        rec.dndv = Vec3::zero();
+        rec.mat = Some(self.mat.clone());
+
+        true
+    }
+}
+
+pub struct SdfSphere {
+    pub center: Point3,
+    pub radius: f64,
+}
+
+impl Sdf for SdfSphere {
+    fn distance(&self, p: &Point3) -> f64 {
+        (*p - self.center).length() - self.radius
+    }
+}
+
+/// An axis-aligned box given by its center and half the length of each side.
+pub struct SdfBox {
+    pub center: Point3,
+    pub half_extents: Vec3<f64>,
+}
+
+impl Sdf for SdfBox {
+    fn distance(&self, p: &Point3) -> f64 {
+        let q = *p - self.center;
+        let q = Vec3::new(q.x.abs(), q.y.abs(), q.z.abs()) - self.half_extents;
+        let outside = Vec3::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).length();
+        let inside = q.x.max(q.y).max(q.z).min(0.0);
+        outside + inside
+    }
+}
+
+/// A torus in the xz-plane.
+pub struct SdfTorus {
+    pub center: Point3,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+}
+
+impl Sdf for SdfTorus {
+    fn distance(&self, p: &Point3) -> f64 {
+        let q = *p - self.center;
+        let ring = (q.x * q.x + q.z * q.z).sqrt() - self.major_radius;
+        (ring * ring + q.y * q.y).sqrt() - self.minor_radius
+    }
+}
+
+/// The union of two shapes with the seam rounded over a distance of about `k`.
+pub struct SmoothUnion {
+    pub a: Arc<dyn Sdf>,
+    pub b: Arc<dyn Sdf>,
+    pub k: f64,
+}
+
+impl Sdf for SmoothUnion {
+    fn distance(&self, p: &Point3) -> f64 {
+        let d1 = self.a.distance(p);
+        let d2 = self.b.distance(p);
+        if self.k <= 0.0 {
+            return d1.min(d2);
+        }
+        // Polynomial smooth minimum
+        let h = (0.5 + 0.5 * (d2 - d1) / self.k).clamp(0.0, 1.0);
+        d2 * (1.0 - h) + d1 * h - self.k * h * (1.0 - h)
+    }
+}
+
+/// Tiles space with copies of a shape centered on multiples of `period`.
+/// An axis with a period of zero is not repeated.
+pub struct Repeat {
+    pub inner: Arc<dyn Sdf>,
+    pub period: Vec3<f64>,
+}
+
+impl Sdf for Repeat {
+    fn distance(&self, p: &Point3) -> f64 {
+        let mut q = *p;
+        for axis in 0..3 {
+            let c = self.period[axis];
+            if c > 0.0 {
+                q[axis] -= c * (q[axis] / c).round();
+            }
+        }
+        self.inner.distance(&q)
+    }
+}
+
+/// Twists a shape around the y axis by `rate` radians per unit of height.
+/// This stretches distances, so trace it with a `step_scale` below one.
+pub struct Twist {
+    pub inner: Arc<dyn Sdf>,
+    pub rate: f64,
+}
+
+impl Sdf for Twist {
+    fn distance(&self, p: &Point3) -> f64 {
+        let (sin, cos) = (self.rate * p.y).sin_cos();
+        let q = Point3::new(cos * p.x - sin * p.z, p.y, sin * p.x + cos * p.z);
+        self.inner.distance(&q)
+    }
+}
+
+/// The Mandelbulb fractal centered at the origin, using the standard
+/// distance estimator from the running derivative of the iteration.
+pub struct Mandelbulb {
+    pub power: f64,
+    pub iterations: usize,
+}
+
+impl Sdf for Mandelbulb {
+    fn distance(&self, p: &Point3) -> f64 {
+        let mut z = *p;
+        let mut dr = 1.0;
+        let mut r = 0.0;
+        for _ in 0..self.iterations {
+            r = z.length();
+            if r > 2.0 {
+                break;
+            }
+
+            let theta = (z.z / r).acos() * self.power;
+            let phi = z.y.atan2(z.x) * self.power;
+            dr = r.powf(self.power - 1.0) * self.power * dr + 1.0;
+
+            let zr = r.powf(self.power);
+            z = Point3::new(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            ) * zr
+                + *p;
+        }
+        0.5 * r.ln() * r / dr
+    }
+}
+
+#[test]
+fn traced_sphere_matches_analytic() {
+    use crate::common::material::Lambertian;
+    use crate::common::sphere::Sphere;
+
+    let mat = Arc::new(Lambertian::new(Color::from_float(0.5)));
+    let center = Point3::new(0.0, 0.0, -2.0);
+    let traced = SdfHittable::new(Arc::new(SdfSphere { center, radius: 0.5 }), mat.clone());
+    let exact = Sphere::new(center, 0.5, mat);
+
+    let r = Ray::new(Point3::zero(), Vec3::new(0.2, 0.1, -2.0));
+    let mut traced_rec = HitRecord::new();
+    let mut exact_rec = HitRecord::new();
+    assert!(traced.hit(&r, Interval::new(0.001, f64::INFINITY), &mut traced_rec));
+    assert!(exact.hit(&r, Interval::new(0.001, f64::INFINITY), &mut exact_rec));
+    assert!((traced_rec.t - exact_rec.t).abs() < 1e-3);
+    assert!((traced_rec.normal() - exact_rec.normal()).length() < 1e-3);
+
+    // From inside, the far wall is found and seen from behind
+    let r = Ray::new(center, Vec3::new(0.0, 0.0, 1.0));
+    assert!(traced.hit(&r, Interval::new(0.001, f64::INFINITY), &mut traced_rec));
+    assert!((traced_rec.t - 0.5).abs() < 1e-3);
+    assert!(!traced_rec.front_face());
+
+    // Starting closer to the surface than epsilon still finds it
+    let r = Ray::new(Point3::new(0.0, 0.0, -1.5 + 5e-5), Vec3::new(0.0, 0.0, -1.0));
+    assert!(traced.hit(&r, Interval::new(0.0, f64::INFINITY), &mut traced_rec));
+    assert!(traced_rec.t < 1e-3);
+    assert!(traced_rec.front_face());
+}
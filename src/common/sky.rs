@@ -0,0 +1,283 @@
+use core::f64;
+use std::sync::Arc;
+
+use crate::common::{
+    background::Background,
+    envmap::EnvironmentMap,
+    image::Image,
+    pdf::{uniform_cone, Pdf},
+    ray::Ray,
+    util::random_double,
+};
+use vec3::*;
+
+// Takes the model's kilocandelas per square metre to scene units, putting a
+// clear midday zenith at around a third
+const LUMINANCE_SCALE: f64 = 0.05;
+// Luminance of the sun's disk before the atmosphere, in kcd/m^2
+const SUN_LUMINANCE: f64 = 2.0e6;
+// Angular radius of the sun's disk, in degrees
+const SUN_RADIUS: f64 = 0.267;
+// Resolution of the table the sky is sampled from
+const TABLE_WIDTH: usize = 128;
+const TABLE_HEIGHT: usize = 64;
+
+/// Preetham, Shirley and Smits' analytic daylight sky for a sun in direction
+/// `sun`, with `turbidity` from about 2 for a clear day to 10 for haze. Below
+/// the horizon is a diffuse ground of `ground_albedo` lit by the sky and sun.
+/// The sun's disk is part of the background, and both it and the sky are
+/// importance sampled so they light the scene through shadow rays.
+pub struct PreethamSky {
+    perez: Perez,
+    sun: Vec3<f64>,
+    sun_radiance: Color,
+    cos_sun_radius: f64,
+    ground: Color,
+    // The sky without the sun tabulated, for sampling directions
+    table: EnvironmentMap,
+    // Chance of aiming at the sun's disk rather than the rest of the sky
+    sun_probability: f64,
+}
+
+impl PreethamSky {
+    pub fn new(sun: Vec3<f64>, turbidity: f64, ground_albedo: Color) -> Self {
+        let sun = sun.unit_vector();
+        let theta_sun = sun.y.clamp(-1.0, 1.0).acos();
+        let perez = Perez::new(turbidity, theta_sun.min(f64::consts::FRAC_PI_2 - 1e-3));
+        let cos_sun_radius = SUN_RADIUS.to_radians().cos();
+        let sun_solid_angle = 2.0 * f64::consts::PI * (1.0 - cos_sun_radius);
+        let sun_radiance = if sun.y > 0.0 {
+            sun_transmittance(turbidity, theta_sun) * (SUN_LUMINANCE * LUMINANCE_SCALE)
+        } else {
+            Color::zero()
+        };
+
+        // Tabulate the upper hemisphere, summing the light it sheds on the
+        // ground at the same time
+        let mut pixels = Vec::with_capacity(TABLE_WIDTH * TABLE_HEIGHT);
+        let mut sky_irradiance = Color::zero();
+        let texel_solid_angle = |theta: f64| {
+            2.0 * f64::consts::PI * f64::consts::PI * theta.sin()
+                / (TABLE_WIDTH * TABLE_HEIGHT) as f64
+        };
+        for y in 0..TABLE_HEIGHT {
+            let theta = f64::consts::PI * (y as f64 + 0.5) / TABLE_HEIGHT as f64;
+            for x in 0..TABLE_WIDTH {
+                let phi = 2.0 * f64::consts::PI * ((x as f64 + 0.5) / TABLE_WIDTH as f64 - 0.5);
+                let direction =
+                    Vec3::new(theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos());
+                let radiance = if direction.y >= 0.0 {
+                    perez.radiance(&direction, &sun)
+                } else {
+                    Color::zero()
+                };
+                sky_irradiance += radiance * (direction.y.max(0.0) * texel_solid_angle(theta));
+                pixels.push(radiance);
+            }
+        }
+        let sun_irradiance = sun_radiance * (sun_solid_angle * sun.y.max(0.0));
+        let ground = ground_albedo * (sky_irradiance + sun_irradiance) / f64::consts::PI;
+
+        // Aim at the sun in proportion to the light it gives
+        let luminance = |c: Color| 0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z;
+        let sun_power = luminance(sun_radiance) * sun_solid_angle;
+        let sky_power: f64 = (0..TABLE_HEIGHT)
+            .map(|y| {
+                let theta = f64::consts::PI * (y as f64 + 0.5) / TABLE_HEIGHT as f64;
+                let row = &pixels[y * TABLE_WIDTH..(y + 1) * TABLE_WIDTH];
+                row.iter().map(|&c| luminance(c)).sum::<f64>() * texel_solid_angle(theta)
+            })
+            .sum();
+        let sun_probability = if sun_power > 0.0 {
+            (sun_power / (sun_power + sky_power)).clamp(0.1, 0.9)
+        } else {
+            0.0
+        };
+
+        PreethamSky {
+            perez,
+            sun,
+            sun_radiance,
+            cos_sun_radius,
+            ground,
+            table: EnvironmentMap::new(
+                Arc::new(Image::new(TABLE_WIDTH, TABLE_HEIGHT, pixels)),
+                0.0,
+                1.0,
+            ),
+            sun_probability,
+        }
+    }
+
+    /// Radiance of the sun's disk, which shading with a delta light standing
+    /// in for the sun should use.
+    pub fn sun_radiance(&self) -> Color {
+        self.sun_radiance
+    }
+
+    /// Unit direction towards the sun.
+    pub fn sun_direction(&self) -> Vec3<f64> {
+        self.sun
+    }
+
+    fn sun_pdf(&self, direction: &Vec3<f64>) -> f64 {
+        if direction.unit_vector().dot(&self.sun) < self.cos_sun_radius {
+            return 0.0;
+        }
+        1.0 / (2.0 * f64::consts::PI * (1.0 - self.cos_sun_radius))
+    }
+}
+
+impl Background for PreethamSky {
+    fn value(&self, r: &Ray) -> Color {
+        let direction = r.direction().unit_vector();
+        if direction.y < 0.0 {
+            return self.ground;
+        }
+        let mut color = self.perez.radiance(&direction, &self.sun);
+        if direction.dot(&self.sun) >= self.cos_sun_radius {
+            color += self.sun_radiance;
+        }
+        color
+    }
+
+    fn pdf(&self) -> Option<&dyn Pdf> {
+        Some(self)
+    }
+}
+
+impl Pdf for PreethamSky {
+    fn value(&self, direction: &Vec3<f64>) -> f64 {
+        // The ground is left to the materials' own sampling
+        self.sun_probability * self.sun_pdf(direction)
+            + (1.0 - self.sun_probability) * Pdf::value(&self.table, direction)
+    }
+
+    fn generate(&self) -> Vec3<f64> {
+        if random_double() < self.sun_probability {
+            let local = uniform_cone(random_double(), random_double(), self.cos_sun_radius);
+            Onb::new(&self.sun).transform(&local)
+        } else {
+            self.table.generate()
+        }
+    }
+}
+
+// The fitted model: zenith luminance and chromaticity, and the Perez
+// coefficients for how each varies over the sky
+struct Perez {
+    zenith: [f64; 3],
+    coefficients: [[f64; 5]; 3],
+    theta_sun: f64,
+}
+
+impl Perez {
+    fn new(turbidity: f64, theta_sun: f64) -> Self {
+        let t = turbidity;
+        let chi = (4.0 / 9.0 - t / 120.0) * (f64::consts::PI - 2.0 * theta_sun);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let (t2, s, s2, s3) = (t * t, theta_sun, theta_sun * theta_sun, theta_sun.powi(3));
+        let zenith_x = t2 * (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s)
+            + t * (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s + 0.00394)
+            + (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s + 0.25886);
+        let zenith_y = t2 * (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s)
+            + t * (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s + 0.00516)
+            + (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s + 0.26688);
+
+        let coefficients = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+        Perez {
+            zenith: [zenith_luminance, zenith_x, zenith_y],
+            coefficients,
+            theta_sun,
+        }
+    }
+
+    fn f(c: &[f64; 5], theta: f64, gamma: f64) -> f64 {
+        let cos_gamma = gamma.cos();
+        (1.0 + c[0] * (c[1] / theta.cos().max(0.01)).exp())
+            * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * cos_gamma * cos_gamma)
+    }
+
+    // Linear sRGB radiance for a unit direction at or above the horizon
+    fn radiance(&self, direction: &Vec3<f64>, sun: &Vec3<f64>) -> Color {
+        let theta = direction.y.clamp(0.0, 1.0).acos();
+        let gamma = direction.dot(sun).clamp(-1.0, 1.0).acos();
+        let [big_y, x, y] = [0, 1, 2].map(|i| {
+            let c = &self.coefficients[i];
+            self.zenith[i] * Perez::f(c, theta, gamma) / Perez::f(c, 0.0, self.theta_sun)
+        });
+
+        // xyY to XYZ to linear sRGB
+        let big_x = x * big_y / y;
+        let big_z = (1.0 - x - y) * big_y / y;
+        let rgb = Color::new(
+            3.2406 * big_x - 1.5372 * big_y - 0.4986 * big_z,
+            -0.9689 * big_x + 1.8758 * big_y + 0.0415 * big_z,
+            0.0557 * big_x - 0.2040 * big_y + 1.0570 * big_z,
+        );
+        Color::new(rgb.x.max(0.0), rgb.y.max(0.0), rgb.z.max(0.0)) * LUMINANCE_SCALE
+    }
+}
+
+// Fraction of sunlight reaching the ground at red, green and blue, from
+// Rayleigh scattering and Angstrom's turbidity law for aerosols over the
+// Kasten and Young air mass
+fn sun_transmittance(turbidity: f64, theta_sun: f64) -> Color {
+    let elevation = 90.0 - theta_sun.to_degrees();
+    let air_mass = 1.0 / (theta_sun.cos() + 0.50572 * (elevation + 6.07995).powf(-1.6364));
+    let beta = 0.04608 * turbidity - 0.04586;
+    let channel = |wavelength: f64| {
+        let rayleigh = 0.008735 * wavelength.powf(-4.08);
+        let aerosol = beta * wavelength.powf(-1.3);
+        (-air_mass * (rayleigh + aerosol)).exp()
+    };
+    Color::new(channel(0.65), channel(0.55), channel(0.45))
+}
+
+#[test]
+fn sky_is_blue_overhead_and_sampled_towards_the_sun() {
+    let sun = Vec3::new(0.0, 1.0, -1.0);
+    let sky = PreethamSky::new(sun, 3.0, Color::from_float(0.3));
+
+    // A clear sky is bluer straight up than the ground is, and brightest
+    // near the sun
+    let look = |d: Vec3<f64>| Background::value(&sky, &Ray::new(Point3::zero(), d));
+    let zenith = look(Vec3::new(0.0, 1.0, 0.0));
+    assert!(zenith.z > zenith.x, "{zenith:?}");
+    let near_sun = look(Vec3::new(0.0, 1.0, -1.2));
+    let away = look(Vec3::new(0.0, 1.0, 1.0));
+    assert!(near_sun.y > away.y, "{near_sun:?} {away:?}");
+    assert_eq!(look(Vec3::new(0.3, -1.0, 0.2)), sky.ground);
+
+    // The sun is redder than the light above the atmosphere, and many
+    // samples head for it
+    let sun_color = sky.sun_radiance();
+    assert!(sun_color.x > sun_color.z, "{sun_color:?}");
+    let hits = (0..1000)
+        .filter(|_| sky.generate().unit_vector().dot(&sky.sun_direction()) >= sky.cos_sun_radius)
+        .count();
+    assert!(hits as f64 > 800.0 * sky.sun_probability, "{hits}");
+}
@@ -0,0 +1,358 @@
+use core::f64;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign};
+
+use crate::common::ray::Ray;
+use vec3::*;
+
+/// Wavelengths followed by each path in spectral mode.
+pub const SPECTRUM_SAMPLES: usize = 4;
+// Range of wavelengths sampled, in nanometres
+pub const LAMBDA_MIN: f64 = 360.0;
+pub const LAMBDA_MAX: f64 = 830.0;
+// The helium d line, at which glasses' refractive indices are quoted and
+// which stands in for every wavelength when rendering in RGB
+pub const D_LINE: f64 = 587.56;
+
+// Integrals of the colour matching functions below over the sampled range
+const CIE_X_INTEGRAL: f64 = 106.765_818_580_246_3;
+const CIE_Y_INTEGRAL: f64 = 106.922_074_506_916_35;
+const CIE_Z_INTEGRAL: f64 = 106.875_004_949_065_14;
+
+/// The wavelengths one path carries, in nanometres, with the density each
+/// was drawn from. The first is the hero: a material whose behaviour depends
+/// on wavelength, such as a dispersive glass, follows the hero alone and
+/// drops the rest.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Wavelengths {
+    lambda: [f64; SPECTRUM_SAMPLES],
+    pdf: [f64; SPECTRUM_SAMPLES],
+}
+
+impl Wavelengths {
+    /// A hero wavelength drawn from `u`, and the rest spaced evenly from it
+    /// through the same distribution. The distribution favours the middle of
+    /// the visible range, where the eye is most sensitive.
+    pub fn sample_visible(u: f64) -> Self {
+        let mut lambda = [0.0; SPECTRUM_SAMPLES];
+        let mut pdf = [0.0; SPECTRUM_SAMPLES];
+        for i in 0..SPECTRUM_SAMPLES {
+            let u = (u + i as f64 / SPECTRUM_SAMPLES as f64).fract();
+            lambda[i] = 538.0 - 138.888_889 * (0.856_910_62 - 1.827_501_97 * u).atanh();
+            pdf[i] = visible_pdf(lambda[i]);
+        }
+        Wavelengths { lambda, pdf }
+    }
+
+    pub fn hero(&self) -> f64 {
+        self.lambda[0]
+    }
+
+    /// The hero alone, carrying the light of all the wavelengths it was
+    /// drawn alongside.
+    pub fn terminate_secondary(&self) -> Self {
+        if self.secondary_terminated() {
+            return *self;
+        }
+        let mut terminated = *self;
+        terminated.pdf[0] /= SPECTRUM_SAMPLES as f64;
+        terminated.pdf[1..].fill(0.0);
+        terminated
+    }
+
+    pub fn secondary_terminated(&self) -> bool {
+        self.pdf[1..].iter().all(|&pdf| pdf == 0.0)
+    }
+}
+
+// Density of `Wavelengths::sample_visible` for any one wavelength
+fn visible_pdf(lambda: f64) -> f64 {
+    if !(LAMBDA_MIN..=LAMBDA_MAX).contains(&lambda) {
+        return 0.0;
+    }
+    0.003_939_804_2 / (0.0072 * (lambda - 538.0)).cosh().powi(2)
+}
+
+/// Light or reflectance at each of a path's `Wavelengths`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SampledSpectrum(pub [f64; SPECTRUM_SAMPLES]);
+
+impl SampledSpectrum {
+    pub fn zero() -> Self {
+        SampledSpectrum([0.0; SPECTRUM_SAMPLES])
+    }
+
+    /// A smooth spectrum with the colour `c`, by Smits' method of summing
+    /// white with the spectra of the secondary and primary colours. Scales
+    /// linearly with `c`, so works for lights as well as reflectances.
+    /// Dropped wavelengths are left black.
+    pub fn from_rgb(c: Color, wavelengths: &Wavelengths) -> Self {
+        let mut values = [0.0; SPECTRUM_SAMPLES];
+        for (i, value) in values.iter_mut().enumerate() {
+            if wavelengths.pdf[i] > 0.0 {
+                *value = smits(c, wavelengths.lambda[i]);
+            }
+        }
+        SampledSpectrum(values)
+    }
+
+    /// The linear sRGB colour these samples estimate. A spectrum that is
+    /// constant across the visible range comes out white.
+    pub fn to_rgb(self, wavelengths: &Wavelengths) -> Color {
+        let mut xyz = Vec3::zero();
+        for i in 0..SPECTRUM_SAMPLES {
+            if wavelengths.pdf[i] > 0.0 {
+                xyz += cie_xyz(wavelengths.lambda[i]) * (self.0[i] / wavelengths.pdf[i]);
+            }
+        }
+        let xyz = xyz / SPECTRUM_SAMPLES as f64;
+        xyz_to_rgb(Vec3::new(
+            xyz.x / CIE_X_INTEGRAL,
+            xyz.y / CIE_Y_INTEGRAL,
+            xyz.z / CIE_Z_INTEGRAL,
+        ))
+    }
+}
+
+/// The linear sRGB colour of a surface reflecting `reflectance(wavelength)`
+/// of white light, integrated over the visible range. For rendering in RGB
+/// materials whose spectra are known but not smooth.
+pub fn reflectance_to_rgb(reflectance: impl Fn(f64) -> f64) -> Color {
+    const STEPS: usize = 40;
+    let mut xyz = Vec3::zero();
+    let mut white = Vec3::zero();
+    for i in 0..STEPS {
+        let lambda = 380.0 + 400.0 * (i as f64 + 0.5) / STEPS as f64;
+        let cmf = cie_xyz(lambda);
+        xyz += cmf * reflectance(lambda);
+        white += cmf;
+    }
+    xyz_to_rgb(xyz / white)
+}
+
+// XYZ normalised so that equal energy is X = Y = Z = 1 to linear sRGB, by
+// first scaling that white onto sRGB's D65 white point
+fn xyz_to_rgb(xyz: Vec3<f64>) -> Color {
+    let (x, y, z) = (xyz.x * 0.950_47, xyz.y, xyz.z * 1.088_83);
+    Color::new(
+        3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+        0.0557 * x - 0.2040 * y + 1.0570 * z,
+    )
+}
+
+impl Add for SampledSpectrum {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        SampledSpectrum(std::array::from_fn(|i| self.0[i] + other.0[i]))
+    }
+}
+
+impl AddAssign for SampledSpectrum {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Mul for SampledSpectrum {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        SampledSpectrum(std::array::from_fn(|i| self.0[i] * other.0[i]))
+    }
+}
+
+impl Mul<f64> for SampledSpectrum {
+    type Output = Self;
+    fn mul(self, t: f64) -> Self {
+        SampledSpectrum(self.0.map(|v| v * t))
+    }
+}
+
+impl MulAssign<f64> for SampledSpectrum {
+    fn mul_assign(&mut self, t: f64) {
+        *self = *self * t;
+    }
+}
+
+impl Div<f64> for SampledSpectrum {
+    type Output = Self;
+    fn div(self, t: f64) -> Self {
+        self * (1.0 / t)
+    }
+}
+
+/// What the camera carries back along a path: RGB, or a `SampledSpectrum`
+/// when rendering spectrally. Materials, lights and backgrounds all give
+/// RGB, which `from_rgb` turns into whichever is being carried.
+pub trait Radiance:
+    Copy
+    + Add<Output = Self>
+    + AddAssign
+    + Mul<Output = Self>
+    + Mul<f64, Output = Self>
+    + MulAssign<f64>
+    + Div<f64, Output = Self>
+{
+    fn zero() -> Self;
+
+    /// `c` as seen by `r`.
+    fn from_rgb(c: Color, r: &Ray) -> Self;
+}
+
+impl Radiance for Color {
+    fn zero() -> Self {
+        Color::zero()
+    }
+
+    fn from_rgb(c: Color, _r: &Ray) -> Self {
+        c
+    }
+}
+
+impl Radiance for SampledSpectrum {
+    fn zero() -> Self {
+        SampledSpectrum::zero()
+    }
+
+    fn from_rgb(c: Color, r: &Ray) -> Self {
+        let wavelengths = r.wavelengths().expect("spectral rays carry wavelengths");
+        SampledSpectrum::from_rgb(c, wavelengths)
+    }
+}
+
+/// How a clear material's refractive index varies with wavelength.
+/// Wavelengths in the formulas are in micrometres.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ior {
+    Constant(f64),
+    /// n = a + b / l^2
+    Cauchy { a: f64, b: f64 },
+    /// n^2 = 1 + sum of b l^2 / (l^2 - c)
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Ior {
+    /// Schott's borosilicate crown, common optical glass.
+    pub const BK7: Ior = Ior::Sellmeier {
+        b: [1.039_612_12, 0.231_792_344, 1.010_469_45],
+        c: [0.006_000_698_67, 0.020_017_914_4, 103.560_653],
+    };
+    /// Schott's dense flint, which spreads colours far more than BK7.
+    pub const SF11: Ior = Ior::Sellmeier {
+        b: [1.737_596_95, 0.313_747_346, 1.898_781_01],
+        c: [0.013_188_707, 0.062_306_814_2, 155.236_29],
+    };
+    pub const DIAMOND: Ior = Ior::Sellmeier {
+        b: [4.3356, 0.3306, 0.0],
+        c: [0.011_236, 0.030_625, 0.0],
+    };
+
+    /// The refractive index at `wavelength` nanometres.
+    pub fn at(&self, wavelength: f64) -> f64 {
+        let l2 = (wavelength / 1000.0).powi(2);
+        match self {
+            Ior::Constant(n) => *n,
+            Ior::Cauchy { a, b } => a + b / l2,
+            Ior::Sellmeier { b, c } => {
+                (1.0 + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<f64>()).sqrt()
+            }
+        }
+    }
+
+    pub fn is_dispersive(&self) -> bool {
+        !matches!(self, Ior::Constant(_))
+    }
+}
+
+// Wyman, Sloan and Shirley's multi-lobe fit to the CIE 1931 standard
+// observer
+fn cie_xyz(lambda: f64) -> Vec3<f64> {
+    let g = |mu: f64, sigma_below: f64, sigma_above: f64| {
+        let sigma = if lambda < mu { sigma_below } else { sigma_above };
+        (-0.5 * ((lambda - mu) / sigma).powi(2)).exp()
+    };
+    Vec3::new(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+// Smits' spectra, in ten bins spanning 380 to 720 nm
+const SMITS_WHITE: [f64; 10] = [1.0, 1.0, 0.9999, 0.9993, 0.9992, 0.9998, 1.0, 1.0, 1.0, 1.0];
+const SMITS_CYAN: [f64; 10] = [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0, 0.0, 0.0];
+const SMITS_MAGENTA: [f64; 10] = [1.0, 1.0, 0.9685, 0.2229, 0.0, 0.0458, 0.8369, 1.0, 1.0, 0.9959];
+const SMITS_YELLOW: [f64; 10] = [0.0001, 0.0, 0.1088, 0.6651, 1.0, 1.0, 0.9996, 0.9586, 0.9685, 0.9840];
+const SMITS_RED: [f64; 10] = [0.1012, 0.0515, 0.0, 0.0, 0.0, 0.0, 0.8325, 1.0149, 1.0149, 1.0149];
+const SMITS_GREEN: [f64; 10] = [0.0, 0.0, 0.0273, 0.7937, 1.0, 0.9418, 0.1719, 0.0, 0.0, 0.0025];
+const SMITS_BLUE: [f64; 10] = [1.0, 1.0, 0.8916, 0.3323, 0.0, 0.0, 0.0003, 0.0369, 0.0483, 0.0496];
+
+fn smits(c: Color, lambda: f64) -> f64 {
+    // Interpolate between bin centres, holding the end bins beyond them
+    let x = ((lambda - 380.0) / 34.0 - 0.5).clamp(0.0, 9.0);
+    let i = (x as usize).min(8);
+    let t = x - i as f64;
+    let at = |s: &[f64; 10]| s[i] * (1.0 - t) + s[i + 1] * t;
+
+    let (r, g, b) = (c.x, c.y, c.z);
+    if r <= g && r <= b {
+        let base = r * at(&SMITS_WHITE);
+        if g <= b {
+            base + (g - r) * at(&SMITS_CYAN) + (b - g) * at(&SMITS_BLUE)
+        } else {
+            base + (b - r) * at(&SMITS_CYAN) + (g - b) * at(&SMITS_GREEN)
+        }
+    } else if g <= r && g <= b {
+        let base = g * at(&SMITS_WHITE);
+        if r <= b {
+            base + (r - g) * at(&SMITS_MAGENTA) + (b - r) * at(&SMITS_BLUE)
+        } else {
+            base + (b - g) * at(&SMITS_MAGENTA) + (r - b) * at(&SMITS_RED)
+        }
+    } else {
+        let base = b * at(&SMITS_WHITE);
+        if r <= g {
+            base + (r - b) * at(&SMITS_YELLOW) + (g - r) * at(&SMITS_GREEN)
+        } else {
+            base + (g - b) * at(&SMITS_YELLOW) + (r - g) * at(&SMITS_RED)
+        }
+    }
+}
+
+#[test]
+fn colors_survive_the_trip_through_spectra() {
+    use crate::common::util::random_double;
+
+    // Averaged over many wavelength samples, an upsampled colour comes back
+    // out close to itself
+    let round_trip = |c: Color| {
+        let n = 100_000;
+        (0..n)
+            .map(|_| {
+                let wavelengths = Wavelengths::sample_visible(random_double());
+                SampledSpectrum::from_rgb(c, &wavelengths).to_rgb(&wavelengths)
+            })
+            .fold(Color::zero(), |sum, c| sum + c)
+            / n as f64
+    };
+    let white = round_trip(Color::from_float(1.0));
+    assert!((white - Color::from_float(1.0)).length() < 0.03, "{white:?}");
+    for c in [Color::new(0.8, 0.1, 0.1), Color::new(0.1, 0.6, 0.2), Color::new(0.2, 0.3, 0.9)] {
+        let back = round_trip(c);
+        assert!((back - c).length() < 0.15, "{c:?} came back {back:?}");
+    }
+
+    // Dropping the secondary wavelengths leaves the hero's weight the same
+    let wavelengths = Wavelengths::sample_visible(0.3);
+    let terminated = wavelengths.terminate_secondary();
+    assert!(terminated.secondary_terminated() && !wavelengths.secondary_terminated());
+    assert_eq!(terminated.terminate_secondary(), terminated);
+    let spectrum = SampledSpectrum::from_rgb(Color::from_float(1.0), &terminated);
+    assert_eq!(spectrum.0[1..], [0.0; SPECTRUM_SAMPLES - 1]);
+
+    // Glass bends blue more than red, and BK7 has its catalogue index
+    assert!((Ior::BK7.at(D_LINE) - 1.5168).abs() < 1e-3, "{}", Ior::BK7.at(D_LINE));
+    assert!((Ior::DIAMOND.at(D_LINE) - 2.417).abs() < 0.01, "{}", Ior::DIAMOND.at(D_LINE));
+    for ior in [Ior::BK7, Ior::SF11, Ior::Cauchy { a: 1.5, b: 0.004 }] {
+        assert!(ior.at(450.0) > ior.at(650.0));
+    }
+}
@@ -0,0 +1,125 @@
+use crate::common::{
+    hittable::HitRecord,
+    material::{Material, ScatterRecord},
+    ray::Ray,
+    spectrum::{Ior, D_LINE},
+    util::random_double,
+};
+use vec3::*;
+
+/// Mirrors `v` about the surface with unit normal `n`.
+pub fn reflect(v: &Vec3<f64>, n: &Vec3<f64>) -> Vec3<f64> {
+    *v - *n * (2.0 * v.dot(n))
+}
+
+/// Bends the unit vector `uv` through a surface with unit normal `n`
+/// facing against it, by Snell's law with the ratio of refractive indices.
+pub fn refract(uv: &Vec3<f64>, n: &Vec3<f64>, etai_over_etat: f64) -> Vec3<f64> {
+    let cos_theta = (-*uv).dot(n).min(1.0);
+    let r_out_perp = (uv + *n * cos_theta) * etai_over_etat;
+    let r_out_parallel = *n * -(1.0 - r_out_perp.length_squared()).abs().sqrt();
+    r_out_perp + r_out_parallel
+}
+
+/// A reflective surface, blurred by perturbing the mirror direction within a
+/// sphere of radius `fuzz`.
+pub struct Metal {
+    albedo: Color,
+    fuzz: f64,
+}
+
+impl Metal {
+    pub fn new(albedo: Color, fuzz: f64) -> Self {
+        Metal {
+            albedo,
+            fuzz: fuzz.min(1.0),
+        }
+    }
+}
+
+impl Material for Metal {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        let reflected = reflect(&r_in.direction().unit_vector(), &rec.normal());
+        let direction = (reflected + Vec3::random_unit_vector() * self.fuzz).unit_vector();
+        srec.specular_ray = rec
+            .spawn_ray(r_in, direction)
+            .with_differentials(rec.reflected_differentials(r_in, &direction));
+        srec.attenuation = self.albedo;
+        srec.pdf = None;
+        // Fuzz can push the ray below the surface, absorb it there
+        direction.dot(&rec.normal()) > 0.0
+    }
+}
+
+/// A clear refractive material such as glass, choosing between reflection and
+/// refraction by Schlick's approximation of the Fresnel factor. A dispersive
+/// index splits white light into colours when rendering spectrally.
+pub struct Dielectric {
+    // Refractive index in vacuum or air, or the ratio of the material's index
+    // over the index of the enclosing medium
+    refraction_index: Ior,
+}
+
+impl Dielectric {
+    pub fn new(refraction_index: f64) -> Self {
+        Self::dispersive(Ior::Constant(refraction_index))
+    }
+
+    pub fn dispersive(refraction_index: Ior) -> Self {
+        Dielectric { refraction_index }
+    }
+
+    fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
+        let r0 = ((1.0 - refraction_index) / (1.0 + refraction_index)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+    }
+}
+
+impl Material for Dielectric {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        srec.attenuation = Color::from_float(1.0);
+        srec.pdf = None;
+        // Each wavelength bends its own way, so only the hero can go on
+        let refraction_index = match r_in.wavelengths() {
+            Some(w) if self.refraction_index.is_dispersive() => {
+                srec.hero_only = true;
+                self.refraction_index.at(w.hero())
+            }
+            _ => self.refraction_index.at(D_LINE),
+        };
+        let ri = if rec.front_face() {
+            1.0 / refraction_index
+        } else {
+            refraction_index
+        };
+
+        let unit_direction = r_in.direction().unit_vector();
+        let cos_theta = (-unit_direction).dot(&rec.normal()).min(1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+        let cannot_refract = ri * sin_theta > 1.0;
+        srec.specular_ray = if cannot_refract || Dielectric::reflectance(cos_theta, ri) > random_double() {
+            let direction = reflect(&unit_direction, &rec.normal());
+            rec.spawn_ray(r_in, direction)
+                .with_differentials(rec.reflected_differentials(r_in, &direction))
+        } else {
+            let direction = refract(&unit_direction, &rec.normal(), ri);
+            rec.spawn_ray(r_in, direction)
+                .with_differentials(rec.refracted_differentials(r_in, &direction, ri))
+        };
+        true
+    }
+}
+
+#[test]
+fn reflect_and_refract() {
+    let n = Vec3::new(0.0, 1.0, 0.0);
+    let v = Vec3::new(1.0, -1.0, 0.0).unit_vector();
+    assert!((reflect(&v, &n) - Vec3::new(1.0, 1.0, 0.0).unit_vector()).near_zero());
+    // Equal indices leave the direction unchanged
+    assert!((refract(&v, &n, 1.0) - v).near_zero());
+    // Entering glass bends towards the normal: sin(theta_t) = sin(45) / 1.5
+    let t = refract(&v, &n, 1.0 / 1.5);
+    assert!((t.x - 0.5f64.sqrt() / 1.5).abs() < 1e-12);
+    assert!((t.length() - 1.0).abs() < 1e-12);
+}
@@ -1,49 +1,192 @@
+use core::f64;
+use std::sync::Arc;
+
+use crate::common::csg::{Solid, Span};
 use crate::common::hittable::{Hittable, HitRecord};
-use crate::common::util::Interval;
+use crate::common::material::Material;
+use crate::common::pdf::uniform_cone;
+use crate::common::util::{azimuth_u, random_double, Interval};
 use crate::common::ray::*;
 use vec3::*;
 pub struct Sphere {
-    center: Point3,
+    // The center moves from center.origin() at time 0 to center.at(1.0) at time 1
+    center: Ray,
     radius: f64,
+    mat: Arc<dyn Material>,
 }
 
 impl Sphere {
-    pub fn new(c: Point3, r: f64) -> Self {
+    pub fn new(c: Point3, r: f64, mat: Arc<dyn Material>) -> Self {
         Sphere {
-            center: c,
+            center: Ray::new(c, Vec3::zero()),
             radius: r,
+            mat,
+        }
+    }
+
+    pub fn new_moving(c1: Point3, c2: Point3, r: f64, mat: Arc<dyn Material>) -> Self {
+        Sphere {
+            center: Ray::new(c1, c2 - c1),
+            radius: r,
+            mat,
         }
     }
 }
 
-impl Hittable for Sphere {
-    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
-        let oc = r.origin() - self.center;
+impl Sphere {
+    /// Maps a point on the unit sphere to (u, v): u is the angle around the y
+    /// axis from x = -1, v the angle from y = -1 to y = +1, both in [0, 1].
+    fn get_sphere_uv(p: &Point3) -> (f64, f64) {
+        let theta = (-p.y).acos();
+        (azimuth_u(p.x, p.z), theta / f64::consts::PI)
+    }
+
+    /// The derivatives of the surface point with respect to the (u, v) of
+    /// get_sphere_uv, given the unit normal. dp/dv vanishes at the poles.
+    fn get_sphere_dpduv(&self, n: &Vec3<f64>) -> (Vec3<f64>, Vec3<f64>) {
+        let pi = f64::consts::PI;
+        let sin_theta = (1.0 - n.y * n.y).max(0.0).sqrt();
+        let dpdu = Vec3::new(n.z, 0.0, -n.x) * (2.0 * pi * self.radius);
+        let dpdv = if sin_theta > 0.0 {
+            Vec3::new(-n.x * n.y / sin_theta, sin_theta, -n.z * n.y / sin_theta) * (pi * self.radius)
+        } else {
+            Vec3::zero()
+        };
+        (dpdu, dpdv)
+    }
+
+    /// Both roots of the ray against the sphere at the ray's time, if any.
+    fn roots(&self, r: &Ray) -> Option<(f64, f64)> {
+        let oc = r.origin() - self.center.at(r.time());
         let a = r.direction().length_squared();
         let half_b = oc.dot(&r.direction());
         let c = oc.length_squared() - self.radius * self.radius;
 
         let discriminant = half_b * half_b - a * c;
         if discriminant < 0.0 {
-            return false;
+            return None;
         };
         let sqrtd = discriminant.sqrt();
+        Some(((-half_b - sqrtd) / a, (-half_b + sqrtd) / a))
+    }
+
+    fn record(&self, r: &Ray, t: f64, rec: &mut HitRecord) {
+        rec.t = t;
+        rec.p = r.at(rec.t);
+        let outward_normal = (rec.point() - self.center.at(r.time())) / self.radius;
+        rec.set_face_normal(r, &outward_normal);
+        (rec.u, rec.v) = Sphere::get_sphere_uv(&outward_normal);
+        (rec.dpdu, rec.dpdv) = self.get_sphere_dpduv(&outward_normal);
+        // The normal is the offset from the center scaled by 1 / radius
+        rec.dndu = rec.dpdu / self.radius;
+        rec.dndv = rec.dpdv / self.radius;
+        rec.mat = Some(self.mat.clone());
+    }
+}
+
+impl Hittable for Sphere {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let Some((near, far)) = self.roots(r) else {
+            return false;
+        };
 
         // Find the nearest root that lies in the acceptable range
-        let mut root = (-half_b - sqrtd) / a;
+        let mut root = near;
         if !ray_t.surrounds(root) {
-            root = (-half_b + sqrtd) / a;
+            root = far;
             if !ray_t.surrounds(root) {
                 return false;
 
             }
         }
 
-        rec.t = root;
-        rec.p = r.at(rec.t);
-        let outward_normal = (rec.point() - self.center) / self.radius;
-        rec.set_face_normal(r, &outward_normal);
-
+        self.record(r, root, rec);
         true
     }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3<f64>, time: f64) -> f64 {
+        let mut rec = HitRecord::new();
+        let r = Ray::new_with_time(*origin, *direction, time);
+        if !self.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
+
+        // Uniform over the cone of directions the sphere covers at that time
+        let distance_squared = (self.center.at(time) - *origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return 0.0;
+        }
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        1.0 / (2.0 * f64::consts::PI * (1.0 - cos_theta_max))
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3<f64> {
+        let direction = self.center.at(time) - *origin;
+        let distance_squared = direction.length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return Vec3::random_unit_vector();
+        }
+
+        // A uniformly chosen direction inside the cone, about the cone's axis
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        let local = uniform_cone(random_double(), random_double(), cos_theta_max);
+        Onb::new(&direction).transform(&local)
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(HitRecord, f64)> {
+        // Look back at a uniformly chosen point from outside
+        let n = Vec3::random_unit_vector();
+        let p = self.center.at(time) + n * self.radius;
+        let mut rec = HitRecord::new();
+        let r = Ray::new_with_time(p + n * self.radius, -n, time);
+        if !self.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec) {
+            return None;
+        }
+        Some((rec, 1.0 / (4.0 * f64::consts::PI * self.radius * self.radius)))
+    }
+
+    fn surface_pdf(&self, origin: &Point3, direction: &Vec3<f64>, time: f64) -> f64 {
+        let mut rec = HitRecord::new();
+        let r = Ray::new_with_time(*origin, *direction, time);
+        if !self.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
+        1.0 / (4.0 * f64::consts::PI * self.radius * self.radius)
+    }
+}
+
+impl Solid for Sphere {
+    fn spans(&self, r: &Ray, spans: &mut Vec<Span>) {
+        if let Some((near, far)) = self.roots(r) {
+            let mut span = Span::default();
+            self.record(r, near, &mut span.enter);
+            self.record(r, far, &mut span.exit);
+            spans.push(span);
+        }
+    }
+}
+
+#[test]
+fn moving_sphere_is_sampled_where_it_is() {
+    let mat = Arc::new(crate::common::material::Lambertian::new(Color::from_float(0.5)));
+    let start = Point3::new(0.0, 0.0, -5.0);
+    let sphere = Sphere::new_moving(start, Point3::new(4.0, 0.0, -5.0), 1.0, mat);
+    let origin = Point3::zero();
+
+    // By time 1 it has moved clear of where it started
+    for time in [0.0, 1.0] {
+        let center = Point3::new(4.0 * time, 0.0, -5.0);
+        for _ in 0..100 {
+            let (rec, _) = sphere.sample_surface(time).unwrap();
+            assert!(((rec.p - center).length() - 1.0).abs() < 1e-9, "{:?}", rec.p);
+            let direction = sphere.random(&origin, time);
+            assert!(sphere.pdf_value(&origin, &direction, time) > 0.0);
+        }
+        let other = Point3::new(4.0 * (1.0 - time), 0.0, -5.0);
+        assert_eq!(sphere.pdf_value(&origin, &other, time), 0.0);
+        assert_eq!(sphere.surface_pdf(&origin, &other, time), 0.0);
+        assert!(sphere.surface_pdf(&origin, &center, time) > 0.0);
+    }
 }
@@ -0,0 +1,247 @@
+use std::sync::Arc;
+
+use crate::common::{
+    hittable::{HitRecord, Hittable},
+    material::{Material, ScatterRecord},
+    microfacet::fresnel_dielectric,
+    phase::{IsotropicPhase, PhaseFunction},
+    ray::Ray,
+    specular::{reflect, refract},
+    util::{random_double, Interval},
+};
+use vec3::*;
+
+// Walks that are still going after this many steps are given up as absorbed
+const MAX_STEPS: usize = 1024;
+
+/// A closed shape filled with a dense, translucent material such as skin, wax
+/// or marble. Light refracts in through the boundary, wanders inside by a
+/// random walk and leaves again somewhere else, so light bleeds through thin
+/// parts and softens the shading everywhere.
+pub struct Subsurface {
+    boundary: Arc<dyn Hittable>,
+    material: Arc<dyn Material>,
+}
+
+impl Subsurface {
+    /// `albedo` is the chance per channel of light surviving each time it
+    /// scatters inside, and `mean_free_path` the average distance it travels
+    /// between scatterings. The boundary has refractive index `ior` and must
+    /// be in world space, so transform it rather than the `Subsurface`.
+    pub fn new(boundary: Arc<dyn Hittable>, albedo: Color, mean_free_path: Color, ior: f64) -> Self {
+        Self::with_phase(boundary, albedo, mean_free_path, ior, Arc::new(IsotropicPhase))
+    }
+
+    /// A material that scatters with the given phase function rather than
+    /// isotropically.
+    pub fn with_phase(
+        boundary: Arc<dyn Hittable>,
+        albedo: Color,
+        mean_free_path: Color,
+        ior: f64,
+        phase: Arc<dyn PhaseFunction>,
+    ) -> Self {
+        let material = Arc::new(RandomWalk {
+            boundary: boundary.clone(),
+            albedo,
+            sigma_t: Color::one() / mean_free_path,
+            ior,
+            phase,
+        });
+        Subsurface { boundary, material }
+    }
+}
+
+impl Hittable for Subsurface {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        if !self.boundary.hit(r, ray_t, rec) {
+            return false;
+        }
+        rec.mat = Some(self.material.clone());
+        true
+    }
+}
+
+/// The material on a `Subsurface` boundary. Each hit either reflects off the
+/// smooth surface or traces a whole walk through the inside, coming back as
+/// one specular bounce from where it enters to where it leaves.
+pub struct RandomWalk {
+    boundary: Arc<dyn Hittable>,
+    albedo: Color,
+    // Extinction per unit length, per channel
+    sigma_t: Color,
+    ior: f64,
+    phase: Arc<dyn PhaseFunction>,
+}
+
+impl RandomWalk {
+    // Follows light that has just refracted in along `ray` until it leaves
+    // the boundary, returning the ray it leaves along and how much of each
+    // channel survived, or None if it was absorbed.
+    fn walk(&self, mut ray: Ray) -> Option<(Ray, Color)> {
+        let mut throughput = Color::one();
+        for _ in 0..MAX_STEPS {
+            // Sample the distance to the next scattering using one channel,
+            // picked in proportion to how much of it is left, and weight by
+            // the density of all the channels' choices together
+            let total = throughput.x + throughput.y + throughput.z;
+            if total <= 0.0 {
+                return None;
+            }
+            let pick = random_double() * total;
+            let channel = if pick < throughput.x {
+                0
+            } else if pick < throughput.x + throughput.y {
+                1
+            } else {
+                2
+            };
+            let distance = -(1.0 - random_double()).ln() / self.sigma_t[channel];
+
+            let mut rec = HitRecord::new();
+            let leaves = self.boundary.hit(&ray, Interval::new(0.0, distance), &mut rec);
+            let t = if leaves { rec.t } else { distance };
+            let transmittance = Color::new(
+                (-self.sigma_t.x * t).exp(),
+                (-self.sigma_t.y * t).exp(),
+                (-self.sigma_t.z * t).exp(),
+            );
+            // Reaching the boundary has the chance of flying at least that
+            // far, scattering short of it the density of stopping there
+            let along = if leaves { transmittance } else { self.sigma_t * transmittance };
+            let pdf = (throughput.x * along.x + throughput.y * along.y + throughput.z * along.z) / total;
+            if pdf <= 0.0 {
+                return None;
+            }
+
+            if leaves {
+                throughput = throughput * transmittance / pdf;
+                let direction = ray.direction().unit_vector();
+                let cos_theta = -direction.dot(&rec.normal());
+                if random_double() < fresnel_dielectric(cos_theta, 1.0 / self.ior) {
+                    ray = rec.spawn_ray(&ray, reflect(&direction, &rec.normal()));
+                    continue;
+                }
+                let out = refract(&direction, &rec.normal(), self.ior);
+                return Some((rec.spawn_ray(&ray, out), throughput));
+            }
+
+            throughput = throughput * self.albedo * self.sigma_t * transmittance / pdf;
+            let direction = self.phase.sample(&ray.direction().unit_vector());
+            ray = Ray::new_with_time(ray.at(t), direction, ray.time())
+                .with_wavelengths(ray.wavelengths().copied());
+
+            // Long walks in dim media are cut short by Russian roulette
+            let brightest = throughput.x.max(throughput.y).max(throughput.z);
+            if brightest < 0.25 {
+                if random_double() >= brightest {
+                    return None;
+                }
+                throughput /= brightest;
+            }
+        }
+        None
+    }
+}
+
+impl Material for RandomWalk {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        srec.attenuation = Color::one();
+        srec.pdf = None;
+        let direction = r_in.direction().unit_vector();
+
+        // Rays that start inside, such as from a camera there, just leave
+        if !rec.front_face() {
+            srec.specular_ray = rec.spawn_ray(r_in, direction);
+            return true;
+        }
+
+        let cos_theta = -direction.dot(&rec.normal());
+        if random_double() < fresnel_dielectric(cos_theta, self.ior) {
+            let reflected = reflect(&direction, &rec.normal());
+            srec.specular_ray = rec
+                .spawn_ray(r_in, reflected)
+                .with_differentials(rec.reflected_differentials(r_in, &reflected));
+            return true;
+        }
+
+        let inward = refract(&direction, &rec.normal(), 1.0 / self.ior);
+        let Some((exit, throughput)) = self.walk(rec.spawn_ray(r_in, inward)) else {
+            return false;
+        };
+        srec.specular_ray = exit;
+        srec.attenuation = throughput;
+        true
+    }
+
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        // Every way out is specular, and asking scatter would run a whole walk
+        0.0
+    }
+}
+
+#[test]
+fn semi_infinite_slab_albedo_matches_chandrasekhar() {
+    // Everything below z = 0
+    struct HalfSpace;
+
+    impl Hittable for HalfSpace {
+        fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+            let t = -r.origin().z / r.direction().z;
+            if !ray_t.surrounds(t) {
+                return false;
+            }
+            rec.t = t;
+            rec.p = r.at(t);
+            rec.set_face_normal(r, &Vec3::new(0.0, 0.0, 1.0));
+            true
+        }
+    }
+
+    // With a boundary that does not bend light, the fraction of light shone
+    // straight down that comes back out is 1 - H(1) sqrt(1 - albedo), where
+    // H is Chandrasekhar's function for isotropic scattering. It does not
+    // depend on the mean free path, so each channel can use a different one.
+    let h_at_one = |albedo: f64| {
+        let n = 200;
+        let mu: Vec<f64> = (0..n).map(|i| (i as f64 + 0.5) / n as f64).collect();
+        let mut h = vec![1.0; n];
+        for _ in 0..100 {
+            h = mu
+                .iter()
+                .map(|&m| {
+                    let integral: f64 = (0..n).map(|j| mu[j] * h[j] / (m + mu[j])).sum::<f64>() / n as f64;
+                    1.0 / ((1.0 - albedo).sqrt() + albedo / 2.0 * integral)
+                })
+                .collect();
+        }
+        let integral: f64 = (0..n).map(|j| mu[j] * h[j] / (1.0 + mu[j])).sum::<f64>() / n as f64;
+        1.0 / ((1.0 - albedo).sqrt() + albedo / 2.0 * integral)
+    };
+
+    let albedo = Color::new(0.5, 0.8, 0.95);
+    let slab = Subsurface::new(Arc::new(HalfSpace), albedo, Color::new(0.5, 1.0, 2.0), 1.0);
+    let r = Ray::new(Point3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+    let mut rec = HitRecord::new();
+    assert!(slab.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec));
+    let mat = rec.mat.clone().unwrap();
+
+    let n = 40_000;
+    let mut reflected = Color::zero();
+    for _ in 0..n {
+        let mut srec = ScatterRecord::default();
+        if mat.scatter(&r, &rec, &mut srec) {
+            assert!(srec.specular_ray.direction().z > 0.0);
+            reflected += srec.attenuation;
+        }
+    }
+    let reflected = reflected / n as f64;
+    for channel in 0..3 {
+        let expected = 1.0 - h_at_one(albedo[channel]) * (1.0 - albedo[channel]).sqrt();
+        assert!(
+            (reflected[channel] - expected).abs() < 0.015,
+            "channel {channel}: {} vs {expected}",
+            reflected[channel]
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use std::sync::Arc;
+
+use crate::common::{
+    hittable::HitRecord,
+    image::Image,
+    mipmap::{FilterMode, MipMap},
+    perlin::Perlin,
+};
+use vec3::*;
+
+pub trait Texture: Send + Sync {
+    /// The color at surface coordinates (u, v) and world point `p`.
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+
+    /// The color seen at a hit, free to filter over the hit's pixel footprint.
+    /// Defaults to a point lookup.
+    fn sample(&self, rec: &HitRecord) -> Color {
+        self.value(rec.u, rec.v, &rec.p)
+    }
+}
+
+pub struct SolidColor {
+    albedo: Color,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Color) -> Self {
+        SolidColor { albedo }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.albedo
+    }
+}
+
+/// A 3D checkerboard of cubes `scale` wide, alternating between two textures.
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        CheckerTexture {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+
+    pub fn from_colors(scale: f64, c1: Color, c2: Color) -> Self {
+        Self::new(scale, Arc::new(SolidColor::new(c1)), Arc::new(SolidColor::new(c2)))
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let x = (self.inv_scale * p.x).floor() as i64;
+        let y = (self.inv_scale * p.y).floor() as i64;
+        let z = (self.inv_scale * p.z).floor() as i64;
+
+        if (x + y + z) % 2 == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+/// What an image texture does with coordinates outside [0, 1].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WrapMode {
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+impl WrapMode {
+    /// Maps a possibly out of range texel index into [0, n).
+    pub fn apply(self, i: i64, n: usize) -> usize {
+        let n = n as i64;
+        let wrapped = match self {
+            WrapMode::Repeat => i.rem_euclid(n),
+            WrapMode::Clamp => i.clamp(0, n - 1),
+            WrapMode::Mirror => {
+                let period = i.rem_euclid(2 * n);
+                if period < n { period } else { 2 * n - 1 - period }
+            }
+        };
+        wrapped as usize
+    }
+}
+
+/// An image mapped over (u, v) with v = 1 at the top row. Point lookups are
+/// bilinear, hits carrying ray differentials are filtered with `filter`.
+pub struct ImageTexture {
+    mip: MipMap,
+    pub filter: FilterMode,
+}
+
+impl ImageTexture {
+    pub fn new(image: Arc<Image>, wrap: WrapMode) -> Self {
+        ImageTexture {
+            mip: MipMap::new(image, wrap),
+            filter: FilterMode::Ewa,
+        }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        self.mip.bilinear(0, u, 1.0 - v)
+    }
+
+    fn sample(&self, rec: &HitRecord) -> Color {
+        // Image rows run down while v runs up
+        let dst0 = (rec.dudx, -rec.dvdx);
+        let dst1 = (rec.dudy, -rec.dvdy);
+        self.mip.filter(self.filter, rec.u, 1.0 - rec.v, dst0, dst1)
+    }
+}
+
+/// Perlin noise remapped to grey levels, at `scale` cycles per unit.
+pub struct NoiseTexture {
+    noise: Perlin,
+    scale: f64,
+}
+
+impl NoiseTexture {
+    pub fn new(noise: Perlin, scale: f64) -> Self {
+        NoiseTexture { noise, scale }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        Color::one() * 0.5 * (1.0 + self.noise.noise(&(*p * self.scale)))
+    }
+}
+
+/// Marble veins: a sine wave along z whose phase is disturbed by turbulence.
+pub struct MarbleTexture {
+    noise: Perlin,
+    scale: f64,
+    turbulence: f64,
+    vein: Color,
+    base: Color,
+}
+
+impl MarbleTexture {
+    pub fn new(noise: Perlin, scale: f64, turbulence: f64, vein: Color, base: Color) -> Self {
+        MarbleTexture {
+            noise,
+            scale,
+            turbulence,
+            vein,
+            base,
+        }
+    }
+}
+
+impl Texture for MarbleTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let phase = self.scale * p.z + self.turbulence * self.noise.turb(p, 7);
+        let t = 0.5 * (1.0 + phase.sin());
+        self.vein * (1.0 - t) + self.base * t
+    }
+}
+
+/// Wood grain: concentric rings around the y axis, wobbled by turbulence.
+pub struct WoodTexture {
+    noise: Perlin,
+    rings_per_unit: f64,
+    turbulence: f64,
+    light: Color,
+    dark: Color,
+}
+
+impl WoodTexture {
+    pub fn new(noise: Perlin, rings_per_unit: f64, turbulence: f64, light: Color, dark: Color) -> Self {
+        WoodTexture {
+            noise,
+            rings_per_unit,
+            turbulence,
+            light,
+            dark,
+        }
+    }
+}
+
+impl Texture for WoodTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let radius = (p.x * p.x + p.z * p.z).sqrt();
+        let rings = radius * self.rings_per_unit + self.turbulence * self.noise.turb(p, 4);
+        // Sharpen the ring profile so the late wood reads as thin dark bands
+        let t = (rings - rings.floor()).powi(3);
+        self.light * (1.0 - t) + self.dark * t
+    }
+}
+
+#[test]
+fn image_texture_filtering_and_wrapping() {
+    // Black on the left column, white on the right
+    let image = Arc::new(Image::new(
+        2,
+        2,
+        vec![Color::zero(), Color::one(), Color::zero(), Color::one()],
+    ));
+    let p = Point3::zero();
+
+    // Halfway between texel centers is an even blend
+    let repeat = ImageTexture::new(image.clone(), WrapMode::Repeat);
+    assert!((repeat.value(0.5, 0.5, &p).x - 0.5).abs() < 1e-12);
+    // At the left edge, repeating blends with the far right column
+    assert!((repeat.value(0.0, 0.5, &p).x - 0.5).abs() < 1e-12);
+
+    let clamp = ImageTexture::new(image.clone(), WrapMode::Clamp);
+    assert_eq!(clamp.value(0.0, 0.5, &p).x, 0.0);
+    assert_eq!(clamp.value(3.0, 0.5, &p).x, 1.0);
+
+    let mirror = ImageTexture::new(image, WrapMode::Mirror);
+    assert_eq!(mirror.value(1.25, 0.5, &p).x, 1.0);
+    assert_eq!(mirror.value(1.75, 0.5, &p).x, 0.0);
+}
@@ -0,0 +1,270 @@
+use core::f64;
+use std::ops::{Add, Div, Mul, Sub};
+use std::sync::Arc;
+
+use crate::common::{
+    hittable::HitRecord,
+    material::{Material, ScatterRecord},
+    ray::Ray,
+    spectrum::reflectance_to_rgb,
+    specular::reflect,
+    util::random_double,
+};
+use vec3::*;
+
+/// A transparent film a few hundred nanometres thick over another material,
+/// such as a soap bubble, oil on water or the oxide on anodised metal. Light
+/// reflected from the film's top and bottom interferes, so how much comes
+/// back depends on wavelength and angle and the surface shimmers with
+/// colour. The film reflects like a mirror; what it lets through reaches
+/// `base`, and so does its reflection if the substrate is a metal.
+pub struct ThinFilm {
+    // None under a metal, which absorbs whatever gets through
+    base: Option<Arc<dyn Material>>,
+    // In nanometres
+    thickness: f64,
+    film_ior: f64,
+    // Complex refractive index of what the film lies on
+    substrate: Complex,
+}
+
+impl ThinFilm {
+    /// A film `thickness` nanometres thick with refractive index `film_ior`,
+    /// lying on `base`, whose surface has index `substrate_ior`. For a soap
+    /// bubble the base is a `Dielectric` of index one, and so is the
+    /// substrate: air.
+    pub fn new(base: Arc<dyn Material>, thickness: f64, film_ior: f64, substrate_ior: f64) -> Self {
+        ThinFilm {
+            base: Some(base),
+            thickness,
+            film_ior,
+            substrate: Complex::new(substrate_ior, 0.0),
+        }
+    }
+
+    /// A film grown on a smooth metal with complex refractive index eta + ik.
+    pub fn over_conductor(thickness: f64, film_ior: f64, eta: f64, k: f64) -> Self {
+        ThinFilm {
+            base: None,
+            thickness,
+            film_ior,
+            substrate: Complex::new(eta, k),
+        }
+    }
+
+    /// Reflectance of the film and substrate together, for light in air
+    /// arriving at `cos_theta` to the normal with `wavelength` nanometres.
+    pub fn airy_reflectance(&self, cos_theta: f64, wavelength: f64) -> f64 {
+        let cos1 = cos_theta.abs().min(1.0);
+        let sin2_1 = 1.0 - cos1 * cos1;
+        let n2 = self.film_ior;
+        let n3 = self.substrate;
+        let cos2_squared = 1.0 - sin2_1 / (n2 * n2);
+        if cos2_squared <= 0.0 {
+            return 1.0;
+        }
+        let cos2 = cos2_squared.sqrt();
+        let cos3 = (Complex::new(1.0, 0.0) - Complex::new(sin2_1, 0.0) / (n3 * n3)).sqrt();
+
+        // Amplitude reflection coefficients at the top and bottom of the
+        // film, for light polarised perpendicular and parallel
+        let real = |x: f64| Complex::new(x, 0.0);
+        let rs12 = real((cos1 - n2 * cos2) / (cos1 + n2 * cos2));
+        let rp12 = real((n2 * cos1 - cos2) / (n2 * cos1 + cos2));
+        let rs23 = (real(n2 * cos2) - n3 * cos3) / (real(n2 * cos2) + n3 * cos3);
+        let rp23 = (n3 * real(cos2) - real(n2) * cos3) / (n3 * real(cos2) + real(n2) * cos3);
+
+        // Sum the reflections bouncing inside the film, each a round trip
+        // behind the last
+        let phase = 4.0 * f64::consts::PI * n2 * self.thickness * cos2 / wavelength;
+        let round_trip = Complex::new(phase.cos(), phase.sin());
+        let airy = |r12: Complex, r23: Complex| {
+            let r23 = r23 * round_trip;
+            ((r12 + r23) / (real(1.0) + r12 * r23)).norm_squared()
+        };
+        ((airy(rs12, rs23) + airy(rp12, rp23)) / 2.0).clamp(0.0, 1.0)
+    }
+
+    // Reflectance at the hero wavelength when rendering spectrally, or the
+    // colour of white light reflected when not
+    fn reflectance(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        let cos_theta = r_in.direction().unit_vector().dot(&rec.normal());
+        match r_in.wavelengths() {
+            Some(w) => Color::from_float(self.airy_reflectance(cos_theta, w.hero())),
+            None => {
+                let c = reflectance_to_rgb(|lambda| self.airy_reflectance(cos_theta, lambda));
+                Color::new(c.x.clamp(0.0, 1.0), c.y.clamp(0.0, 1.0), c.z.clamp(0.0, 1.0))
+            }
+        }
+    }
+
+    // Chance of following the film's reflection rather than the base
+    fn reflect_probability(&self, reflectance: &Color) -> f64 {
+        if self.base.is_none() {
+            return 1.0;
+        }
+        (reflectance.x + reflectance.y + reflectance.z) / 3.0
+    }
+}
+
+impl Material for ThinFilm {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord) -> bool {
+        let reflectance = self.reflectance(r_in, rec);
+        let p = self.reflect_probability(&reflectance);
+        if random_double() < p {
+            let direction = reflect(&r_in.direction().unit_vector(), &rec.normal());
+            srec.specular_ray = rec
+                .spawn_ray(r_in, direction)
+                .with_differentials(rec.reflected_differentials(r_in, &direction));
+            srec.attenuation = reflectance / p;
+            srec.pdf = None;
+            srec.hero_only = r_in.wavelengths().is_some();
+            return true;
+        }
+
+        let Some(base) = &self.base else {
+            return false;
+        };
+        if !base.scatter(r_in, rec, srec) {
+            return false;
+        }
+        srec.attenuation = srec.attenuation * (Color::one() - reflectance) / (1.0 - p);
+        srec.hero_only |= r_in.wavelengths().is_some();
+        true
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> Color {
+        let Some(base) = &self.base else {
+            return Color::zero();
+        };
+        let reflectance = self.reflectance(r_in, rec);
+        let p = self.reflect_probability(&reflectance);
+        if p >= 1.0 {
+            return Color::zero();
+        }
+        base.eval(r_in, rec, scattered) * (Color::one() - reflectance) / (1.0 - p)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let Some(base) = &self.base else {
+            return 0.0;
+        };
+        let p = self.reflect_probability(&self.reflectance(r_in, rec));
+        (1.0 - p) * base.pdf(r_in, rec, scattered)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        match &self.base {
+            Some(base) => base.emitted(r_in, rec),
+            None => Color::zero(),
+        }
+    }
+}
+
+// Just enough complex arithmetic for absorbing substrates
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Self {
+        Complex { re, im }
+    }
+
+    fn norm_squared(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    // The principal square root, with a non-negative real part
+    fn sqrt(self) -> Self {
+        let r = self.norm_squared().sqrt();
+        let re = ((r + self.re) / 2.0).max(0.0).sqrt();
+        let im = ((r - self.re) / 2.0).max(0.0).sqrt();
+        Complex::new(re, if self.im < 0.0 { -im } else { im })
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+    fn div(self, other: Self) -> Self {
+        let d = other.norm_squared();
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / d,
+            (self.im * other.re - self.re * other.im) / d,
+        )
+    }
+}
+
+#[test]
+fn films_interfere() {
+    use crate::common::{
+        microfacet::{fresnel_conductor, fresnel_dielectric},
+        specular::Dielectric,
+    };
+
+    // With no thickness the film vanishes and the bare substrate reflects
+    let glass = ThinFilm::new(Arc::new(Dielectric::new(1.5)), 0.0, 1.33, 1.5);
+    let gold = ThinFilm::over_conductor(0.0, 1.6, 0.2, 3.0);
+    for cos in [1.0, 0.7, 0.3, 0.05] {
+        let bare = fresnel_dielectric(cos, 1.5);
+        assert!((glass.airy_reflectance(cos, 550.0) - bare).abs() < 1e-9, "{cos}");
+        let bare = fresnel_conductor(cos, &Color::from_float(0.2), &Color::from_float(3.0)).x;
+        assert!((gold.airy_reflectance(cos, 550.0) - bare).abs() < 1e-9, "{cos}");
+    }
+
+    // A quarter wave of the geometric mean index cancels reflection at that
+    // wavelength only, as on coated lenses
+    let n = 1.5_f64.sqrt();
+    let coating = ThinFilm::new(Arc::new(Dielectric::new(1.5)), 550.0 / (4.0 * n), n, 1.5);
+    assert!(coating.airy_reflectance(1.0, 550.0) < 1e-9);
+    assert!(coating.airy_reflectance(1.0, 420.0) > 1e-3);
+
+    // A soap film is coloured in RGB, and never reflects more than it gets
+    let bubble = ThinFilm::new(Arc::new(Dielectric::new(1.0)), 400.0, 1.33, 1.0);
+    let r = Ray::new(Point3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+    let mut rec = HitRecord::new();
+    rec.set_face_normal(&r, &Vec3::new(0.0, 0.0, 1.0));
+    let color = bubble.reflectance(&r, &rec);
+    assert!(color.x.max(color.y).max(color.z) > 2.0 * color.x.min(color.y).min(color.z), "{color:?}");
+    for lambda in (380..780).step_by(10) {
+        let reflectance = bubble.airy_reflectance(0.5, lambda as f64);
+        assert!((0.0..=1.0).contains(&reflectance));
+    }
+
+    // Over a diffuse base the density covers the chance of skipping the
+    // film's mirror lobe, the same on every call
+    let base = Arc::new(crate::common::material::Lambertian::new(Color::one()));
+    let coated = ThinFilm::new(base.clone(), 400.0, 1.33, 1.5);
+    let scattered = rec.spawn_ray(&r, Vec3::new(0.3, 0.0, 1.0));
+    let p = coated.reflect_probability(&coated.reflectance(&r, &rec));
+    let expected = (1.0 - p) * base.pdf(&r, &rec, &scattered);
+    for _ in 0..8 {
+        assert!((coated.pdf(&r, &rec, &scattered) - expected).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,102 @@
+use core::f64;
+use std::sync::Arc;
+
+use crate::common::csg::Solid;
+use crate::common::hittable::{Hittable, HitRecord};
+use crate::common::material::Material;
+use crate::common::ray::*;
+use crate::common::util::{azimuth_u, solve_quartic, Interval};
+use vec3::*;
+
+/// A torus lying in the xz-plane around `center`. `major_radius` is the distance
+/// from the center to the middle of the tube and `minor_radius` the radius of
+/// the tube itself.
+pub struct Torus {
+    center: Point3,
+    major_radius: f64,
+    minor_radius: f64,
+    mat: Arc<dyn Material>,
+}
+
+impl Torus {
+    pub fn new(c: Point3, major: f64, minor: f64, mat: Arc<dyn Material>) -> Self {
+        Torus {
+            center: c,
+            major_radius: major,
+            minor_radius: minor,
+            mat,
+        }
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        // Solve in terms of distance along a unit direction to keep the quartic
+        // coefficients well scaled, then convert back to the ray's parameter.
+        let scale = r.direction().length();
+        let dir = r.direction() / scale;
+        let o = r.origin() - self.center;
+
+        let r2 = self.major_radius * self.major_radius;
+        let b = 2.0 * o.dot(&dir);
+        let k = o.length_squared() + r2 - self.minor_radius * self.minor_radius;
+        let coefficients = [
+            1.0,
+            2.0 * b,
+            b * b + 2.0 * k - 4.0 * r2 * (dir.x * dir.x + dir.z * dir.z),
+            2.0 * b * k - 8.0 * r2 * (o.x * dir.x + o.z * dir.z),
+            k * k - 4.0 * r2 * (o.x * o.x + o.z * o.z),
+        ];
+
+        let Some(root) = solve_quartic(coefficients)
+            .into_iter()
+            .map(|s| s / scale)
+            .find(|&t| ray_t.surrounds(t))
+        else {
+            return false;
+        };
+
+        rec.t = root;
+        rec.p = r.at(rec.t);
+
+        // The normal points away from the nearest point on the tube's center line
+        let local = rec.p - self.center;
+        let radial = Vec3::new(local.x, 0.0, local.z).unit_vector();
+        let from_tube = local - radial * self.major_radius;
+        let outward_normal = from_tube / self.minor_radius;
+        rec.set_face_normal(r, &outward_normal);
+        rec.mat = Some(self.mat.clone());
+
+        rec.u = azimuth_u(local.x, local.z);
+        let theta = from_tube.y.atan2(from_tube.dot(&radial));
+        rec.v = (theta + f64::consts::PI) / (2.0 * f64::consts::PI);
+        rec.dpdu = Vec3::new(local.z, 0.0, -local.x) * (2.0 * f64::consts::PI);
+        // Turning theta moves the point around the tube in the plane of the radial
+        rec.dpdv = (Vec3::new(0.0, 1.0, 0.0) * from_tube.dot(&radial) - radial * from_tube.y)
+            * (2.0 * f64::consts::PI);
+        // Treated as locally flat when propagating ray differentials
+        rec.dndu = Vec3::zero();
+        rec.dndv = Vec3::zero();
+
+        true
+    }
+}
+
+impl Solid for Torus {}
+
+#[test]
+fn torus_hit_from_outside() {
+    let mat = Arc::new(crate::common::material::Lambertian::new(Color::from_float(0.5)));
+    let torus = Torus::new(Point3::zero(), 2.0, 0.5, mat);
+    let r = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0));
+    let mut rec = HitRecord::new();
+    assert!(torus.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+    // The outer edge of the tube sits at x = -2.5
+    assert!((rec.p.x + 2.5).abs() < 1e-9);
+    assert!((rec.t - 1.25).abs() < 1e-9);
+    assert!((rec.normal().x + 1.0).abs() < 1e-9);
+
+    // Straight down through the hole misses
+    let r = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+    assert!(!torus.hit(&r, Interval::new(0.0, f64::INFINITY), &mut rec));
+}
@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use crate::common::csg::Solid;
+use crate::common::hittable::{Hittable, HitRecord};
+use crate::common::ray::*;
+use crate::common::util::Interval;
+use vec3::*;
+
+/// An instance of some geometry placed in the world by an affine transform.
+/// The geometry is shared, so the same object can be instanced many times.
+pub struct Transformed<H: Hittable + ?Sized> {
+    object: Arc<H>,
+    motion: AnimatedTransform,
+}
+
+impl<H: Hittable + ?Sized> Transformed<H> {
+    pub fn new(object: Arc<H>, transform: Transform) -> Self {
+        Self::animated(object, AnimatedTransform::fixed(transform))
+    }
+
+    /// An instance that moves between keyframes over the camera's shutter
+    /// interval, so it is motion blurred.
+    pub fn animated(object: Arc<H>, motion: AnimatedTransform) -> Self {
+        Transformed { object, motion }
+    }
+}
+
+impl<H: Hittable + ?Sized> Hittable for Transformed<H> {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        // The direction is left unnormalized so t means the same in both spaces
+        let transform = self.motion.at(r.time());
+        let to_object = transform.inverse();
+        let object_ray = Ray::new_with_time(
+            to_object.point(&r.origin()),
+            to_object.vector(&r.direction()),
+            r.time(),
+        )
+        .with_wavelengths(r.wavelengths().copied());
+
+        if !self.object.hit(&object_ray, ray_t, rec) {
+            return false;
+        }
+
+        rec.transform(&transform);
+        true
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3<f64>, time: f64) -> f64 {
+        let transform = self.motion.at(time);
+        let to_object = transform.inverse();
+        let object_direction = to_object.vector(direction);
+        let r = Ray::new_with_time(to_object.point(origin), object_direction, time);
+        let pdf = self.object.pdf_value(&r.origin(), &object_direction, time);
+        let mut rec = HitRecord::new();
+        if pdf == 0.0 || !self.object.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
+
+        // Through a density over the surface's area, which the transform
+        // stretches, back to one over solid angle in the world. t is the
+        // same in both spaces.
+        let area_pdf = pdf * rec.normal().dot(&object_direction).abs()
+            / (rec.t * rec.t * object_direction.length().powi(3))
+            / area_scale(&transform, &rec.normal());
+        rec.transform(&transform);
+        let cosine = (rec.normal().dot(direction) / direction.length()).abs();
+        area_pdf * rec.t * rec.t * direction.length_squared() / cosine
+    }
+
+    fn random(&self, origin: &Point3, time: f64) -> Vec3<f64> {
+        let transform = self.motion.at(time);
+        let object_origin = transform.inverse().point(origin);
+        transform.vector(&self.object.random(&object_origin, time))
+    }
+
+    fn sample_surface(&self, time: f64) -> Option<(HitRecord, f64)> {
+        let transform = self.motion.at(time);
+        let (mut rec, pdf) = self.object.sample_surface(time)?;
+        let pdf = pdf / area_scale(&transform, &rec.normal());
+        rec.transform(&transform);
+        Some((rec, pdf))
+    }
+
+    fn surface_pdf(&self, origin: &Point3, direction: &Vec3<f64>, time: f64) -> f64 {
+        let transform = self.motion.at(time);
+        let to_object = transform.inverse();
+        let r = Ray::new_with_time(to_object.point(origin), to_object.vector(direction), time);
+        let mut rec = HitRecord::new();
+        if !self.object.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
+        let pdf = self.object.surface_pdf(&r.origin(), &r.direction(), time);
+        pdf / area_scale(&transform, &rec.normal())
+    }
+}
+
+// How many times larger `transform` makes a small patch of surface with unit
+// normal `n`
+fn area_scale(transform: &Transform, n: &Vec3<f64>) -> f64 {
+    let onb = Onb::new(n);
+    Vec3::cross_product(&transform.vector(&onb.u()), &transform.vector(&onb.v())).length()
+}
+
+impl<H: Solid + ?Sized> Solid for Transformed<H> {}
+
+#[test]
+fn transformed_lights_sample_like_placed_ones() {
+    use crate::common::{material::DiffuseLight, quad::Quad};
+
+    // A unit square stretched, turned and moved into the same place as a
+    // quad built there directly
+    let mat = Arc::new(DiffuseLight::new(Color::one()));
+    let (u, v) = (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+    let square = Quad::new(Point3::zero(), u, v, mat.clone());
+    let transform = Transform::translate(Vec3::new(0.0, 1.0, -4.0))
+        * Transform::rotate_y(30.0)
+        * Transform::scale(Vec3::new(2.0, 3.0, 1.0));
+    let transformed = Transformed::new(Arc::new(square), transform);
+    let corner = transform.point(&Point3::zero());
+    let placed = Quad::new(corner, transform.vector(&u), transform.vector(&v), mat);
+    let normal = transform.normal(&Vec3::new(0.0, 0.0, 1.0)).unit_vector();
+
+    // Six times the area, so a sixth of the density per unit area
+    let origin = Point3::new(0.5, 0.0, 1.0);
+    for _ in 0..100 {
+        let direction = transformed.random(&origin, 0.0);
+        let expected = placed.pdf_value(&origin, &direction, 0.0);
+        let pdf = transformed.pdf_value(&origin, &direction, 0.0);
+        assert!(expected > 0.0 && (pdf - expected).abs() < 1e-9 * expected, "{pdf} {expected}");
+        let pdf = transformed.surface_pdf(&origin, &direction, 0.0);
+        assert!((pdf - 1.0 / 6.0).abs() < 1e-12, "{pdf}");
+
+        let (rec, pdf) = transformed.sample_surface(0.0).unwrap();
+        assert!((pdf - 1.0 / 6.0).abs() < 1e-12, "{pdf}");
+        assert!(placed.surface_pdf(&origin, &(rec.p - origin), 0.0) > 0.0);
+        assert!((rec.normal() - normal).length() < 1e-12);
+    }
+}
@@ -1,3 +1,8 @@
+use core::f64;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use vec3::Vec3;
+
 pub struct Interval {
     pub min: f64,
     pub max: f64,
@@ -40,13 +45,206 @@ impl Interval {
         self.min < x && x < self.max
     }
 
-    const EMPTY : Interval = Interval {
+    pub const EMPTY : Interval = Interval {
         min: f64::INFINITY,
         max: f64::NEG_INFINITY,
     };
 
-    const UNIVERSE : Interval = Interval {
+    pub const UNIVERSE : Interval = Interval {
         min: f64::NEG_INFINITY,
         max: f64::INFINITY,
     };
-}
\ No newline at end of file
+}
+/// Returns a random real in [0, 1).
+pub fn random_double() -> f64 {
+    rand::thread_rng().r#gen::<f64>()
+}
+
+/// Returns a random real in [min, max).
+pub fn random_double_range(min: f64, max: f64) -> f64 {
+    min + (max - min) * random_double()
+}
+
+/// Weight for a sample drawn from a strategy with density `f_pdf` when another
+/// strategy with density `g_pdf` could have produced it too. Veach's power
+/// heuristic with exponent two.
+pub fn power_heuristic(f_pdf: f64, g_pdf: f64) -> f64 {
+    let f2 = f_pdf * f_pdf;
+    let g2 = g_pdf * g_pdf;
+    if f2 + g2 == 0.0 {
+        return 0.0;
+    }
+    f2 / (f2 + g2)
+}
+
+/// A deterministic generator for building procedural content, so a scene
+/// built from the same seed comes out the same every time.
+pub fn seeded_rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// Maps the angle around the y axis of a local point to a texture coordinate
+/// in [0, 1], starting from -x and turning towards +z.
+pub fn azimuth_u(x: f64, z: f64) -> f64 {
+    let phi = (-z).atan2(x) + f64::consts::PI;
+    phi / (2.0 * f64::consts::PI)
+}
+
+/// The unit vector pointing away from the y axis through a local point, zero
+/// on the axis itself.
+pub fn radial_direction(p: &Vec3<f64>) -> Vec3<f64> {
+    let rho = (p.x * p.x + p.z * p.z).sqrt();
+    if rho > 0.0 {
+        Vec3::new(p.x / rho, 0.0, p.z / rho)
+    } else {
+        Vec3::zero()
+    }
+}
+
+/// Real roots of `a*t^2 + b*t + c`, smallest first.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    if a == 0.0 {
+        if b == 0.0 {
+            return None;
+        }
+        let t = -c / b;
+        return Some((t, t));
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    // Avoid cancellation by never subtracting two values of similar magnitude
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let (t0, t1) = if q == 0.0 { (0.0, 0.0) } else { (q / a, c / q) };
+    if t0 < t1 { Some((t0, t1)) } else { Some((t1, t0)) }
+}
+
+/// Real roots of the monic cubic `t^3 + a*t^2 + b*t + c`.
+fn solve_cubic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    // Substitute t = y - a/3 to get the depressed cubic y^3 + p*y + q
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + b) / 3.0;
+    let q = (2.0 / 27.0 * a * sq_a - a * b / 3.0 + c) / 2.0;
+    let cb_p = p * p * p;
+    let d = q * q + cb_p;
+    let shift = a / 3.0;
+
+    if d.abs() < 1e-14 {
+        if q.abs() < 1e-14 {
+            vec![-shift]
+        } else {
+            let u = (-q).cbrt();
+            vec![2.0 * u - shift, -u - shift]
+        }
+    } else if d < 0.0 {
+        // Three real roots, use the trigonometric form
+        let phi = (-q / (-cb_p).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        vec![
+            t * phi.cos() - shift,
+            -t * (phi + f64::consts::PI / 3.0).cos() - shift,
+            -t * (phi - f64::consts::PI / 3.0).cos() - shift,
+        ]
+    } else {
+        let sqrt_d = d.sqrt();
+        vec![(sqrt_d - q).cbrt() - (sqrt_d + q).cbrt() - shift]
+    }
+}
+
+/// Real roots of `c[0]*t^4 + c[1]*t^3 + c[2]*t^2 + c[3]*t + c[4]`, sorted
+/// ascending. Uses Ferrari's method followed by a few Newton steps to polish
+/// the roots, since the closed form loses precision for grazing rays.
+pub fn solve_quartic(c: [f64; 5]) -> Vec<f64> {
+    if c[0] == 0.0 {
+        return Vec::new();
+    }
+
+    let a = c[1] / c[0];
+    let b = c[2] / c[0];
+    let cc = c[3] / c[0];
+    let d = c[4] / c[0];
+
+    // Substitute t = y - a/4 to get the depressed quartic y^4 + p*y^2 + q*y + r
+    let sq_a = a * a;
+    let p = -3.0 / 8.0 * sq_a + b;
+    let q = 1.0 / 8.0 * sq_a * a - 0.5 * a * b + cc;
+    let r = -3.0 / 256.0 * sq_a * sq_a + 1.0 / 16.0 * sq_a * b - 0.25 * a * cc + d;
+
+    let mut roots = Vec::with_capacity(4);
+    if r.abs() < 1e-14 {
+        // y * (y^3 + p*y + q) = 0
+        roots.push(0.0);
+        roots.extend(solve_cubic(0.0, p, q));
+    } else {
+        // Take one root of the resolvent cubic and factor into two quadratics
+        let z = solve_cubic(-0.5 * p, -r, 0.5 * r * p - 0.125 * q * q)[0];
+
+        let mut u = z * z - r;
+        let mut v = 2.0 * z - p;
+        if u.abs() < 1e-14 {
+            u = 0.0;
+        } else if u > 0.0 {
+            u = u.sqrt();
+        } else {
+            return Vec::new();
+        }
+        if v.abs() < 1e-14 {
+            v = 0.0;
+        } else if v > 0.0 {
+            v = v.sqrt();
+        } else {
+            return Vec::new();
+        }
+
+        let v_q = if q < 0.0 { -v } else { v };
+        if let Some((r0, r1)) = solve_quadratic(1.0, v_q, z - u) {
+            roots.push(r0);
+            roots.push(r1);
+        }
+        if let Some((r0, r1)) = solve_quadratic(1.0, -v_q, z + u) {
+            roots.push(r0);
+            roots.push(r1);
+        }
+    }
+
+    let shift = 0.25 * a;
+    for root in roots.iter_mut() {
+        let mut t = *root - shift;
+        for _ in 0..2 {
+            let f = (((c[0] * t + c[1]) * t + c[2]) * t + c[3]) * t + c[4];
+            let df = ((4.0 * c[0] * t + 3.0 * c[1]) * t + 2.0 * c[2]) * t + c[3];
+            if df == 0.0 {
+                break;
+            }
+            t -= f / df;
+        }
+        *root = t;
+    }
+
+    roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    roots
+}
+
+#[test]
+fn quadratic_roots() {
+    let (t0, t1) = solve_quadratic(1.0, -3.0, 2.0).unwrap();
+    assert!((t0 - 1.0).abs() < 1e-12);
+    assert!((t1 - 2.0).abs() < 1e-12);
+    assert!(solve_quadratic(1.0, 0.0, 1.0).is_none());
+}
+
+#[test]
+fn quartic_roots() {
+    // (t - 1)(t - 2)(t - 3)(t - 4)
+    let roots = solve_quartic([1.0, -10.0, 35.0, -50.0, 24.0]);
+    assert_eq!(roots.len(), 4);
+    for (root, expected) in roots.iter().zip([1.0, 2.0, 3.0, 4.0]) {
+        assert!((root - expected).abs() < 1e-9, "{root} != {expected}");
+    }
+
+    // t^4 + 1 has no real roots
+    assert!(solve_quartic([1.0, 0.0, 0.0, 0.0, 1.0]).is_empty());
+}
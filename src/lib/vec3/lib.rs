@@ -9,6 +9,15 @@ use std::fmt;
 use rand::distributions::{Distribution, Uniform};
 use std::ops::{Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
 
+mod animated;
+mod mat4;
+mod onb;
+mod quaternion;
+pub use animated::AnimatedTransform;
+pub use mat4::{Mat4, Transform};
+pub use onb::Onb;
+pub use quaternion::Quaternion;
+
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct Vec3<T: Float> {
     pub x: T,
@@ -91,6 +100,15 @@ where
         }
     }
 
+    #[inline]
+    pub fn cross_product(u: &Vec3<T>, v: &Vec3<T>) -> Vec3<T> {
+        Vec3 {
+            x: u[1] * v[2] - u[2] * v[1],
+            y: u[2] * v[0] - u[0] * v[2],
+            z: u[0] * v[1] - u[1] * v[0],
+        }
+    }
+
     #[inline]
     pub fn unit_vector(self) -> Vec3<T> {
         return self / self.length();
@@ -118,6 +136,20 @@ where
         println!("{ir} {ig} {ib}")
     }
 
+    pub fn write_color_srgb(&self, samples_per_pixel: i64) {
+        // Average the samples and encode the linear result for display
+        let scale = 1.0 / samples_per_pixel as f64;
+        let r = linear_to_srgb(T::to_f64(&self.x).unwrap() * scale);
+        let g = linear_to_srgb(T::to_f64(&self.y).unwrap() * scale);
+        let b = linear_to_srgb(T::to_f64(&self.z).unwrap() * scale);
+
+        let ir = (256.0 * clamp(r, 0.0, 0.999)) as i32;
+        let ig = (256.0 * clamp(g, 0.0, 0.999)) as i32;
+        let ib = (256.0 * clamp(b, 0.0, 0.999)) as i32;
+
+        println!("{ir} {ig} {ib}")
+    }
+
     pub fn sample_square() -> Vec3<T> {
         // Returns the vector to a random point in the [(-0.5,-0.5), (0.5, 0.5)]
         // unit square.
@@ -162,6 +194,34 @@ where
         }
         return point;
     }
+
+    pub fn random_unit_vector() -> Self {
+        Self::random_in_unit_sphere().unit_vector()
+    }
+
+    pub fn near_zero(&self) -> bool {
+        // Return true if the vector is close to zero in all dimensions.
+        let s = T::from(1e-8).unwrap();
+        self.x.abs() < s && self.y.abs() < s && self.z.abs() < s
+    }
+}
+
+/// Applies the sRGB transfer curve to a linear intensity.
+pub fn linear_to_srgb(x: f64) -> f64 {
+    if x <= 0.0031308 {
+        12.92 * x.max(0.0)
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Inverts the sRGB transfer curve, giving linear intensity.
+pub fn srgb_to_linear(x: f64) -> f64 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
 }
 
 impl Vec3<f32> {
@@ -523,3 +583,11 @@ fn length() {
     assert_eq!(x.length(), y);
     assert_eq!(Vec3::length(x), y);
 }
+
+#[test]
+fn srgb_round_trip() {
+    for x in [0.0, 0.002, 0.2, 0.5, 1.0] {
+        assert!((srgb_to_linear(linear_to_srgb(x)) - x).abs() < 1e-12);
+    }
+    assert!((linear_to_srgb(0.5) - 0.735).abs() < 1e-3);
+}
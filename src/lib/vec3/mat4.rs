@@ -0,0 +1,309 @@
+use std::ops::Mul;
+
+use crate::Vec3;
+
+/// A 4x4 row-major matrix acting on column vectors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Mat4::IDENTITY
+    }
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4 {
+        m: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    pub fn new(m: [[f64; 4]; 4]) -> Self {
+        Mat4 { m }
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut result = Mat4::IDENTITY;
+        for i in 0..4 {
+            for j in 0..4 {
+                result.m[i][j] = self.m[j][i];
+            }
+        }
+        result
+    }
+
+    /// Inverts the matrix with Gauss-Jordan elimination, returning `None` if it
+    /// is singular.
+    pub fn inverse(&self) -> Option<Mat4> {
+        let mut a = self.m;
+        let mut inv = Mat4::IDENTITY.m;
+
+        for col in 0..4 {
+            // Partial pivoting on the largest remaining entry of the column
+            let pivot = (col..4)
+                .max_by(|&i, &j| a[i][col].abs().partial_cmp(&a[j][col].abs()).unwrap())
+                .unwrap();
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= scale;
+                inv[col][j] *= scale;
+            }
+
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let factor = a[row][col];
+                for j in 0..4 {
+                    a[row][j] -= factor * a[col][j];
+                    inv[row][j] -= factor * inv[col][j];
+                }
+            }
+        }
+
+        Some(Mat4 { m: inv })
+    }
+
+    /// Applies the matrix to a point, including translation.
+    pub fn transform_point(&self, p: &Vec3<f64>) -> Vec3<f64> {
+        let m = &self.m;
+        let x = m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3];
+        let y = m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3];
+        let z = m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3];
+        let w = m[3][0] * p.x + m[3][1] * p.y + m[3][2] * p.z + m[3][3];
+        if w == 1.0 {
+            Vec3 { x, y, z }
+        } else {
+            Vec3 { x, y, z } / w
+        }
+    }
+
+    /// Applies the matrix to a direction, ignoring translation.
+    pub fn transform_vector(&self, v: &Vec3<f64>) -> Vec3<f64> {
+        let m = &self.m;
+        Vec3 {
+            x: m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            y: m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            z: m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        }
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut result = Mat4 { m: [[0.0; 4]; 4] };
+        for i in 0..4 {
+            for j in 0..4 {
+                for k in 0..4 {
+                    result.m[i][j] += self.m[i][k] * other.m[k][j];
+                }
+            }
+        }
+        result
+    }
+}
+
+/// An affine transform stored together with its inverse so points, vectors
+/// and normals can be moved in either direction without re-inverting.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Transform {
+    m: Mat4,
+    m_inv: Mat4,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        m: Mat4::IDENTITY,
+        m_inv: Mat4::IDENTITY,
+    };
+
+    /// Panics if `m` is singular.
+    pub fn new(m: Mat4) -> Self {
+        let m_inv = m
+            .inverse()
+            .expect("Transform matrix must be invertible.");
+        Transform { m, m_inv }
+    }
+
+    pub fn translate(delta: Vec3<f64>) -> Self {
+        let m = Mat4::new([
+            [1.0, 0.0, 0.0, delta.x],
+            [0.0, 1.0, 0.0, delta.y],
+            [0.0, 0.0, 1.0, delta.z],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        let m_inv = Mat4::new([
+            [1.0, 0.0, 0.0, -delta.x],
+            [0.0, 1.0, 0.0, -delta.y],
+            [0.0, 0.0, 1.0, -delta.z],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        Transform { m, m_inv }
+    }
+
+    pub fn scale(factors: Vec3<f64>) -> Self {
+        let m = Mat4::new([
+            [factors.x, 0.0, 0.0, 0.0],
+            [0.0, factors.y, 0.0, 0.0],
+            [0.0, 0.0, factors.z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        let m_inv = Mat4::new([
+            [1.0 / factors.x, 0.0, 0.0, 0.0],
+            [0.0, 1.0 / factors.y, 0.0, 0.0],
+            [0.0, 0.0, 1.0 / factors.z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        Transform { m, m_inv }
+    }
+
+    /// Rotation of `degrees` counter-clockwise around `axis`.
+    pub fn rotate(degrees: f64, axis: Vec3<f64>) -> Self {
+        let a = axis.unit_vector();
+        let (sin_theta, cos_theta) = degrees.to_radians().sin_cos();
+        let one_minus_cos = 1.0 - cos_theta;
+
+        let m = Mat4::new([
+            [
+                a.x * a.x * one_minus_cos + cos_theta,
+                a.x * a.y * one_minus_cos - a.z * sin_theta,
+                a.x * a.z * one_minus_cos + a.y * sin_theta,
+                0.0,
+            ],
+            [
+                a.y * a.x * one_minus_cos + a.z * sin_theta,
+                a.y * a.y * one_minus_cos + cos_theta,
+                a.y * a.z * one_minus_cos - a.x * sin_theta,
+                0.0,
+            ],
+            [
+                a.z * a.x * one_minus_cos - a.y * sin_theta,
+                a.z * a.y * one_minus_cos + a.x * sin_theta,
+                a.z * a.z * one_minus_cos + cos_theta,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        // Rotations are orthonormal so the inverse is the transpose
+        Transform {
+            m,
+            m_inv: m.transpose(),
+        }
+    }
+
+    pub fn rotate_x(degrees: f64) -> Self {
+        Self::rotate(degrees, Vec3::new(1.0, 0.0, 0.0))
+    }
+
+    pub fn rotate_y(degrees: f64) -> Self {
+        Self::rotate(degrees, Vec3::new(0.0, 1.0, 0.0))
+    }
+
+    pub fn rotate_z(degrees: f64) -> Self {
+        Self::rotate(degrees, Vec3::new(0.0, 0.0, 1.0))
+    }
+
+    pub fn matrix(&self) -> &Mat4 {
+        &self.m
+    }
+
+    pub fn inverse_matrix(&self) -> &Mat4 {
+        &self.m_inv
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            m: self.m_inv,
+            m_inv: self.m,
+        }
+    }
+
+    pub fn transpose(&self) -> Transform {
+        Transform {
+            m: self.m.transpose(),
+            m_inv: self.m_inv.transpose(),
+        }
+    }
+
+    pub fn point(&self, p: &Vec3<f64>) -> Vec3<f64> {
+        self.m.transform_point(p)
+    }
+
+    pub fn vector(&self, v: &Vec3<f64>) -> Vec3<f64> {
+        self.m.transform_vector(v)
+    }
+
+    /// Normals stay perpendicular to the surface by transforming with the
+    /// inverse transpose. The result is not renormalized.
+    pub fn normal(&self, n: &Vec3<f64>) -> Vec3<f64> {
+        self.m_inv.transpose().transform_vector(n)
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    /// Composes two transforms so that `a * b` applies `b` first.
+    fn mul(self, other: Transform) -> Transform {
+        Transform {
+            m: self.m * other.m,
+            m_inv: other.m_inv * self.m_inv,
+        }
+    }
+}
+
+#[cfg(test)]
+fn assert_vec_near(a: Vec3<f64>, b: Vec3<f64>) {
+    assert!((a - b).length() < 1e-9, "{a} != {b}");
+}
+
+#[test]
+fn matrix_inverse() {
+    let m = Mat4::new([
+        [2.0, 0.0, 1.0, 3.0],
+        [1.0, 3.0, 0.0, -1.0],
+        [0.0, 1.0, 4.0, 2.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+    let product = m * m.inverse().unwrap();
+    for i in 0..4 {
+        for j in 0..4 {
+            let expected = if i == j { 1.0 } else { 0.0 };
+            assert!((product.m[i][j] - expected).abs() < 1e-12);
+        }
+    }
+
+    let singular = Mat4::new([[1.0, 2.0, 3.0, 4.0], [2.0, 4.0, 6.0, 8.0], [0.0; 4], [0.0; 4]]);
+    assert!(singular.inverse().is_none());
+}
+
+#[test]
+fn points_vectors_and_normals() {
+    let t = Transform::translate(Vec3::new(1.0, 2.0, 3.0)) * Transform::rotate_z(90.0);
+    let x = Vec3::new(1.0, 0.0, 0.0);
+
+    // Points pick up the translation, vectors do not
+    assert_vec_near(t.point(&x), Vec3::new(1.0, 3.0, 3.0));
+    assert_vec_near(t.vector(&x), Vec3::new(0.0, 1.0, 0.0));
+    assert_vec_near(t.inverse().point(&t.point(&x)), x);
+
+    // A non-uniform scale must keep normals perpendicular to tangents
+    let s = Transform::scale(Vec3::new(2.0, 1.0, 1.0));
+    let tangent = Vec3::new(1.0, -1.0, 0.0);
+    let normal = Vec3::new(1.0, 1.0, 0.0);
+    assert!(s.vector(&tangent).dot(&s.normal(&normal)).abs() < 1e-12);
+}
@@ -0,0 +1,64 @@
+use crate::Vec3;
+
+/// An orthonormal basis with `w` along a given direction, for working in a
+/// frame where that direction is the z axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Onb {
+    axis: [Vec3<f64>; 3],
+}
+
+impl Onb {
+    /// A basis whose `w` is `n` normalized. The other two axes are arbitrary
+    /// but chosen without dividing by anything small.
+    pub fn new(n: &Vec3<f64>) -> Self {
+        let w = n.unit_vector();
+        let a = if w.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = Vec3::cross_product(&w, &a).unit_vector();
+        let u = Vec3::cross_product(&v, &w);
+        Onb { axis: [u, v, w] }
+    }
+
+    pub fn u(&self) -> Vec3<f64> {
+        self.axis[0]
+    }
+
+    pub fn v(&self) -> Vec3<f64> {
+        self.axis[1]
+    }
+
+    pub fn w(&self) -> Vec3<f64> {
+        self.axis[2]
+    }
+
+    /// Takes coordinates in this basis to the frame the basis is given in.
+    pub fn transform(&self, local: &Vec3<f64>) -> Vec3<f64> {
+        self.axis[0] * local.x + self.axis[1] * local.y + self.axis[2] * local.z
+    }
+
+    /// Expresses `v` in this basis.
+    pub fn to_local(&self, v: &Vec3<f64>) -> Vec3<f64> {
+        Vec3::new(v.dot(&self.axis[0]), v.dot(&self.axis[1]), v.dot(&self.axis[2]))
+    }
+}
+
+#[test]
+fn onb_is_right_handed_and_orthonormal() {
+    for n in [
+        Vec3::new(0.0, 0.0, 1.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(-0.3, 2.0, 0.7),
+    ] {
+        let onb = Onb::new(&n);
+        assert!((onb.w() - n.unit_vector()).near_zero());
+        assert!(onb.u().dot(&onb.v()).abs() < 1e-12);
+        assert!((onb.u().length() - 1.0).abs() < 1e-12);
+        assert!((Vec3::cross_product(&onb.u(), &onb.v()) - onb.w()).near_zero());
+
+        let v = Vec3::new(0.2, -0.5, 0.9);
+        assert!((onb.to_local(&onb.transform(&v)) - v).near_zero());
+    }
+}
@@ -0,0 +1,145 @@
+use std::ops::{Add, Mul, Neg, Sub};
+
+use crate::{Mat4, Vec3};
+
+/// A rotation stored as a unit quaternion `w + v`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub v: Vec3<f64>,
+    pub w: f64,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Quaternion {
+            v: Vec3::zero(),
+            w: 1.0,
+        }
+    }
+}
+
+impl Quaternion {
+    pub fn dot(&self, other: &Quaternion) -> f64 {
+        self.v.dot(&other.v) + self.w * other.w
+    }
+
+    pub fn normalize(self) -> Quaternion {
+        self * (1.0 / self.dot(&self).sqrt())
+    }
+
+    /// Extracts the rotation from the upper 3x3 of `m`, which must be
+    /// orthonormal.
+    pub fn from_matrix(m: &Mat4) -> Quaternion {
+        let m = &m.m;
+        let trace = m[0][0] + m[1][1] + m[2][2];
+        if trace > 0.0 {
+            let s = (trace + 1.0).sqrt();
+            let w = s / 2.0;
+            let s = 0.5 / s;
+            Quaternion {
+                v: Vec3::new(
+                    (m[2][1] - m[1][2]) * s,
+                    (m[0][2] - m[2][0]) * s,
+                    (m[1][0] - m[0][1]) * s,
+                ),
+                w,
+            }
+        } else {
+            // Pivot on the largest diagonal entry to stay well conditioned
+            let i = if m[1][1] > m[0][0] { 1 } else { 0 };
+            let i = if m[2][2] > m[i][i] { 2 } else { i };
+            let j = (i + 1) % 3;
+            let k = (j + 1) % 3;
+
+            let s = ((m[i][i] - (m[j][j] + m[k][k])) + 1.0).sqrt();
+            let mut q = [0.0; 3];
+            q[i] = s * 0.5;
+            let s = if s != 0.0 { 0.5 / s } else { s };
+            let w = (m[k][j] - m[j][k]) * s;
+            q[j] = (m[j][i] + m[i][j]) * s;
+            q[k] = (m[k][i] + m[i][k]) * s;
+            Quaternion {
+                v: Vec3::new(q[0], q[1], q[2]),
+                w,
+            }
+        }
+    }
+
+    pub fn to_matrix(&self) -> Mat4 {
+        let (x, y, z, w) = (self.v.x, self.v.y, self.v.z, self.w);
+        let (xx, yy, zz) = (x * x, y * y, z * z);
+        let (xy, xz, yz) = (x * y, x * z, y * z);
+        let (wx, wy, wz) = (x * w, y * w, z * w);
+
+        Mat4::new([
+            [1.0 - 2.0 * (yy + zz), 2.0 * (xy - wz), 2.0 * (xz + wy), 0.0],
+            [2.0 * (xy + wz), 1.0 - 2.0 * (xx + zz), 2.0 * (yz - wx), 0.0],
+            [2.0 * (xz - wy), 2.0 * (yz + wx), 1.0 - 2.0 * (xx + yy), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Spherical linear interpolation, taking the shorter way around.
+    pub fn slerp(t: f64, q1: &Quaternion, q2: &Quaternion) -> Quaternion {
+        let mut q2 = *q2;
+        let mut cos_theta = q1.dot(&q2);
+        if cos_theta < 0.0 {
+            q2 = -q2;
+            cos_theta = -cos_theta;
+        }
+
+        if cos_theta > 0.9995 {
+            // Nearly parallel, fall back to a normalized lerp
+            (*q1 * (1.0 - t) + q2 * t).normalize()
+        } else {
+            let theta = cos_theta.clamp(-1.0, 1.0).acos();
+            let theta_p = theta * t;
+            let q_perp = (q2 - *q1 * cos_theta).normalize();
+            *q1 * theta_p.cos() + q_perp * theta_p.sin()
+        }
+    }
+}
+
+impl Add for Quaternion {
+    type Output = Quaternion;
+
+    fn add(self, other: Quaternion) -> Quaternion {
+        Quaternion {
+            v: self.v + other.v,
+            w: self.w + other.w,
+        }
+    }
+}
+
+impl Sub for Quaternion {
+    type Output = Quaternion;
+
+    fn sub(self, other: Quaternion) -> Quaternion {
+        Quaternion {
+            v: self.v - other.v,
+            w: self.w - other.w,
+        }
+    }
+}
+
+impl Mul<f64> for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, s: f64) -> Quaternion {
+        Quaternion {
+            v: self.v * s,
+            w: self.w * s,
+        }
+    }
+}
+
+impl Neg for Quaternion {
+    type Output = Quaternion;
+
+    fn neg(self) -> Quaternion {
+        Quaternion {
+            v: -self.v,
+            w: -self.w,
+        }
+    }
+}